@@ -61,6 +61,15 @@ fn db_bench(c: &mut Criterion) {
         });
     });
 
+    c.bench_function("par_map", |b| {
+        b.iter(|| {
+            db.get_relation_mut("Students").unwrap().par_map_column("grade", |datum| match datum {
+                DataType::Float(grade) => DataType::Float(grade.sqrt().powi(2)),
+                other => other.clone(),
+            }).unwrap();
+        });
+    });
+
     c.bench_function("idx", |b| {
         b.iter(|| {
             db.create_index("Students", "id").unwrap();
@@ -1,8 +1,11 @@
 use crate::interface::*;
 use crate::errors::*;
 use crate::dtype::*;
+use crate::relation::compare_with;
 
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Main DBMS structure 
 pub struct Database {
@@ -11,39 +14,497 @@ pub struct Database {
 
     /// name of the database
     name: String,
+
+    /// materialized views by name, their data is stored in `relations`
+    views: HashMap<String, MaterializedView>,
+
+    /// joins fail once their result would exceed this many rows
+    max_result_rows: Option<usize>,
+
+    /// aggregates over a relation that are kept up to date while rows are added
+    live_aggregates: HashMap<String, LiveAggregate>,
+
+    /// once the resident relations exceed this many bytes, the least recently used ones are evicted to disk
+    memory_budget: Option<usize>,
+
+    /// evicted relations by name and the file holding them
+    evicted: HashMap<String, PathBuf>,
+
+    /// directory of the evicted relations, created by the first eviction
+    spill_dir: Option<tempfile::TempDir>,
+
+    /// logical time of the last access per relation
+    last_access: RefCell<HashMap<String, u64>>,
+
+    /// source of the access times
+    access_clock: Cell<u64>,
+}
+
+/// filter-based view over a source relation that is refreshed incrementally
+struct MaterializedView {
+    /// name of the relation the view is derived from
+    source: String,
+    /// column the filter is evaluated on
+    column: String,
+    /// filter applied to every source row
+    predicate: Box<dyn Fn(&DataType) -> bool>,
+    /// number of source rows already processed by the view
+    watermark: usize,
+}
+
+/// materialized aggregate over a column of a source relation
+struct LiveAggregate {
+    /// name of the relation the aggregate is computed on
+    source: String,
+    /// running state over the rows processed so far
+    aggregator: Aggregator,
+    /// number of source rows already added to the aggregator
+    watermark: usize,
 }
 
 
 // #################################### OPTIONAL
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+enum SqlValue {
+    /// literal as written in the query, an unquoted `NULL` stands for a null value
+    Literal(String),
+    /// quoted string literal without its quotes
+    Quoted(String),
+    /// `?` placeholder that is bound before execution
+    Placeholder,
+    /// value bound to a placeholder
+    Bound(DataType),
+}
+
+/// comparison of a column with a value in a WHERE clause
+#[derive(Debug, Clone)]
+struct SqlCondition {
+    column: String,
+    op: CompareOp,
+    value: SqlValue,
+}
+
+#[derive(Debug, Clone)]
 enum SqlCommand {
     Select {
         columns: Vec<String>,
         table: String,
-        where_clause: Option<(String, String)>,  // (column, value)
+        /// conditions joined by OR, each of them a list of conditions joined by AND, empty without WHERE
+        where_clause: Vec<Vec<SqlCondition>>,
+        limit: Option<usize>,
+        offset: usize,
+    },
+    Insert {
+        table: String,
+        /// columns the values are given for, all columns in schema order without a column list
+        columns: Option<Vec<String>>,
+        rows: Vec<Vec<SqlValue>>,
+    },
+    Update {
+        table: String,
+        assignments: Vec<(String, SqlValue)>,
+        where_clause: Vec<Vec<SqlCondition>>,
+    },
+    Delete {
+        table: String,
+        where_clause: Vec<Vec<SqlCondition>>,
+    },
+    CreateTable {
+        table: String,
+        /// column names with their declared type, see `fields`
+        columns: Vec<(String, DataType)>,
     },
+    DropTable {
+        table: String,
+    },
+}
+
+impl SqlCommand {
+    /// values of the command in order of appearance
+    fn values_mut(&mut self) -> Vec<&mut SqlValue> {
+        match self {
+            SqlCommand::Select { where_clause, .. } | SqlCommand::Delete { where_clause, .. } => {
+                where_clause.iter_mut().flatten().map(|condition| &mut condition.value).collect()
+            },
+            SqlCommand::Insert { rows, .. } => rows.iter_mut().flatten().collect(),
+            SqlCommand::Update { assignments, where_clause, .. } => {
+                assignments.iter_mut().map(|(_, value)| value)
+                    .chain(where_clause.iter_mut().flatten().map(|condition| &mut condition.value))
+                    .collect()
+            },
+            SqlCommand::CreateTable { .. } | SqlCommand::DropTable { .. } => Vec::new(),
+        }
+    }
+
+    /// number of `?` placeholders in the command
+    fn num_placeholders(&self) -> usize {
+        self.clone().values_mut().into_iter().filter(|value| matches!(value, SqlValue::Placeholder)).count()
+    }
+
+    /// replaces the placeholders in order of appearance with the given values
+    fn bind(&self, values: &[DataType]) -> SqlCommand {
+        let mut values = values.iter();
+        let mut command = self.clone();
+        for value in command.values_mut() {
+            if let SqlValue::Placeholder = value {
+                if let Some(bound) = values.next() {
+                    *value = SqlValue::Bound(bound.clone());
+                }
+            }
+        }
+        command
+    }
+}
+
+/// parsed query with `?` placeholders that can be executed repeatedly with different bindings
+pub struct PreparedStatement {
+    command: SqlCommand,
+    bindings: Vec<DataType>,
+}
+
+impl PreparedStatement {
+    /// binds the next placeholder to the given value
+    pub fn bind(&mut self, value: DataType) -> &mut Self {
+        self.bindings.push(value);
+        self
+    }
+
+    /// removes all bound values so the statement can be bound again
+    pub fn clear_bindings(&mut self) -> &mut Self {
+        self.bindings.clear();
+        self
+    }
+
+    /// executes the statement on the given database, every placeholder has to be bound
+    pub fn execute(&self, db: &mut Database) -> Result<ColumnStoreRelation, String> {
+        let expected = self.command.num_placeholders();
+        if self.bindings.len() != expected {
+            return Err(format!("Expected {} bound values but got {}", expected, self.bindings.len()));
+        }
+        db.run_command(self.command.bind(&self.bindings))
+    }
+}
+
+/// result of a single SQL statement
+pub type QueryResult = ColumnStoreRelation;
+
+/// splits a script into its semicolon-separated statements, `--` comments and empty statements are dropped
+///
+/// `;` and `--` inside quoted string literals are kept, a doubled quote toggles the literal twice and so stays inside it.
+fn split_statements(script: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut statement = String::new();
+    let mut in_literal = false;
+    let mut chars = script.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_literal = !in_literal;
+                statement.push(c);
+            },
+            ';' if !in_literal => statements.push(std::mem::take(&mut statement)),
+            '-' if !in_literal && chars.peek() == Some(&'-') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            },
+            _ => statement.push(c),
+        }
+    }
+    statements.push(statement);
+    statements.into_iter()
+        .map(|statement| statement.trim().to_string())
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+/// splits a query into words, comparison operators, parentheses, commas and quoted string literals
+///
+/// Operators and punctuation need no surrounding whitespace (`age>=30`, `(1,'a')`). Quoted literals keep their quotes so they
+/// can be told apart from keywords and placeholders, a doubled quote inside them stands for one quote.
+fn tokenize_sql(query: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' {
+            chars.next();
+            let mut literal = String::from("'");
+            loop {
+                match chars.next() {
+                    Some('\'') if chars.peek() == Some(&'\'') => {
+                        chars.next();
+                        literal.push('\'');
+                    },
+                    Some('\'') => break,
+                    Some(c) => literal.push(c),
+                    None => return Err("Unterminated string literal".to_string()),
+                }
+            }
+            literal.push('\'');
+            tokens.push(literal);
+        } else if "(),".contains(c) {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if "=!<>".contains(c) {
+            let mut operator = String::new();
+            while let Some(&c) = chars.peek().filter(|c| "=!<>".contains(**c)) {
+                operator.push(c);
+                chars.next();
+            }
+            tokens.push(operator);
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace() && !"=!<>'(),".contains(**c)) {
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        }
+    }
+    Ok(tokens)
+}
+
+/// comparison operator of a WHERE clause, `<>` is an alias of `!=`
+fn parse_compare_op(token: &str) -> Option<CompareOp> {
+    match token {
+        "=" => Some(CompareOp::Eq),
+        "!=" | "<>" => Some(CompareOp::Ne),
+        "<" => Some(CompareOp::Lt),
+        "<=" => Some(CompareOp::Le),
+        ">" => Some(CompareOp::Gt),
+        ">=" => Some(CompareOp::Ge),
+        _ => None,
+    }
+}
+
+/// parses a comparison like `price <= 2.5` from the front of `tokens`, a missing operator means equality
+fn parse_condition(tokens: &mut Vec<String>) -> Result<SqlCondition, String> {
+    if tokens.len() < 2 {
+        return Err("Invalid WHERE clause".to_string());
+    }
+    let column = tokens.remove(0);
+    let op = match parse_compare_op(&tokens[0]) {
+        Some(op) => {
+            tokens.remove(0);
+            op
+        },
+        None if tokens[0].chars().all(|c| "=!<>".contains(c)) => {
+            return Err(format!("Unknown operator {}", tokens[0]));
+        },
+        None => CompareOp::Eq,
+    };
+    if tokens.is_empty() {
+        return Err("Invalid WHERE clause".to_string());
+    }
+    let value = parse_value(tokens.remove(0));
+    Ok(SqlCondition { column, op, value })
+}
+
+/// value token of a query, either a quoted string, a `?` placeholder or a bare literal
+fn parse_value(token: String) -> SqlValue {
+    match token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        Some(quoted) => SqlValue::Quoted(quoted.to_string()),
+        None if token == "?" => SqlValue::Placeholder,
+        None => SqlValue::Literal(token),
+    }
+}
+
+/// removes `keyword` from the front of `tokens`, ignoring case
+fn expect_keyword(tokens: &mut Vec<String>, keyword: &str) -> Result<(), String> {
+    match tokens.first() {
+        Some(token) if token.eq_ignore_ascii_case(keyword) => {
+            tokens.remove(0);
+            Ok(())
+        },
+        _ => Err(format!("Expected {}", keyword)),
+    }
+}
+
+/// removes a name like a table or column name from the front of `tokens`
+fn expect_name(tokens: &mut Vec<String>, what: &str) -> Result<String, String> {
+    match tokens.first() {
+        Some(token) if !token.starts_with('\'') && !"(),".contains(token.as_str()) => Ok(tokens.remove(0)),
+        _ => Err(format!("Expected {}", what)),
+    }
+}
+
+/// parses a parenthesized, comma-separated list like `(id, 'a', ?)` from the front of `tokens`
+fn parse_list(tokens: &mut Vec<String>) -> Result<Vec<String>, String> {
+    expect_keyword(tokens, "(")?;
+    let mut items = Vec::new();
+    loop {
+        match tokens.first().map(String::as_str) {
+            None | Some("(") | Some(",") | Some(")") => return Err("Expected a list item".to_string()),
+            Some(_) => items.push(tokens.remove(0)),
+        }
+        match tokens.first().map(String::as_str) {
+            Some(",") => { tokens.remove(0); },
+            Some(")") => {
+                tokens.remove(0);
+                return Ok(items);
+            },
+            _ => return Err("Expected , or ) in list".to_string()),
+        }
+    }
+}
+
+/// parses the conditions following WHERE, AND binds tighter than OR so every OR starts a new group
+fn parse_where(tokens: &mut Vec<String>) -> Result<Vec<Vec<SqlCondition>>, String> {
+    let mut where_clause = vec![vec![parse_condition(tokens)?]];
+    while let Some(connective) = tokens.first().map(|token| token.to_uppercase()).filter(|t| t == "AND" || t == "OR") {
+        tokens.remove(0);
+        let condition = parse_condition(tokens)
+            .map_err(|_| format!("Expected a condition after {}", connective))?;
+        if connective == "OR" {
+            where_clause.push(Vec::new());
+        }
+        where_clause.last_mut().unwrap().push(condition);
+    }
+    Ok(where_clause)
+}
+
+/// parses an optional trailing WHERE clause, nothing may follow it
+fn parse_optional_where(tokens: &mut Vec<String>) -> Result<Vec<Vec<SqlCondition>>, String> {
+    let where_clause = if tokens.is_empty() {
+        Vec::new()
+    } else {
+        expect_keyword(tokens, "WHERE")?;
+        parse_where(tokens)?
+    };
+    if !tokens.is_empty() {
+        return Err("Unexpected token in query".to_string());
+    }
+    Ok(where_clause)
+}
+
+/// `INSERT INTO table [(columns)] VALUES (values)[, (values)]...`
+fn parse_insert(tokens: &mut Vec<String>) -> Result<SqlCommand, String> {
+    expect_keyword(tokens, "INTO")?;
+    let table = expect_name(tokens, "table name")?;
+    let columns = if tokens.first().is_some_and(|token| token == "(") {
+        Some(parse_list(tokens)?)
+    } else {
+        None
+    };
+    expect_keyword(tokens, "VALUES")?;
+    let mut rows = vec![parse_list(tokens)?.into_iter().map(parse_value).collect()];
+    while tokens.first().is_some_and(|token| token == ",") {
+        tokens.remove(0);
+        rows.push(parse_list(tokens)?.into_iter().map(parse_value).collect());
+    }
+    if !tokens.is_empty() {
+        return Err("Unexpected token in query".to_string());
+    }
+    Ok(SqlCommand::Insert { table, columns, rows })
+}
+
+/// `UPDATE table SET column = value[, column = value]... [WHERE conditions]`
+fn parse_update(tokens: &mut Vec<String>) -> Result<SqlCommand, String> {
+    let table = expect_name(tokens, "table name")?;
+    expect_keyword(tokens, "SET")?;
+    let mut assignments = Vec::new();
+    loop {
+        let column = expect_name(tokens, "column name")?;
+        expect_keyword(tokens, "=")?;
+        if tokens.is_empty() {
+            return Err(format!("Expected a value for {}", column));
+        }
+        assignments.push((column, parse_value(tokens.remove(0))));
+        if tokens.first().is_some_and(|token| token == ",") {
+            tokens.remove(0);
+        } else {
+            break;
+        }
+    }
+    let where_clause = parse_optional_where(tokens)?;
+    Ok(SqlCommand::Update { table, assignments, where_clause })
+}
+
+/// `DELETE FROM table [WHERE conditions]`
+fn parse_delete(tokens: &mut Vec<String>) -> Result<SqlCommand, String> {
+    expect_keyword(tokens, "FROM")?;
+    let table = expect_name(tokens, "table name")?;
+    let where_clause = parse_optional_where(tokens)?;
+    Ok(SqlCommand::Delete { table, where_clause })
 }
 
 fn parse_sql(query: &str) -> Result<SqlCommand, String> {
-    let mut tokens = query.split_whitespace().collect::<Vec<&str>>();
+    let mut tokens = tokenize_sql(query)?;
 
     if tokens.is_empty() {
         return Err("Empty query".to_string());
     }
 
-    if tokens[0].to_uppercase() != "SELECT" {
-        return Err("Only SELECT queries are supported".to_string());
+    match tokens.remove(0).to_uppercase().as_str() {
+        "SELECT" => parse_select(&mut tokens),
+        "INSERT" => parse_insert(&mut tokens),
+        "UPDATE" => parse_update(&mut tokens),
+        "DELETE" => parse_delete(&mut tokens),
+        "CREATE" => parse_create_table(&mut tokens),
+        "DROP" => parse_drop_table(&mut tokens),
+        statement => Err(format!("Unsupported statement {}", statement)),
     }
+}
 
-    tokens.remove(0); // Remove "SELECT"
+/// column type of CREATE TABLE as the value `fields` declares it with
+fn parse_column_type(name: &str) -> Option<DataType> {
+    match name.to_uppercase().as_str() {
+        "INT" | "INTEGER" => Some(DataType::Int(0)),
+        "FLOAT" => Some(DataType::Float(0.0)),
+        "STRING" => Some(DataType::String(String::new())),
+        "BOOL" | "BOOLEAN" => Some(DataType::Bool(false)),
+        _ => None,
+    }
+}
 
+/// `CREATE TABLE table (column TYPE[, column TYPE]...)` with the types INT, FLOAT, STRING and BOOL
+fn parse_create_table(tokens: &mut Vec<String>) -> Result<SqlCommand, String> {
+    expect_keyword(tokens, "TABLE")?;
+    let table = expect_name(tokens, "table name")?;
+    expect_keyword(tokens, "(")?;
+    let mut columns: Vec<(String, DataType)> = Vec::new();
+    loop {
+        let column = expect_name(tokens, "column name")?;
+        let type_name = expect_name(tokens, &format!("a type for {}", column))?;
+        let field = parse_column_type(&type_name).ok_or_else(|| format!("Unknown type {}", type_name))?;
+        if columns.iter().any(|(name, _)| *name == column) {
+            return Err(format!("Column {} is declared more than once", column));
+        }
+        columns.push((column, field));
+        match tokens.first().map(String::as_str) {
+            Some(",") => { tokens.remove(0); },
+            Some(")") => {
+                tokens.remove(0);
+                break;
+            },
+            _ => return Err("Expected , or ) in column list".to_string()),
+        }
+    }
+    if !tokens.is_empty() {
+        return Err("Unexpected token in query".to_string());
+    }
+    Ok(SqlCommand::CreateTable { table, columns })
+}
+
+/// `DROP TABLE table`
+fn parse_drop_table(tokens: &mut Vec<String>) -> Result<SqlCommand, String> {
+    expect_keyword(tokens, "TABLE")?;
+    let table = expect_name(tokens, "table name")?;
+    if !tokens.is_empty() {
+        return Err("Unexpected token in query".to_string());
+    }
+    Ok(SqlCommand::DropTable { table })
+}
+
+/// `SELECT columns FROM table [WHERE conditions] [LIMIT n] [OFFSET n]`
+fn parse_select(tokens: &mut Vec<String>) -> Result<SqlCommand, String> {
     let mut columns = Vec::new();
     while !tokens.is_empty() {
         let token = tokens.remove(0);
         if token.to_uppercase() == "FROM" {
             break;
         }
-        columns.push(token.trim_end_matches(',').to_string());
+        columns.extend(token.split(',').filter(|column| !column.is_empty()).map(str::to_string));
     }
 
     if columns.is_empty() {
@@ -54,19 +515,26 @@ fn parse_sql(query: &str) -> Result<SqlCommand, String> {
         return Err("Expected table name".to_string());
     }
 
-    let table = tokens.remove(0).to_string();
+    let table = tokens.remove(0);
 
-    let mut where_clause = None;
+    let mut where_clause: Vec<Vec<SqlCondition>> = Vec::new();
+    let mut limit = None;
+    let mut offset = 0;
 
     while !tokens.is_empty() {
         match tokens.remove(0).to_uppercase().as_str() {
-            "WHERE" => {
-                if tokens.len() < 2 {
-                    return Err("Invalid WHERE clause".to_string());
+            "WHERE" if where_clause.is_empty() => where_clause = parse_where(tokens)?,
+            keyword @ ("LIMIT" | "OFFSET") => {
+                let count = match tokens.first().map(|token| token.parse::<usize>()) {
+                    Some(Ok(count)) => count,
+                    _ => return Err(format!("Expected a row count after {}", keyword)),
+                };
+                tokens.remove(0);
+                if keyword == "LIMIT" {
+                    limit = Some(count);
+                } else {
+                    offset = count;
                 }
-                let column = tokens.remove(0).to_string();
-                let value = tokens.remove(0).to_string();
-                where_clause = Some((column, value));
             }
             _ => return Err("Unexpected token in query".to_string()),
         }
@@ -76,11 +544,135 @@ fn parse_sql(query: &str) -> Result<SqlCommand, String> {
         columns,
         table,
         where_clause,
+        limit,
+        offset,
     })
 }
 
+/// converts a literal into the type of the column it is compared with or stored in, the type is the one
+/// declared in `fields` or otherwise the type of the first non-null value
+fn typed_literal(relation: &ColumnStoreRelation, column: &str, literal: &str) -> Result<DataType, String> {
+    let column_type = relation.fields.get(column).or_else(|| {
+        relation.columns.get(column).and_then(|values| values.iter().find(|value| !value.is_null()))
+    });
+    match column_type {
+        Some(DataType::String(_)) => Ok(DataType::String(literal.to_string())),
+        Some(field @ (DataType::Int(_) | DataType::Float(_))) => match DataType::from_str(literal) {
+            // an integer stored in or compared with a float column is a float
+            DataType::Int(i) if matches!(field, DataType::Float(_)) => Ok(DataType::Float(i as f64)),
+            number @ (DataType::Int(_) | DataType::Float(_)) => Ok(number),
+            _ => Err(format!("Cannot compare numeric column {} with {}", column, literal)),
+        },
+        _ => Ok(DataType::from_str(literal)),
+    }
+}
+
+/// converts a query value into the type of `column`, an unquoted `NULL` is a null value
+fn typed_value(relation: &ColumnStoreRelation, column: &str, value: &SqlValue) -> Result<DataType, String> {
+    match value {
+        SqlValue::Literal(literal) if literal.eq_ignore_ascii_case("NULL") => Ok(DataType::Null),
+        SqlValue::Literal(literal) | SqlValue::Quoted(literal) => typed_literal(relation, column, literal),
+        SqlValue::Bound(DataType::Int(i)) if matches!(relation.fields.get(column), Some(DataType::Float(_))) => {
+            Ok(DataType::Float(*i as f64))
+        },
+        SqlValue::Bound(value) => Ok(value.clone()),
+        SqlValue::Placeholder => Err("Unbound placeholder".to_string()),
+    }
+}
+
+/// fails unless `value` is null or of the type declared for `column` in `fields`
+fn check_field(relation: &ColumnStoreRelation, column: &str, value: &DataType) -> Result<(), String> {
+    match relation.fields.get(column) {
+        Some(field) if !value.is_null() && !field.same_type(value) => {
+            Err(format!("Value {} of column {} is not of type {}", value, column, field.type_name()))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// positions of the rows matching a WHERE clause in their original order, all rows without one
+///
+/// The rows of every AND group are narrowed down condition by condition and the groups are joined by OR.
+fn where_rows(relation: &ColumnStoreRelation, where_clause: &[Vec<SqlCondition>]) -> Result<Vec<usize>, String> {
+    let num_rows = relation.num_tuples().map_err(|e| format!("{:?}", e))?;
+    if where_clause.is_empty() {
+        return Ok((0..num_rows).collect());
+    }
+    let mut matched = vec![false; num_rows];
+    for conjunction in where_clause {
+        let mut rows: Vec<usize> = (0..num_rows).collect();
+        for SqlCondition { column, op, value } in conjunction {
+            let value = typed_value(relation, column, value)?;
+            let data = relation.columns.get(column)
+                .ok_or_else(|| format!("{:?}", RelationErrors::ColumnNotFound(column.clone())))?;
+            rows.retain(|&row| data.get(row).is_some_and(|d| compare_with(d, *op, &value)));
+        }
+        rows.into_iter().for_each(|row| matched[row] = true);
+    }
+    Ok((0..num_rows).filter(|&row| matched[row]).collect())
+}
+
+/// result of INSERT, UPDATE and DELETE: a single `rows_affected` column holding one row
+fn rows_affected(count: usize) -> Result<ColumnStoreRelation, String> {
+    ColumnStoreRelation::from_records("rows_affected", vec!["rows_affected"], vec![vec![DataType::Int(count as i32)]])
+        .map_err(|e| format!("{:?}", e))
+}
+
 // #################################### 
 
+/// empty relation with the schema of `source` holding the rows of a view before its first refresh
+fn empty_view(name: &str, source: &ColumnStoreRelation) -> ColumnStoreRelation {
+    let mut view = ColumnStoreRelation::new();
+    view.name = name.to_string();
+    view.fields = source.fields.clone();
+    view.select_columns = source.select_columns.clone();
+    for key in source.columns.keys() {
+        view.columns.insert(key.clone(), Vec::new());
+    }
+    view
+}
+
+/// matches a file name against a pattern supporting `*` (any sequence) and `?` (any character)
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => wildcard_match(&pattern[1..], name) || (!name.is_empty() && wildcard_match(pattern, &name[1..])),
+        (Some('?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// expands a pattern like `data/part_*.csv` into the sorted list of matching files,
+/// wildcards are only supported in the file name
+fn expand_glob(pattern: &str) -> Result<Vec<String>, RelationErrors> {
+    let path = Path::new(pattern);
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_pattern: Vec<char> = path.file_name()
+        .and_then(|f| f.to_str())
+        .ok_or_else(|| RelationErrors::InvalidInput(format!("Invalid pattern {}", pattern)))?
+        .chars()
+        .collect();
+
+    let mut matches = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry_path = entry?.path();
+        if !entry_path.is_file() {
+            continue;
+        }
+        if let Some(name) = entry_path.file_name().and_then(|f| f.to_str()) {
+            if wildcard_match(&file_pattern, &name.chars().collect::<Vec<char>>()) {
+                matches.push(entry_path.to_string_lossy().to_string());
+            }
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
+
 
 
 impl Database {
@@ -89,50 +681,402 @@ impl Database {
         Ok(Database {
             relations: HashMap::new(),
             name: name.to_string(),
+            views: HashMap::new(),
+            max_result_rows: None,
+            live_aggregates: HashMap::new(),
+            memory_budget: None,
+            evicted: HashMap::new(),
+            spill_dir: None,
+            last_access: RefCell::new(HashMap::new()),
+            access_clock: Cell::new(0),
         })
     }
 
+    /// executes a single SELECT, INSERT, UPDATE, DELETE, CREATE TABLE or DROP TABLE statement
+    ///
+    /// INSERT, UPDATE and DELETE change the stored relation in place and return a relation with a
+    /// single `rows_affected` column holding the number of inserted, updated or deleted rows.
+    /// CREATE TABLE and DROP TABLE return the same relation with a count of 0.
     pub fn execute_sql(&mut self, query: &str) -> Result<ColumnStoreRelation, String> {
         let command = parse_sql(query)?;
+        if command.num_placeholders() > 0 {
+            return Err("Queries with placeholders have to be prepared".to_string());
+        }
+        self.run_command(command)
+    }
+
+    /// executes the semicolon-separated statements of a script file in order and returns their results
+    ///
+    /// Execution stops at the first failing statement, the error names its position in the script.
+    /// Statements executed before it are not rolled back.
+    pub fn execute_file(&mut self, path: &str) -> Result<Vec<QueryResult>, String> {
+        let script = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        let mut results = Vec::new();
+        for (i, statement) in split_statements(&script).iter().enumerate() {
+            let result = self.execute_sql(statement).map_err(|e| format!("Statement {}: {}", i + 1, e))?;
+            results.push(result);
+        }
+        Ok(results)
+    }
 
+    /// parses a query containing `?` placeholders into a reusable statement
+    pub fn prepare(&self, query: &str) -> Result<PreparedStatement, String> {
+        Ok(PreparedStatement {
+            command: parse_sql(query)?,
+            bindings: Vec::new(),
+        })
+    }
+
+    fn run_command(&mut self, command: SqlCommand) -> Result<ColumnStoreRelation, String> {
         match command {
             SqlCommand::Select {
                 columns,
                 table,
                 where_clause,
+                limit,
+                offset,
             } => {
+                self.access(&[&table]).map_err(|e| format!("{:?}", e))?;
                 let mut relation = self.relations.get(&table)
                     .ok_or_else(|| "Table not found".to_string())?
                     .clone();
 
-                if let Some((column, value)) = where_clause {
-                    relation = relation.select(&column, |d| d.to_string() == value)
+                if !where_clause.is_empty() {
+                    let rows = where_rows(&relation, &where_clause)?;
+                    relation = relation.take_rows(&rows).map_err(|e| format!("{:?}", e))?;
+                }
+                if limit.is_some() || offset > 0 {
+                    relation = relation.limit(limit.unwrap_or(usize::MAX), offset)
                         .map_err(|e| format!("{:?}", e))?;
                 }
 
                 relation.project(columns.iter().map(String::as_str).collect())
                     .map_err(|e| format!("{:?}", e))
             },
+            SqlCommand::Insert { table, columns, rows } => {
+                self.access(&[&table]).map_err(|e| format!("{:?}", e))?;
+                let relation = self.relations.get_mut(&table)
+                    .ok_or_else(|| "Table not found".to_string())?;
+                let columns = columns.unwrap_or_else(|| relation.select_columns.clone());
+                if let Some(column) = columns.iter().find(|&column| !relation.select_columns.contains(column)) {
+                    return Err(format!("{:?}", RelationErrors::ColumnNotFound(column.clone())));
+                }
+
+                // every row is converted before the first one is added so a bad value inserts nothing
+                let mut tuples = Vec::new();
+                for row in &rows {
+                    if row.len() != columns.len() {
+                        return Err(format!("Expected {} values but got {}", columns.len(), row.len()));
+                    }
+                    let tuple = columns.iter().zip(row)
+                        .map(|(column, value)| typed_value(relation, column, value)
+                            .and_then(|value| check_field(relation, column, &value).map(|_| value)))
+                        .collect::<Result<Vec<DataType>, String>>()?;
+                    tuples.push(tuple);
+                }
+                for tuple in tuples {
+                    relation.add_partial_tuple(columns.iter().map(String::as_str).zip(tuple).collect())
+                        .map_err(|e| format!("{:?}", e))?;
+                }
+                relation.rebuild_indices().map_err(|e| format!("{:?}", e))?;
+                self.recompute_dependents(&table).map_err(|e| format!("{:?}", e))?;
+                rows_affected(rows.len())
+            },
+            SqlCommand::Update { table, assignments, where_clause } => {
+                self.access(&[&table]).map_err(|e| format!("{:?}", e))?;
+                let relation = self.relations.get_mut(&table)
+                    .ok_or_else(|| "Table not found".to_string())?;
+                let rows = where_rows(relation, &where_clause)?;
+                let values = assignments.iter()
+                    .map(|(column, value)| typed_value(relation, column, value))
+                    .collect::<Result<Vec<DataType>, String>>()?;
+                // all assignments are checked before the first one is applied
+                for ((column, _), value) in assignments.iter().zip(&values) {
+                    if !relation.columns.contains_key(column) {
+                        return Err(format!("{:?}", RelationErrors::ColumnNotFound(column.clone())));
+                    }
+                    check_field(relation, column, value)?;
+                }
+                for ((column, _), value) in assignments.iter().zip(values) {
+                    relation.update_rows(&rows, column, value).map_err(|e| format!("{:?}", e))?;
+                }
+                self.recompute_dependents(&table).map_err(|e| format!("{:?}", e))?;
+                rows_affected(rows.len())
+            },
+            SqlCommand::Delete { table, where_clause } => {
+                self.access(&[&table]).map_err(|e| format!("{:?}", e))?;
+                let relation = self.relations.get_mut(&table)
+                    .ok_or_else(|| "Table not found".to_string())?;
+                let rows = where_rows(relation, &where_clause)?;
+                let deleted = relation.delete_rows(&rows).map_err(|e| format!("{:?}", e))?;
+                self.recompute_dependents(&table).map_err(|e| format!("{:?}", e))?;
+                rows_affected(deleted)
+            },
+            SqlCommand::CreateTable { table, columns } => {
+                if self.contains_relation(&table) {
+                    return Err(format!("{:?}", RelationErrors::RelationAlreadyExists));
+                }
+                let mut relation = ColumnStoreRelation::new();
+                relation.name = table.clone();
+                for (column, field) in columns {
+                    relation.columns.insert(column.clone(), Vec::new());
+                    relation.fields.insert(column.clone(), field);
+                    relation.select_columns.push(column);
+                }
+                self.add_relation(table, relation);
+                rows_affected(0)
+            },
+            SqlCommand::DropTable { table } => {
+                self.drop_relation(&table).map_err(|e| format!("{:?}", e))?;
+                rows_affected(0)
+            },
         }
     }
 
     /// Adds a new relation to the database
     pub fn add_relation(&mut self, name: String, relation: ColumnStoreRelation) {
-        // Collect keys and values into Vecs to solve the borrowing issue
+        self.evicted.remove(&name);
         self.relations.insert(name.clone(), relation);
+        // the new relation stays resident, a failing eviction only leaves the database over budget
+        let _ = self.access(&[&name]);
+    }
+
+    /// returns a reference to the relation with the given name
+    ///
+    /// An evicted relation cannot be reloaded through a shared reference, `load_relation` reloads it.
+    pub fn get_relation(&self, name: &str) -> Result<&ColumnStoreRelation, RelationErrors> {
+        if self.evicted.contains_key(name) {
+            return Err(RelationErrors::Error(format!("Relation {} is evicted, load_relation reloads it", name)));
+        }
+        self.touch(name);
+        self.relations.get(name).ok_or(RelationErrors::RelationNotFound)
+    }
+
+    /// returns a reference to the relation with the given name, an evicted relation is reloaded
+    pub fn load_relation(&mut self, name: &str) -> Result<&ColumnStoreRelation, RelationErrors> {
+        self.access(&[name])?;
+        self.relations.get(name).ok_or(RelationErrors::RelationNotFound)
+    }
+
+    /// calls `f` with the relation of the given name, an evicted relation is read from disk for
+    /// the call without making it resident again
+    fn with_relation<T, F>(&self, name: &str, f: F) -> Result<T, RelationErrors>
+    where F: FnOnce(&ColumnStoreRelation) -> Result<T, RelationErrors> {
+        if let Some(path) = self.evicted.get(name) {
+            return f(&ColumnStoreRelation::read_binary(path)?);
+        }
+        self.touch(name);
+        f(self.relations.get(name).ok_or(RelationErrors::RelationNotFound)?)
+    }
+
+    /// returns a mutable reference to the relation with the given name, an evicted relation is reloaded
+    pub fn get_relation_mut(&mut self, name: &str) -> Result<&mut ColumnStoreRelation, RelationErrors> {
+        self.access(&[name])?;
+        self.relations.get_mut(name).ok_or(RelationErrors::RelationNotFound)
+    }
+
+    /// true if a relation with the given name exists, in memory or evicted
+    fn contains_relation(&self, name: &str) -> bool {
+        self.relations.contains_key(name) || self.evicted.contains_key(name)
     }
 
     /// creates a new relation and inserts it into the hashmap
     pub fn create_relation(&mut self, name: &str) -> Result<(), RelationErrors> {
-        if self.relations.contains_key(name) {
+        if self.contains_relation(name) {
             Err(RelationErrors::RelationAlreadyExists)
         } else {
             let relation = ColumnStoreRelation::new(); // Create a new instance
-            self.relations.insert(name.to_string(), relation); // Add it to the database
+            self.add_relation(name.to_string(), relation); // Add it to the database
             Ok(())
         }
     }
 
+    /// stores a deep copy of the relation `source` under the name `dest`
+    pub fn copy_relation(&mut self, source: &str, dest: &str) -> Result<(), RelationErrors> {
+        if self.contains_relation(dest) {
+            return Err(RelationErrors::RelationAlreadyExists);
+        }
+        let mut copy = self.load_relation(source)?.clone();
+        copy.name = dest.to_string();
+        self.add_relation(dest.to_string(), copy);
+        Ok(())
+    }
+
+    /// names of all relations, in memory or evicted, in sorted order
+    pub fn list_relations(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.relations.keys().chain(self.evicted.keys()).cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// removes the relation with the given name, a materialized view of that name is dropped with it
+    ///
+    /// Views and live aggregates computed on the relation are dropped as well, views on those views included.
+    pub fn drop_relation(&mut self, name: &str) -> Result<(), RelationErrors> {
+        if !self.contains_relation(name) {
+            return Err(RelationErrors::RelationNotFound);
+        }
+        self.relations.remove(name);
+        if let Some(path) = self.evicted.remove(name) {
+            let _ = std::fs::remove_file(path);
+        }
+        self.last_access.borrow_mut().remove(name);
+        self.views.remove(name);
+        self.live_aggregates.retain(|_, live| live.source != name);
+
+        let dependent_views: Vec<String> = self.views.iter()
+            .filter(|(_, view)| view.source == name)
+            .map(|(view_name, _)| view_name.clone())
+            .collect();
+        for view_name in dependent_views {
+            self.drop_relation(&view_name)?;
+        }
+        Ok(())
+    }
+
+    /// stores the relation `old` under the name `new`, views and live aggregates follow the rename
+    pub fn rename_relation(&mut self, old: &str, new: &str) -> Result<(), RelationErrors> {
+        if !self.contains_relation(old) {
+            return Err(RelationErrors::RelationNotFound);
+        }
+        if old == new {
+            return Ok(());
+        }
+        if self.contains_relation(new) {
+            return Err(RelationErrors::RelationAlreadyExists);
+        }
+        self.access(&[old])?;
+        let mut relation = self.relations.remove(old).ok_or(RelationErrors::RelationNotFound)?;
+        relation.name = new.to_string();
+        self.relations.insert(new.to_string(), relation);
+        let mut last_access = self.last_access.borrow_mut();
+        if let Some(time) = last_access.remove(old) {
+            last_access.insert(new.to_string(), time);
+        }
+        drop(last_access);
+        if let Some(view) = self.views.remove(old) {
+            self.views.insert(new.to_string(), view);
+        }
+        for view in self.views.values_mut().filter(|view| view.source == old) {
+            view.source = new.to_string();
+        }
+        for live in self.live_aggregates.values_mut().filter(|live| live.source == old) {
+            live.source = new.to_string();
+        }
+        Ok(())
+    }
+
+    /// limits the estimated memory of the resident relations, see `Relation::memory_footprint`
+    ///
+    /// Once the budget is exceeded, the least recently used relations are written to a temporary
+    /// directory and removed from memory until the others fit. Accessing an evicted relation
+    /// reloads it. Relations are measured whenever one of them is accessed, so relations growing
+    /// through `get_relation_mut` may exceed the budget until the next access. `None` disables
+    /// eviction, evicted relations are still reloaded on access.
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) -> Result<(), RelationErrors> {
+        self.memory_budget = memory_budget;
+        self.access(&[])
+    }
+
+    /// true if the relation is currently evicted to disk
+    pub fn is_evicted(&self, name: &str) -> bool {
+        self.evicted.contains_key(name)
+    }
+
+    /// marks a resident relation as used, the bookkeeping works through a shared reference
+    fn touch(&self, name: &str) {
+        if self.relations.contains_key(name) {
+            self.access_clock.set(self.access_clock.get() + 1);
+            self.last_access.borrow_mut().insert(name.to_string(), self.access_clock.get());
+        }
+    }
+
+    /// reloads the given relations if they are evicted, marks them as used and evicts the least
+    /// recently used other relations while the budget is exceeded
+    fn access(&mut self, names: &[&str]) -> Result<(), RelationErrors> {
+        for &name in names {
+            if let Some(path) = self.evicted.remove(name) {
+                let relation = ColumnStoreRelation::read_binary(&path)?;
+                let _ = std::fs::remove_file(&path);
+                self.relations.insert(name.to_string(), relation);
+            }
+            self.touch(name);
+        }
+
+        let Some(budget) = self.memory_budget else { return Ok(()) };
+        let mut footprint: usize = self.relations.values().map(|relation| relation.memory_footprint()).sum();
+        while footprint > budget {
+            let Some(victim) = self.relations.keys()
+                .filter(|name| !names.contains(&name.as_str()))
+                .min_by_key(|name| self.last_access.borrow().get(*name).copied().unwrap_or(0))
+                .cloned() else { break };
+
+            if self.spill_dir.is_none() {
+                self.spill_dir = Some(tempfile::tempdir()?);
+            }
+            self.access_clock.set(self.access_clock.get() + 1);
+            let path = self.spill_dir.as_ref().unwrap().path().join(format!("{}.rel", self.access_clock.get()));
+            let relation = self.relations.remove(&victim).unwrap();
+            if let Err(e) = relation.write_binary(&path) {
+                self.relations.insert(victim, relation);
+                return Err(e);
+            }
+            footprint -= relation.memory_footprint();
+            self.evicted.insert(victim, path);
+        }
+
+        Ok(())
+    }
+
+    /// exchanges the relations stored under the names `a` and `b`, views and live aggregates on
+    /// either of them are recomputed from the relation now stored under their source name
+    pub fn swap_relations(&mut self, a: &str, b: &str) -> Result<(), RelationErrors> {
+        self.access(&[a, b])?;
+        if !self.relations.contains_key(a) || !self.relations.contains_key(b) {
+            return Err(RelationErrors::RelationNotFound);
+        }
+        if a == b {
+            return Ok(());
+        }
+        let mut relation_a = self.relations.remove(a).unwrap();
+        let mut relation_b = self.relations.remove(b).unwrap();
+        std::mem::swap(&mut relation_a.name, &mut relation_b.name);
+        self.relations.insert(a.to_string(), relation_b);
+        self.relations.insert(b.to_string(), relation_a);
+
+        // the watermarks of views and live aggregates refer to the rows of the other relation now
+        self.recompute_dependents(a)?;
+        self.recompute_dependents(b)
+    }
+
+    /// recomputes the views and live aggregates on `source` from scratch
+    fn recompute_dependents(&mut self, source: &str) -> Result<(), RelationErrors> {
+        let views: Vec<String> = self.views.iter()
+            .filter(|(_, view)| view.source == source)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in views {
+            self.access(&[&name, source])?;
+            let source_relation = self.relations.get(source).ok_or(RelationErrors::RelationNotFound)?;
+            let view = empty_view(&name, source_relation);
+            self.relations.insert(name.clone(), view);
+            self.views.get_mut(&name).unwrap().watermark = 0;
+            self.refresh_view(&name)?;
+        }
+
+        let live_aggregates: Vec<String> = self.live_aggregates.iter()
+            .filter(|(_, live)| live.source == source)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in live_aggregates {
+            let live = self.live_aggregates.get_mut(&name).unwrap();
+            live.aggregator = Aggregator::new(&live.aggregator.column_name, live.aggregator.aggregation);
+            live.watermark = 0;
+            self.refresh_live_aggregate(&name)?;
+        }
+        Ok(())
+    }
+
     /// loads a csv-file, given by path into an existing relation
     pub fn load_from_csv(
         &mut self,
@@ -142,12 +1086,65 @@ impl Database {
         select_columns: Vec<&str>,
     ) -> Result<(), RelationErrors> {
         // check and get relation by name
+        self.access(&[name])?;
         if let Some(relation) = self.relations.get_mut(name) {
             return relation.load_csv(path, name, delimiter, select_columns);
         }
         Err(RelationErrors::RelationNotFound)
     }
 
+    /// loads all csv-files matching a glob pattern into an existing relation
+    ///
+    /// Files are loaded in sorted order, the first one establishes the schema and every
+    /// following file has to provide the same columns.
+    pub fn load_glob(
+        &mut self,
+        name: &str,
+        pattern: &str,
+        delimiter: &str,
+        select_columns: Vec<&str>,
+    ) -> Result<(), RelationErrors> {
+        let relation = self.get_relation_mut(name)?;
+        let files = expand_glob(pattern)?;
+        let (first, rest) = files.split_first()
+            .ok_or_else(|| RelationErrors::ReadError(format!("No files match {}", pattern)))?;
+
+        relation.load_csv(first, name, delimiter, select_columns.clone())?;
+
+        for file in rest {
+            let mut part = ColumnStoreRelation::new();
+            part.load_csv(file, name, delimiter, select_columns.clone())?;
+
+            let mut expected: Vec<&String> = relation.columns.keys().collect();
+            let mut found: Vec<&String> = part.columns.keys().collect();
+            expected.sort();
+            found.sort();
+            if expected != found {
+                return Err(RelationErrors::ReadError(format!("Schema of {} does not match {}", file, first)));
+            }
+
+            // a column without values in one of the files takes its type from the others
+            for (key, field) in &part.fields {
+                match relation.fields.get(key) {
+                    Some(DataType::Null) | None => { relation.fields.insert(key.clone(), field.clone()); },
+                    Some(expected) if !field.is_null() && !expected.same_type(field) => {
+                        return Err(RelationErrors::ReadError(format!(
+                            "Column {} of {} is {} but {} in {}", key, file, field.type_name(), expected.type_name(), first
+                        )));
+                    },
+                    Some(_) => {},
+                }
+            }
+
+            for (key, values) in part.columns {
+                relation.columns.get_mut(&key).unwrap().extend(values);
+            }
+        }
+
+        relation.sorted_columns.clear();
+        relation.rebuild_indices()
+    }
+
     /// filters rows from a relation based on a predicate
     pub fn select_from_relation<F>(
         &mut self,
@@ -158,6 +1155,7 @@ impl Database {
     where
         F: Fn(&DataType) -> bool,
     {
+        self.access(&[name])?;
         if let Some(relation) = self.relations.get_mut(name) {
             return relation.select(column_name, predicate);
         }
@@ -170,6 +1168,7 @@ impl Database {
         name: &str,
         columns_to_keep: Vec<&str>,
     ) -> Result<ColumnStoreRelation, RelationErrors> {
+        self.access(&[name])?;
         if let Some(relation) = self.relations.get(name) {
             return relation.project(columns_to_keep);
         }
@@ -179,24 +1178,20 @@ impl Database {
     /// prints the content of a given relation into a table-like format to cmd
     pub fn pretty_print_relation(&self, name: &str) -> Result<(), RelationErrors> {
         println!("{}.{}", self.name, name);
-        if let Some(relation) = self.relations.get(name) {
+        self.with_relation(name, |relation| {
             relation.pretty_print();
             Ok(())
-        } else {
-            Err(RelationErrors::RelationNotFound)
-        }
+        })
     }
 
     /// calculates an aggregate function on a given function
     pub fn aggregate(&self, relation_name: &str, column_name: &str, aggregation: Aggregation) -> Result<DataType, RelationErrors> {
-        match self.relations.get(relation_name) {
-            Some(relation) => relation.aggr(column_name, aggregation),
-            None => Err(RelationErrors::RelationNotFound),
-        }
+        self.with_relation(relation_name, |relation| relation.aggr(column_name, aggregation))
     }
 
     /// sorts a relation in ascending or descending order
     pub fn sort_relation(&mut self, relation_name: &str, column_name: &str, order: Order) -> Result<(), RelationErrors> {
+        self.access(&[relation_name])?;
         if let Some(relation) = self.relations.get_mut(relation_name) {
             relation.sort(column_name, order)
         } else {
@@ -206,6 +1201,7 @@ impl Database {
 
     /// creates and index for a given relation and column
     pub fn create_index(&mut self, relation_name: &str, column_name: &str) -> Result<(), String> {
+        self.access(&[relation_name]).map_err(|e| format!("{:?}", e))?;
         let relation = self.relations.get_mut(relation_name)
             .ok_or_else(|| "Relation not found".to_string())?;
         relation.create_index(column_name)
@@ -214,18 +1210,166 @@ impl Database {
     /// joins two columns given by name and predicate
     pub fn join<F>(&mut self, r_name: &str, r_col: &str, s_name: &str, s_col: &str, predicate: F, jt: JoinType) -> Result<ColumnStoreRelation, RelationErrors> 
     where F: Fn(&DataType, &DataType) -> bool {
+        self.access(&[r_name, s_name])?;
         let r = self.relations.get(r_name).unwrap();
         let s = self.relations.get(s_name).unwrap();
-        match jt {
-            JoinType::NestedLoop => {
-                return r.nested_loop_join(s, r_col, s_col, predicate);
-            },
-            JoinType::MergeJoin => {
-                return r.merge_join(s, r_col, s_col, predicate);
-            },
-            JoinType::HashJoin => {
-                return r.hash_join(s, r_col, s_col, predicate);
+        r.join_with_limit(s, r_col, s_col, predicate, jt, self.max_result_rows)
+    }
+
+    /// equi-joins two relations and aggregates a column of the join result
+    #[allow(clippy::too_many_arguments)]
+    pub fn aggregate_join(&mut self, r_name: &str, r_col: &str, s_name: &str, s_col: &str, agg_col: &str, aggregation: Aggregation, jt: JoinType) -> Result<DataType, RelationErrors> {
+        self.access(&[r_name, s_name])?;
+        let r = self.relations.get(r_name).ok_or(RelationErrors::RelationNotFound)?;
+        let s = self.relations.get(s_name).ok_or(RelationErrors::RelationNotFound)?;
+        let joined = r.join_with_limit(s, r_col, s_col, |l, r| l == r, jt, self.max_result_rows)?;
+        joined.aggr(agg_col, aggregation)
+    }
+
+    /// limits the number of rows a join may produce, `None` removes the limit
+    pub fn set_max_result_rows(&mut self, max_result_rows: Option<usize>) {
+        self.max_result_rows = max_result_rows;
+    }
+
+    /// joins two relations after prefixing their columns with `<alias>.`, which makes self-joins
+    /// unambiguous; the join columns are given unprefixed and the aliases must differ
+    #[allow(clippy::too_many_arguments)]
+    pub fn join_aliased<F>(&mut self, r_name: &str, r_alias: &str, r_col: &str, s_name: &str, s_alias: &str, s_col: &str, predicate: F, jt: JoinType) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool {
+        if r_alias == s_alias {
+            return Err(RelationErrors::InvalidInput(format!("Both sides of the join use the alias {}", r_alias)));
+        }
+        self.access(&[r_name, s_name])?;
+        let r = self.relations.get(r_name).ok_or(RelationErrors::RelationNotFound)?.prefix_columns(r_alias);
+        let s = self.relations.get(s_name).ok_or(RelationErrors::RelationNotFound)?.prefix_columns(s_alias);
+        let r_col = format!("{}.{}", r_alias, r_col);
+        let s_col = format!("{}.{}", s_alias, s_col);
+        r.join_with_limit(&s, &r_col, &s_col, predicate, jt, self.max_result_rows)
+    }
+
+    /// registers a materialized view holding the rows of `source` whose `column_name` matches the predicate
+    pub fn create_view<F>(&mut self, name: &str, source: &str, column_name: &str, predicate: F) -> Result<(), RelationErrors>
+    where F: Fn(&DataType) -> bool + 'static {
+        if self.contains_relation(name) {
+            return Err(RelationErrors::RelationAlreadyExists);
+        }
+        self.access(&[source])?;
+        let source_relation = self.relations.get(source).ok_or(RelationErrors::RelationNotFound)?;
+        if !source_relation.columns.contains_key(column_name) {
+            return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+        }
+
+        // the view starts out empty and is filled by the first refresh
+        let view = empty_view(name, source_relation);
+        self.relations.insert(name.to_string(), view);
+        self.views.insert(name.to_string(), MaterializedView {
+            source: source.to_string(),
+            column: column_name.to_string(),
+            predicate: Box::new(predicate),
+            watermark: 0,
+        });
+
+        self.refresh_view(name).map(|_| ())
+    }
+
+    /// appends the matching source rows added since the last refresh and returns how many were appended
+    ///
+    /// Only appends are tracked. If the source shrank below the watermark (rows were deleted),
+    /// the view is recomputed from scratch.
+    pub fn refresh_view(&mut self, name: &str) -> Result<usize, RelationErrors> {
+        let source_name = self.views.get(name).ok_or(RelationErrors::RelationNotFound)?.source.clone();
+        self.access(&[name, &source_name])?;
+        let view = self.views.get_mut(name).ok_or(RelationErrors::RelationNotFound)?;
+        let source = self.relations.get(&view.source).ok_or(RelationErrors::RelationNotFound)?;
+        let filter_column = source.columns.get(&view.column)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(view.column.clone()))?;
+
+        let mut rebuild = false;
+        if filter_column.len() < view.watermark {
+            view.watermark = 0;
+            rebuild = true;
+        }
+
+        let matching_rows: Vec<usize> = (view.watermark..filter_column.len())
+            .filter(|&row| (view.predicate)(&filter_column[row]))
+            .collect();
+
+        let mut appended: Vec<(String, Vec<DataType>)> = Vec::new();
+        for (key, values) in &source.columns {
+            appended.push((key.clone(), matching_rows.iter().filter_map(|&row| values.get(row).cloned()).collect()));
+        }
+        view.watermark = filter_column.len();
+
+        let view_relation = self.relations.get_mut(name).ok_or(RelationErrors::RelationNotFound)?;
+        for (key, values) in appended {
+            let column = view_relation.columns.entry(key).or_default();
+            if rebuild {
+                column.clear();
             }
+            column.extend(values);
         }
+
+        Ok(matching_rows.len())
     }
-}
\ No newline at end of file
+
+    /// appends a tuple to a relation and updates the live aggregates computed on it
+    pub fn add_tuple(&mut self, relation_name: &str, tuple: Vec<DataType>) -> Result<(), RelationErrors> {
+        self.get_relation_mut(relation_name)?.add_tuple(tuple)?;
+
+        let names: Vec<String> = self.live_aggregates.iter()
+            .filter(|(_, live)| live.source == relation_name)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in names {
+            self.refresh_live_aggregate(&name)?;
+        }
+        Ok(())
+    }
+
+    /// registers an aggregate over `column_name` of `source` that is updated as rows are added with `add_tuple`
+    pub fn create_live_aggregate(&mut self, name: &str, source: &str, column_name: &str, aggregation: Aggregation) -> Result<(), RelationErrors> {
+        if self.live_aggregates.contains_key(name) {
+            return Err(RelationErrors::RelationAlreadyExists);
+        }
+        if !self.load_relation(source)?.columns.contains_key(column_name) {
+            return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+        }
+
+        self.live_aggregates.insert(name.to_string(), LiveAggregate {
+            source: source.to_string(),
+            aggregator: Aggregator::new(column_name, aggregation),
+            watermark: 0,
+        });
+        self.refresh_live_aggregate(name)
+    }
+
+    /// current result of a live aggregate, without scanning the source
+    pub fn current_value(&self, name: &str) -> Result<DataType, RelationErrors> {
+        self.live_aggregates.get(name).ok_or(RelationErrors::RelationNotFound)?.aggregator.value()
+    }
+
+    /// adds the source rows appended since the last update to a live aggregate
+    ///
+    /// Rows added through `get_relation_mut` are only picked up by this method. Deletes cannot
+    /// be undone on the running state: if the source shrank below the watermark, the aggregate
+    /// is recomputed over the whole column.
+    pub fn refresh_live_aggregate(&mut self, name: &str) -> Result<(), RelationErrors> {
+        let source_name = self.live_aggregates.get(name).ok_or(RelationErrors::RelationNotFound)?.source.clone();
+        self.access(&[&source_name])?;
+        let live = self.live_aggregates.get_mut(name).ok_or(RelationErrors::RelationNotFound)?;
+        let source = self.relations.get(&live.source).ok_or(RelationErrors::RelationNotFound)?;
+        let column = source.columns.get(&live.aggregator.column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(live.aggregator.column_name.clone()))?;
+
+        if column.len() < live.watermark {
+            live.aggregator = Aggregator::new(&live.aggregator.column_name, live.aggregator.aggregation);
+            live.watermark = 0;
+        }
+        for value in &column[live.watermark..] {
+            live.aggregator.update(value);
+        }
+        live.watermark = column.len();
+
+        Ok(())
+    }
+}
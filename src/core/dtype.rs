@@ -1,3 +1,6 @@
+use crate::errors::RelationErrors;
+
+use std::cmp::Ordering;
 use std::io::{self};
 use std::hash::{Hash, Hasher};
 
@@ -6,25 +9,76 @@ pub enum DataType {
     String(String),
     Int(i32),
     Float(f64),
+    Bool(bool),
+    /// missing value
+    Null,
+}
+
+/// placement of nulls in `DataType::sql_cmp`, like SQL `NULLS FIRST` and `NULLS LAST`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+/// how fractional values are handled when casting a Float to an Int
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatToIntMode {
+    /// drop the fractional part, rounding towards zero
+    Truncate,
+    /// round to the nearest integer, halfway cases away from zero
+    Round,
+    /// fail if the value has a fractional part
+    ErrorOnFractional,
 }
 
 impl DataType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         let str = s.trim();
         if let Ok(i) = str.parse::<i32>() {
             DataType::Int(i)
         } else if let Ok(f) = str.parse::<f64>() {
             DataType::Float(f)
+        } else if str.eq_ignore_ascii_case("true") {
+            DataType::Bool(true)
+        } else if str.eq_ignore_ascii_case("false") {
+            DataType::Bool(false)
         } else {
             DataType::String(str.to_string())
         }
     }
 
+    /// parses a string strictly into the variant of `target` and fails instead of falling back
+    /// to another variant, surrounding whitespace is ignored except for strings
+    ///
+    /// Booleans accept `true`/`false` in any case as well as `1`/`0`. There is no date variant,
+    /// dates are kept as strings.
+    pub fn parse_as(s: &str, target: &DataType) -> Result<DataType, RelationErrors> {
+        let invalid = || RelationErrors::InvalidInput(format!("Cannot parse {:?} as {}", s, target.type_name()));
+        let trimmed = s.trim();
+        match target {
+            DataType::Int(_) => trimmed.parse::<i32>().map(DataType::Int).map_err(|_| invalid()),
+            DataType::Float(_) => trimmed.parse::<f64>().map(DataType::Float).map_err(|_| invalid()),
+            DataType::String(_) => Ok(DataType::String(s.to_string())),
+            DataType::Bool(_) => match trimmed {
+                "1" => Ok(DataType::Bool(true)),
+                "0" => Ok(DataType::Bool(false)),
+                b if b.eq_ignore_ascii_case("true") => Ok(DataType::Bool(true)),
+                b if b.eq_ignore_ascii_case("false") => Ok(DataType::Bool(false)),
+                _ => Err(invalid()),
+            },
+            DataType::Null => Err(invalid()),
+        }
+    }
+
     pub fn to_str(&self) -> String {
         match self {
             DataType::Int(i) => format!("{}", i),
             DataType::Float(f) => format!("{:.6}", f), // Limit precision to avoid floating-point comparison issues.
-            DataType::String(s) => format!("{}", s),
+            DataType::String(s) => s.to_string(),
+            DataType::Bool(b) => format!("{}", b),
+            DataType::Null => String::new(),
         }
     }
 
@@ -33,6 +87,8 @@ impl DataType {
             DataType::Int(_) => self.dlen() + 1,
             DataType::Float(_) => self.dlen() + 1, 
             DataType::String(_) => self.dlen() + 1,
+            DataType::Bool(_) => self.dlen() + 1,
+            DataType::Null => self.dlen() + 1,
         }
     }
 
@@ -41,14 +97,109 @@ impl DataType {
             DataType::Int(_) => std::mem::size_of::<i32>(),
             DataType::Float(_) => std::mem::size_of::<f64>(), 
             DataType::String(s) => s.len(),
+            DataType::Bool(_) => std::mem::size_of::<bool>(),
+            DataType::Null => 0,
         }
     }
 
-    pub fn to_json(&self) -> String {
+    /// returns true if the value is missing
+    pub fn is_null(&self) -> bool {
+        matches!(self, DataType::Null)
+    }
+
+    /// converts the value into the variant of `target`, float to int conversions follow `mode`
+    ///
+    /// Booleans cast to the ints 1 and 0, and the ints 1 and 0 as well as the strings `true`,
+    /// `false`, `1` and `0` cast to booleans.
+    pub fn cast(&self, target: &DataType, mode: FloatToIntMode) -> Result<DataType, RelationErrors> {
+        let invalid = || RelationErrors::InvalidInput(format!("Cannot cast {} to {}", self, target.type_name()));
+        match (self, target) {
+            (_, DataType::Null) => Err(invalid()),
+            (DataType::Null, _) => Ok(DataType::Null),
+            (DataType::Int(i), DataType::Int(_)) => Ok(DataType::Int(*i)),
+            (DataType::Float(f), DataType::Int(_)) => {
+                let value = match mode {
+                    FloatToIntMode::Truncate => f.trunc(),
+                    FloatToIntMode::Round => f.round(),
+                    FloatToIntMode::ErrorOnFractional if f.fract() != 0.0 => return Err(invalid()),
+                    FloatToIntMode::ErrorOnFractional => *f,
+                };
+                if value.is_nan() || value < i32::MIN as f64 || value > i32::MAX as f64 {
+                    return Err(invalid());
+                }
+                Ok(DataType::Int(value as i32))
+            },
+            (DataType::String(s), DataType::Int(_)) => s.trim().parse::<i32>().map(DataType::Int).map_err(|_| invalid()),
+            (DataType::Int(i), DataType::Float(_)) => Ok(DataType::Float(*i as f64)),
+            (DataType::Float(f), DataType::Float(_)) => Ok(DataType::Float(*f)),
+            (DataType::String(s), DataType::Float(_)) => s.trim().parse::<f64>().map(DataType::Float).map_err(|_| invalid()),
+            (DataType::Bool(b), DataType::Int(_)) => Ok(DataType::Int(*b as i32)),
+            (DataType::Bool(b), DataType::Float(_)) => Ok(DataType::Float(*b as i32 as f64)),
+            (DataType::Bool(b), DataType::Bool(_)) => Ok(DataType::Bool(*b)),
+            (DataType::Int(i @ (0 | 1)), DataType::Bool(_)) => Ok(DataType::Bool(*i == 1)),
+            (DataType::String(s), DataType::Bool(_)) => DataType::parse_as(s, target).map_err(|_| invalid()),
+            (_, DataType::Bool(_)) => Err(invalid()),
+            (value, DataType::String(_)) => Ok(DataType::String(value.to_string())),
+        }
+    }
+
+    /// name of the variant, e.g. `Integer`
+    pub fn type_name(&self) -> &'static str {
         match self {
-            DataType::Int(_) => "\"Integer\"".to_string(),
-            DataType::Float(_) => "\"Float\"".to_string(),
-            DataType::String(_) => "\"String\"".to_string(),
+            DataType::Int(_) => "Integer",
+            DataType::Float(_) => "Float",
+            DataType::String(_) => "String",
+            DataType::Bool(_) => "Boolean",
+            DataType::Null => "Null",
+        }
+    }
+
+    /// returns true if both values are of the same variant
+    pub fn same_type(&self, other: &DataType) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("\"{}\"", self.type_name())
+    }
+
+    /// total order shared by sorting, merge joins and indices
+    ///
+    /// Nulls are placed according to `nulls`, the other values sort as numbers, then strings, then
+    /// booleans. Ints and Floats are compared numerically, an Int sorts before a numerically equal
+    /// Float and NaN sorts after all other numbers. Strings compare bytewise and false sorts before true.
+    pub fn sql_cmp(&self, other: &DataType, nulls: NullsOrder) -> Ordering {
+        let rank = |value: &DataType| match value {
+            DataType::Null => 0,
+            DataType::Int(_) | DataType::Float(_) => 1,
+            DataType::String(_) => 2,
+            DataType::Bool(_) => 3,
+        };
+        let as_f64 = |value: &DataType| match value {
+            DataType::Int(i) => *i as f64,
+            DataType::Float(f) => *f,
+            _ => 0.0,
+        };
+
+        match (self, other) {
+            (DataType::Null, DataType::Null) => Ordering::Equal,
+            (DataType::Null, _) if nulls == NullsOrder::First => Ordering::Less,
+            (DataType::Null, _) => Ordering::Greater,
+            (_, DataType::Null) => other.sql_cmp(self, nulls).reverse(),
+            (DataType::Int(a), DataType::Int(b)) => a.cmp(b),
+            (DataType::String(a), DataType::String(b)) => a.cmp(b),
+            (DataType::Bool(a), DataType::Bool(b)) => a.cmp(b),
+            (a, b) if rank(a) == 1 && rank(b) == 1 => {
+                let (a_f, b_f) = (as_f64(a), as_f64(b));
+                match (a_f.is_nan(), b_f.is_nan()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => a_f.partial_cmp(&b_f).unwrap()
+                        .then_with(|| matches!(a, DataType::Float(_)).cmp(&matches!(b, DataType::Float(_)))),
+                }
+            },
+            (a, b) => rank(a).cmp(&rank(b)),
         }
     }
 }
@@ -65,6 +216,8 @@ impl Hash for DataType {
                 let bits = f.to_bits();
                 bits.hash(state);
             }
+            DataType::Bool(b) => b.hash(state),
+            DataType::Null => 0.hash(state),
         }
     }
 }
@@ -75,10 +228,56 @@ impl std::fmt::Display for DataType {
             DataType::String(s) => write!(f, "{}", s),
             DataType::Int(i) => write!(f, "{}", i),
             DataType::Float(fl) => write!(f, "{}", fl),
+            DataType::Bool(b) => write!(f, "{}", b),
+            DataType::Null => Ok(()),
+        }
+    }
+}
+
+/// wrapper giving DataType a total order so it can be used as a BTreeMap key
+///
+/// The order is `DataType::sql_cmp` with nulls first.
+#[derive(Debug, Clone)]
+pub struct OrdDataType(pub DataType);
+
+impl OrdDataType {
+    /// rank of the value's kind in the total order, values of the same rank are comparable
+    pub fn rank(&self) -> u8 {
+        match self.0 {
+            DataType::Null => 0,
+            DataType::Int(_) | DataType::Float(_) => 1,
+            DataType::String(_) => 2,
+            DataType::Bool(_) => 3,
         }
     }
 }
 
+impl From<DataType> for OrdDataType {
+    fn from(value: DataType) -> Self {
+        OrdDataType(value)
+    }
+}
+
+impl Ord for OrdDataType {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.sql_cmp(&other.0, NullsOrder::First)
+    }
+}
+
+impl PartialOrd for OrdDataType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OrdDataType {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrdDataType {}
+
 pub fn serialize_data_types(data_types: &[DataType]) -> io::Result<Vec<u8>> {
     // Example serialization function
     let mut bytes = Vec::new();
@@ -97,6 +296,13 @@ pub fn serialize_data_types(data_types: &[DataType]) -> io::Result<Vec<u8>> {
                 bytes.push(2); // '2' prefix for Float
                 bytes.extend(f.to_be_bytes());
             },
+            DataType::Null => {
+                bytes.push(3); // '3' prefix for Null, no payload
+            },
+            DataType::Bool(b) => {
+                bytes.push(4); // '4' prefix for Bool
+                bytes.push(*b as u8);
+            },
         }
     }
     Ok(bytes)
@@ -121,6 +327,14 @@ pub fn deserialize_data_types(bytes: &[u8]) -> io::Result<Vec<DataType>> {
                 i += 1;
                 DataType::Float(f64::from_be_bytes(bytes[i..i+8].try_into().unwrap()))
             },
+            3 => {
+                i += 1;
+                DataType::Null
+            },
+            4 => {
+                i += 1;
+                DataType::Bool(bytes[i] != 0)
+            },
             _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown DataType prefix")),
         };
         data_types.push(data_type);
@@ -128,6 +342,8 @@ pub fn deserialize_data_types(bytes: &[u8]) -> io::Result<Vec<DataType>> {
             DataType::String(s) => s.len(),
             DataType::Int(_) => 4,
             DataType::Float(_) => 8,
+            DataType::Bool(_) => 1,
+            DataType::Null => 0,
         };
     }
     Ok(data_types)
@@ -14,6 +14,14 @@ pub enum RelationErrors {
     Error(String),
 
     InvalidInput(String),
+    /// An aggregate needs numbers but the column only holds nulls.
+    EmptyColumn(String),
+    /// An aggregate needs numbers but the column holds other values.
+    NonNumericColumn(String),
+    /// A cell failed an assertion, holds the column and the index of the row.
+    AssertionFailed(String, usize),
+    /// A column is shorter than the longest one, holds the column, its length and the longest length.
+    ColumnLengthMismatch(String, usize, usize),
 }
 
 impl From<csv::Error> for RelationErrors {
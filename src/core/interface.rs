@@ -1,7 +1,8 @@
 use crate::errors::*;
 use crate::dtype::*;
+use crate::rowset::RowSet;
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 // In-memory representation of a table/relation
 #[derive(Clone)]
@@ -15,10 +16,28 @@ pub struct ColumnStoreRelation {
     // Query helper
     pub select_columns: Vec<String>,
     /// Indexes
-    pub indices: HashMap<String, BTreeMap<String, Vec<usize>>>,
+    pub indices: HashMap<String, BTreeMap<OrdDataType, Vec<usize>>>,
+    /// Indexes with compact posting lists
+    pub compact_indices: HashMap<String, BTreeMap<OrdDataType, RowSet>>,
+    /// Indexes over several columns, keyed by the column names in index order
+    pub composite_indices: HashMap<Vec<String>, BTreeMap<Vec<OrdDataType>, Vec<usize>>>,
+    /// Columns known to be sorted ascending, set by `sort` and cleared by the operators that
+    /// modify them, code changing `columns` directly has to clear it as well
+    pub sorted_columns: HashSet<String>,
+    /// Values filling the columns left out by `add_partial_tuple`
+    pub defaults: HashMap<String, DataType>,
+}
+
+/// read-only projection of a relation borrowing its column storage, returned by `project_view`
+#[derive(Clone)]
+pub struct RelationView<'a> {
+    pub name: String,
+    pub select_columns: Vec<String>,
+    pub columns: HashMap<String, &'a [DataType]>,
 }
 
 /// available aggregate functions
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Aggregation {
     Count,
     Sum,
@@ -27,6 +46,32 @@ pub enum Aggregation {
     Average,
 }
 
+/// running state of an aggregate function, values are added one at a time
+#[derive(Debug, Clone)]
+pub struct Aggregator {
+    /// column the values come from, used in errors
+    pub column_name: String,
+    pub aggregation: Aggregation,
+    // number of values and of numeric values seen so far
+    pub(crate) count: usize,
+    pub(crate) numbers: usize,
+    // number of nulls so far
+    pub(crate) nulls: usize,
+    pub(crate) sum: f64,
+    // smallest and largest non-null value so far
+    pub(crate) min: Option<OrdDataType>,
+    pub(crate) max: Option<OrdDataType>,
+}
+
+/// time buckets for grouping date columns, weeks start on monday
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeBucket {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
 /// order for sort operator
 pub enum Order {
     Asc,
@@ -34,10 +79,141 @@ pub enum Order {
 }
 
 /// available join algorithms
+///
+/// The outer joins match rows like `HashJoin` and then add the unmatched rows of the left, the
+/// right or both relations with the columns of the other side set to `DataType::Null`.
 pub enum JoinType {
     NestedLoop,
     MergeJoin,
     HashJoin,
+    LeftOuter,
+    RightOuter,
+    FullOuter,
+}
+
+/// join columns kept in the result of a join
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum JoinKeyPolicy {
+    /// only the join column of the left relation
+    #[default]
+    KeepLeft,
+    /// the join columns of both relations, the right one is renamed to `<relation>.<column>`
+    /// if both have the same name
+    KeepBoth,
+    /// neither join column
+    DropBoth,
+}
+
+/// representation of the posting lists of an index
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IndexKind {
+    /// plain vectors of row numbers
+    #[default]
+    Rows,
+    /// roaring-bitmap-like row sets, smaller for columns with many duplicates
+    Compact,
+}
+
+/// predicate on a single value built at runtime, e.g. by `predicate_from_json`
+pub type ValuePredicate = Box<dyn Fn(&DataType) -> bool>;
+
+/// comparison operators for predicates on a value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// handling of NaN and infinite float values when loading data
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SpecialFloatPolicy {
+    /// load them as regular floats
+    #[default]
+    Keep,
+    /// replace them with `DataType::Null`
+    Nullify,
+    /// fail the load
+    Reject,
+}
+
+/// options for loading csv files
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// handling of `nan`/`inf` cells
+    pub special_floats: SpecialFloatPolicy,
+    /// number of lines skipped before the header, e.g. metadata lines
+    pub skip_rows: usize,
+    /// lines starting with this byte are ignored, e.g. `Some(b'#')`
+    pub comment_char: Option<u8>,
+    /// fail the load if a column holds values of more than one type, nulls and empty cells aside
+    pub enforce_homogeneous: bool,
+    /// remove leading and trailing whitespace from string cells, numbers are always parsed trimmed
+    pub trim: bool,
+}
+
+/// summary of a csv load
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    /// number of data rows read from the file
+    pub rows_loaded: usize,
+    /// number of null or empty cells per loaded column
+    pub null_counts: HashMap<String, usize>,
+}
+
+/// quoting of fields when saving csv files
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum QuoteStyle {
+    /// quote only fields containing delimiters, quotes or newlines
+    #[default]
+    Necessary,
+    /// quote every field
+    Always,
+    /// never quote, fields are written as they are
+    Never,
+    /// quote every field that is not a number
+    NonNumeric,
+}
+
+/// formatting of float values when saving csv files
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FloatFormat {
+    /// the `Display` output, whole numbers lose their fraction and load back as ints
+    #[default]
+    Display,
+    /// the shortest representation that loads back as the same float, e.g. `3.33` or `1.0`
+    RoundTrip,
+    /// a fixed number of decimal places
+    Fixed(usize),
+}
+
+/// float format of `pretty_print` and `print_plain`, saving with it writes floats as they are printed
+///
+/// `save` keeps using `FloatFormat::Display` by default so no precision is lost in files.
+pub const PRINT_FLOAT_FORMAT: FloatFormat = FloatFormat::Fixed(2);
+
+/// placeholder `pretty_print` and `print_plain` show for nulls, saved files leave null cells empty
+pub const PRINT_NULL: &str = "NULL";
+
+/// options for saving csv files
+#[derive(Debug, Clone)]
+pub struct SaveOptions {
+    /// when fields are quoted
+    pub quote_style: QuoteStyle,
+    /// how floats are written
+    pub float_format: FloatFormat,
+    /// byte separating the fields, a comma by default
+    pub delimiter: u8,
+}
+
+/// side of a binary operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinSide {
+    Left,
+    Right,
 }
 
 /// main interface for relations
@@ -50,6 +226,16 @@ pub trait Relation {
     /// returns the column name of the relation
     fn get_select_columns(&self) -> &Vec<String>;
 
+    /// returns true if both relations have the same columns in the same order with matching types
+    fn schema_compatible(&self, other: &ColumnStoreRelation) -> bool;
+
+    /// describes every difference in column order and column types between both relations
+    fn schema_diff(&self, other: &ColumnStoreRelation) -> Vec<String>;
+
+    /// describes the relation name and the column types in `select_columns` order as JSON,
+    /// e.g. `{"name": "people", "columns": [{"name": "id", "type": "Integer"}]}`
+    fn schema_json(&self) -> String;
+
     // load the relation from csv file    
     fn load_csv(
         &mut self,
@@ -59,29 +245,110 @@ pub trait Relation {
         select_columns: Vec<&str>,
     ) -> Result<(), RelationErrors>;
 
+    // load the relation from csv file with additional options, returns a summary of the loaded data,
+    // an empty delimiter is detected with `ColumnStoreRelation::sniff_delimiter`
+    fn load_csv_with_options(
+        &mut self,
+        path: &str,
+        table_name: &str,
+        delimiter: &str,
+        select_columns: Vec<&str>,
+        options: &LoadOptions,
+    ) -> Result<LoadReport, RelationErrors>;
+
     // saves the relation into a csv file
     fn save(&self, path: &str) -> Result<(), RelationErrors>;
 
-    /// returns the number of tuples stored in the relation
+    // saves the relation into a csv file with additional options
+    fn save_with_options(&self, path: &str, options: &SaveOptions) -> Result<(), RelationErrors>;
+
+    // renders the relation as csv text, the same as `save_with_options` writes it
+    fn to_csv_string(&self, options: &SaveOptions) -> Result<String, RelationErrors>;
+
+    /// returns the number of tuples stored in the relation, the length of its longest column
     fn num_tuples(&self) -> Result<usize, RelationErrors>;
 
+    /// checks that all columns have the same length, fails with `ColumnLengthMismatch` naming
+    /// the first shorter column in name order
+    fn validate_column_lengths(&self) -> Result<(), RelationErrors>;
+
+    /// estimated number of bytes held by the relation, its values, schema and indices
+    fn memory_footprint(&self) -> usize;
+
     /// prints the content of the relation to cmd in a table-like format
     fn pretty_print(&self);
 
+    /// renders the relation in the format of `pretty_print`, an empty relation is rendered
+    /// as its header followed by `(0 rows)`
+    fn format_pretty(&self) -> String;
+
+    /// prints the content of the relation as space aligned columns without borders
+    fn print_plain(&self);
+
+    /// renders the relation in the format of `print_plain`, columns are separated by two spaces
+    fn format_plain(&self) -> String;
+
     /// adds a tuple to a given relation
     fn add_tuple(&mut self, tuple: Vec<DataType>) -> Result<(), RelationErrors>;
 
+    /// sets the value `add_partial_tuple` uses for the column when it is not given
+    fn set_default(&mut self, column_name: &str, default: DataType) -> Result<(), RelationErrors>;
+
+    /// adds a tuple given as column value pairs, the other columns get their default or null
+    fn add_partial_tuple(&mut self, values: Vec<(&str, DataType)>) -> Result<(), RelationErrors>;
+
 
     /// deleted a tuple of a relation
     fn delete_tuple<F>(&mut self, column_name: &str, predicate: F) -> Result<usize, RelationErrors>
     where F: Fn(&DataType) -> bool;
 
+    /// deletes the rows at the given positions and returns how many were deleted
+    fn delete_rows(&mut self, indices: &[usize]) -> Result<usize, RelationErrors>;
+
     /// updates a tuple a given relation
     fn update_tuple<F, G>(&mut self, target_column: &str, filter_column: &str, predicate: F, update_func: G) -> Result<usize, RelationErrors>
     where
         F: Fn(&DataType) -> bool,
         G: Fn(&DataType) -> DataType;
 
+    /// replaces every row whose `key_col` equals `key` with `new_tuple`, given in `select_columns` order,
+    /// and returns the number of replaced rows
+    fn update_row_by_key(&mut self, key_col: &str, key: &DataType, new_tuple: Vec<DataType>) -> Result<usize, RelationErrors>;
+
+    /// sets `column_name` to `value` in the rows at the given positions and returns how many were updated
+    fn update_rows(&mut self, indices: &[usize], column_name: &str, value: DataType) -> Result<usize, RelationErrors>;
+
+    /// replaces every value of a column by the result of `f`, the values are mapped in parallel
+    /// unless the `parallel` feature is disabled
+    fn par_map_column<F>(&mut self, column_name: &str, f: F) -> Result<(), RelationErrors>
+    where F: Fn(&DataType) -> DataType + Sync;
+
+    /// converts every value of a column into the variant of `target`
+    fn cast_column(&mut self, column_name: &str, target: &DataType, mode: FloatToIntMode) -> Result<(), RelationErrors>;
+
+    /// casts every numeric column (only ints, floats and nulls) to the numeric type of `target`,
+    /// other columns are left untouched
+    fn promote_numeric(&mut self, target: DataType) -> Result<(), RelationErrors>;
+
+    /// reorders the columns to match the schema and casts each of them to its declared type,
+    /// a strict conform fails on columns missing on either side, otherwise extra columns are
+    /// dropped and missing ones are filled with nulls
+    fn conform_to_schema(&mut self, schema: Vec<(&str, DataType)>, strict: bool) -> Result<(), RelationErrors>;
+
+    /// creates a column holding the first non-null value of the given source columns per row
+    fn coalesce_columns(&mut self, new_name: &str, sources: Vec<&str>) -> Result<(), RelationErrors>;
+
+    /// prepends an integer column numbering the rows starting at 0
+    fn add_row_id(&mut self, column_name: &str) -> Result<(), RelationErrors>;
+
+    /// inserts a column at the given position of `select_columns`, the column has to be new and
+    /// hold one value per row
+    fn insert_column_at(&mut self, index: usize, column_name: &str, data: Vec<DataType>) -> Result<(), RelationErrors>;
+
+    /// splits the string values of a column at `delimiter` into up to `new_names.len()` new columns,
+    /// placed after the original one, the last column keeps the remainder and missing parts are null
+    fn split_column(&mut self, column_name: &str, delimiter: &str, new_names: Vec<&str>, keep_original: bool) -> Result<(), RelationErrors>;
+
 //####################################################################
 
 
@@ -94,28 +361,191 @@ pub trait Relation {
     fn select<F>(&mut self, column_name: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
         where F: Fn(&DataType) -> bool;
 
-    /// returns a relation with only selected columns
+    /// like `select` but also returns the original row indices of the matching rows
+    fn select_indexed<F>(&self, column_name: &str, predicate: F) -> Result<(ColumnStoreRelation, Vec<usize>), RelationErrors>
+        where F: Fn(&DataType) -> bool;
+
+    /// returns a relation holding the given rows in the given order, rows may repeat
+    fn take_rows(&self, indices: &[usize]) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// returns a relation with exactly one row per key in `keys`, in that order, taking the first
+    /// row with a matching `key_col` and filling the other columns of missing keys with `fill`
+    fn reindex(&self, key_col: &str, keys: &[DataType], fill: DataType) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// returns true if any value of the column satisfies the predicate, stops at the first match
+    fn any<F>(&self, column_name: &str, predicate: F) -> Result<bool, RelationErrors>
+        where F: Fn(&DataType) -> bool;
+
+    /// returns true if all values of the column satisfy the predicate, stops at the first mismatch
+    fn all<F>(&self, column_name: &str, predicate: F) -> Result<bool, RelationErrors>
+        where F: Fn(&DataType) -> bool;
+
+    /// returns at most `count` rows starting at row `offset`, an offset past the end gives a
+    /// relation with all columns but no rows
+    fn limit(&self, count: usize, offset: usize) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// removes duplicate rows, comparing the values of all selected columns, the first occurrence
+    /// of every row is kept in place
+    fn distinct(&self) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// keeps the first row of every distinct combination of values in `columns`, the other columns
+    /// are taken from that row
+    fn distinct_on(&self, columns: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// checks that every value of the column satisfies the predicate, fails with `AssertionFailed`
+    /// naming the first row that does not
+    fn assert_column<F>(&self, column_name: &str, predicate: F) -> Result<(), RelationErrors>
+        where F: Fn(&DataType) -> bool;
+
+    /// returns a relation with only selected columns, fails with `ColumnNotFound` if any of them is missing
     fn project(&self, columns_to_keep: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors>;
 
+    /// like `project` but the result borrows the columns instead of copying them
+    fn project_view(&self, columns_to_keep: Vec<&str>) -> Result<RelationView<'_>, RelationErrors>;
+
+    /// returns a relation with only selected columns, missing columns are skipped instead of failing
+    fn project_lenient(&self, columns_to_keep: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// splits a string column on the delimiter and emits one row per element, duplicating the other columns
+    fn explode(&self, column_name: &str, delimiter: &str) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// returns the unique values of a column in first-seen order
+    fn distinct_values(&self, column_name: &str) -> Result<Vec<DataType>, RelationErrors>;
+
+    /// names of the columns, in `select_columns` order, whose values are all distinct and non-null,
+    /// i.e. the columns that could serve as a primary key
+    fn candidate_keys(&self) -> Vec<String>;
+
+    /// returns the distinct values of a column with their number of occurrences in a `count` column,
+    /// sorted by count descending, ties keep their first-seen order
+    fn value_counts(&self, column_name: &str) -> Result<ColumnStoreRelation, RelationErrors>;
+
     /// execute an aggregate function on a given column
+    ///
+    /// Count counts every row including nulls, the other aggregations skip nulls. On an empty column
+    /// Count and Sum return 0, Min, Max and Average return `DataType::Null`. Sum fails with
+    /// `NonNumericColumn` on any other value that is not a number. Average skips such values, Min
+    /// and Max only consider strings without numbers in the column. Min, Max and Average fail with
+    /// `EmptyColumn` if the column only holds nulls and Average with `NonNumericColumn` if there is
+    /// no number at all.
     fn aggr(&self, column_name: &str, aggregation: Aggregation) -> Result<DataType, RelationErrors>;
 
+    /// like `aggr` but string cells are parsed as numbers, returns the aggregate and the number
+    /// of cells that were skipped because they are not numeric
+    fn aggr_coerce(&self, column_name: &str, aggregation: Aggregation) -> Result<(DataType, usize), RelationErrors>;
+
+    /// like `aggr` but also returns a label for the result, e.g. `sum_grade`
+    fn aggr_labeled(&self, column_name: &str, aggregation: Aggregation) -> Result<(String, DataType), RelationErrors>;
+
+    /// partitions the rows by the distinct values of `group_columns` and aggregates every group,
+    /// the result holds the group columns followed by one labeled column per aggregation, e.g. `avg_grade`
+    ///
+    /// Groups appear in the order of their first row and nulls form a group of their own. The
+    /// aggregations behave like `aggr` on the rows of each group, except that groups holding only
+    /// nulls get a null instead of failing.
+    fn group_by(&self, group_columns: Vec<&str>, aggregations: Vec<(&str, Aggregation)>) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// truncates the `YYYY-MM-DD` dates of `date_col` to the start of their bucket and aggregates `value_col`
+    /// per bucket, the result holds the bucket starts in ascending order and the labeled aggregate
+    fn group_by_time(&self, date_col: &str, bucket: TimeBucket, value_col: &str, aggregation: Aggregation) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// pearson correlation of two columns over the rows where both values are numeric
+    fn correlation(&self, col_a: &str, col_b: &str) -> Result<f64, RelationErrors>;
+
+    /// aggregates two columns and divides the results, a zero denominator is rejected
+    fn ratio(&self, numerator_col: &str, denominator_col: &str, num_agg: Aggregation, den_agg: Aggregation) -> Result<f64, RelationErrors>;
+
+    /// returns a copy of the relation with a `rank` column numbering the rows by `order_col` within each
+    /// partition, or over all rows without a partition column
+    ///
+    /// Ties share a rank and the next distinct value skips the tied positions (1, 2, 2, 4) like SQL `RANK`.
+    /// The rows keep their original order.
+    fn rank(&self, partition_col: Option<&str>, order_col: &str, order: Order) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// adds the column `cumulative_<aggregation>_<column_name>` holding for every row the aggregate
+    /// over the column up to and including that row, in the current row order
+    ///
+    /// Rows before the first value Min, Max and Average can use are null. Like `aggr`, Sum fails
+    /// as soon as it reaches a value that is not a number.
+    fn cumulative(&self, column_name: &str, aggregation: Aggregation) -> Result<ColumnStoreRelation, RelationErrors>;
+
     /// sorts the relation by given column and order
+    ///
+    /// Values are ordered by `DataType::sql_cmp`, so Ints and Floats sort numerically, followed by
+    /// strings and booleans. Nulls and NaN come last in both orders and the sort is stable.
     fn sort(&mut self, column_name: &str, order: Order) -> Result<(), RelationErrors>;
 
+    /// sorts the relation by several columns, each key breaks the ties of the ones before it,
+    /// rows equal in every key keep their order
+    fn sort_by(&mut self, keys: Vec<(&str, Order)>) -> Result<(), RelationErrors>;
+
+    /// sorts a copy of the relation by spilling sorted chunks of `chunk_rows` rows to `temp_dir` and merging them
+    fn external_sort(&self, column_name: &str, order: Order, chunk_rows: usize, temp_dir: &str) -> Result<ColumnStoreRelation, RelationErrors>;
+
     /// creates and index for a given column
     fn create_index(&mut self, column_name: &str) -> Result<(), String>;
 
+    /// creates an index for a given column with the chosen posting list representation,
+    /// replacing an existing index of the other kind
+    fn create_index_with(&mut self, column_name: &str, kind: IndexKind) -> Result<(), String>;
+
+    /// writes the index of a column to a file so it does not have to be rebuilt
+    fn save_index(&self, column_name: &str, path: &str) -> Result<(), RelationErrors>;
+
+    /// reads an index written by `save_index`, an index that does not match the current column is rejected
+    fn load_index(&mut self, column_name: &str, path: &str) -> Result<(), RelationErrors>;
+
     /// filters the relation by using a previously created/exisitng index
     fn index_select<F>(&self, column_name: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
     where F: Fn(&DataType) -> bool;
 
+    /// filters the relation by comparing against a value, only the matching key range of the index is scanned
+    fn index_select_cmp(&self, column_name: &str, op: CompareOp, value: &DataType) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// returns the rows whose indexed values lie in `[low, high]` in index order, nulls never match
+    ///
+    /// Only the keys within the range are visited. The range follows `DataType::sql_cmp`, so Ints and
+    /// Floats compare numerically and a range between two numbers never includes strings.
+    fn index_range_select(&self, column_name: &str, low: &DataType, high: &DataType) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// creates an index keyed by the values of several columns in the given order
+    fn create_composite_index(&mut self, columns: Vec<&str>) -> Result<(), String>;
+
+    /// returns the rows whose values of `columns` equal `values`, using the composite index over exactly these columns
+    fn composite_index_select(&self, columns: Vec<&str>, values: &[DataType]) -> Result<ColumnStoreRelation, RelationErrors>;
+
 //####################################################################    
 
 
 //############################# SESSION 3 ############################
 
+    /// appends the rows of `other` after matching its columns by name, the column order of this
+    /// relation is kept and both relations need the same column names; duplicate rows are removed
+    /// unless `all` is set
+    fn union_by_name(&self, other: &ColumnStoreRelation, all: bool) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// rows of either relation without duplicates, the relations need the same schema
+    fn union(&self, other: &ColumnStoreRelation) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// rows of this relation that also appear in `other` without duplicates, the relations need the same schema
+    fn intersect(&self, other: &ColumnStoreRelation) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// rows of this relation that do not appear in `other` without duplicates, the relations need the same schema
+    fn difference(&self, other: &ColumnStoreRelation) -> Result<ColumnStoreRelation, RelationErrors>;
+
+    /// compares two versions of a relation by the unique values of `key_col` and returns the rows
+    /// added in `other`, the rows removed from this relation and the rows of `other` whose other
+    /// values changed, both relations need the same column names
+    fn diff_keyed(&self, other: &ColumnStoreRelation, key_col: &str) -> Result<(ColumnStoreRelation, ColumnStoreRelation, ColumnStoreRelation), RelationErrors>;
+
+    /// returns a copy of the relation with every column renamed to `<prefix>.<column>`,
+    /// used on one side of a join to keep the output columns unambiguous
+    fn prefix_columns(&self, prefix: &str) -> ColumnStoreRelation;
+
     /// performs a nested loop join with another column
+    ///
+    /// Like every join the result holds the selected left columns in their order, then the selected
+    /// right columns in theirs, a right column named like a left one becomes `<right name>.<column>`.
     fn nested_loop_join<F>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
     where F: Fn(&DataType, &DataType) -> bool;
 
@@ -123,9 +553,27 @@ pub trait Relation {
     fn merge_join<F>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
     where F: Fn(&DataType, &DataType) -> bool;
 
-    /// performs a hash join with another column
+    /// performs a hash join with another column, the hash table is built on the smaller relation
     fn hash_join<F>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
     where F: Fn(&DataType, &DataType) -> bool;
 
+    /// performs an equi hash join on the keys mapped through `normalize`, e.g. lowercased and trimmed
+    /// strings, the result holds the original values and keeps both join columns as they may differ
+    fn hash_join_normalized<N>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, normalize: N) -> Result<ColumnStoreRelation, RelationErrors>
+    where N: Fn(&DataType) -> DataType;
+
+    /// performs a join with the given algorithm, fails once the result would exceed `max_result_rows` rows
+    fn join_with_limit<F>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F, jt: JoinType, max_result_rows: Option<usize>) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool;
+
+    /// performs a join with the given algorithm, `key_policy` decides which join columns are kept
+    fn join_with_key_policy<F>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F, jt: JoinType, key_policy: JoinKeyPolicy) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool;
+
+    /// performs a join with the given algorithm but only materializes `output_columns`, a column
+    /// present in both relations is taken from this one
+    fn join_project<F>(&self, other_column: &ColumnStoreRelation, r_col: &str, s_col: &str, output_columns: Vec<&str>, predicate: F, jt: JoinType) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool;
+
 //#################################################################### 
 }
\ No newline at end of file
@@ -2,4 +2,5 @@ pub mod dtype;
 pub mod errors;
 pub mod interface;
 pub mod relation;
+pub mod rowset;
 pub mod database;
\ No newline at end of file
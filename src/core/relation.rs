@@ -1,9 +1,15 @@
 use crate::dtype::*;
 use crate::errors::*;
 use crate::interface::*;
+use crate::rowset::RowSet;
 
-use std::collections::{BTreeMap, HashMap};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
+use std::path::Path;
+use std::ops::Bound;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use dashmap::DashMap;
 
@@ -14,82 +20,442 @@ impl ColumnStoreRelation {
             fields: HashMap::<String, DataType>::new(),
             columns: HashMap::<String, Vec<DataType>>::new(),
             select_columns: Vec::<String>::new(),
-            indices: HashMap::<String, BTreeMap<String, Vec<usize>>>::new(),
+            indices: HashMap::<String, BTreeMap<OrdDataType, Vec<usize>>>::new(),
+            compact_indices: HashMap::<String, BTreeMap<OrdDataType, RowSet>>::new(),
+            composite_indices: HashMap::<Vec<String>, BTreeMap<Vec<OrdDataType>, Vec<usize>>>::new(),
+            sorted_columns: HashSet::<String>::new(),
+            defaults: HashMap::<String, DataType>::new(),
         }
     }
 
+    /// builds a relation from row-major records whose values follow the order of `columns`
+    pub fn from_records(name: &str, columns: Vec<&str>, records: Vec<Vec<DataType>>) -> Result<ColumnStoreRelation, RelationErrors> {
+        let mut relation = ColumnStoreRelation::new();
+        relation.name = name.to_string();
+        relation.select_columns = columns.iter().map(|&c| c.to_string()).collect();
+        for &column_name in &columns {
+            relation.columns.insert(column_name.to_string(), Vec::with_capacity(records.len()));
+        }
+
+        for record in records {
+            relation.add_tuple(record)?;
+        }
+
+        Ok(relation)
+    }
+
+    /// guesses the delimiter of a csv file from its first lines, the candidate (`,`, `;`, tab, `|`)
+    /// that splits every line into the same number of fields and the most fields wins,
+    /// files without such a candidate are read as comma separated
+    pub fn sniff_delimiter(path: &str) -> Result<u8, RelationErrors> {
+        const SAMPLE_LINES: usize = 10;
+        let mut sample = String::new();
+        for line in BufReader::new(File::open(path)?).lines().take(SAMPLE_LINES) {
+            sample.push_str(&line?);
+            sample.push('\n');
+        }
+
+        let mut best = (b',', 1);
+        for delimiter in [b',', b';', b'\t', b'|'] {
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(sample.as_bytes());
+            let field_counts: Vec<usize> = reader.records()
+                .filter_map(|record| record.ok())
+                .map(|record| record.len())
+                .collect();
+            let consistent = field_counts.windows(2).all(|w| w[0] == w[1]);
+            if let (true, Some(&fields)) = (consistent, field_counts.first()) {
+                if fields > best.1 {
+                    best = (delimiter, fields);
+                }
+            }
+        }
+
+        Ok(best.0)
+    }
+
     pub fn get_columns(&self) -> &HashMap<String, Vec<DataType>> {
-        return &self.columns;
+        &self.columns
     }
-}
 
-pub fn calculate_max_width(vec: &Vec<DataType>, column_name: &str) -> Result<usize, &'static str> {
-    vec.iter().try_fold(column_name.len(), |max, datum| {
-        let formatted_datum = match datum {
-            DataType::Float(f) => format!("{:.2}", f),
-            _ => format!("{}", datum),
-        };
-        Ok(std::cmp::max(max, formatted_datum.len()))
-    }).or_else(|e : String| Err("Failed to calculate max width"))
-}
+    /// iterates over the rows of the relation with values in `select_columns` order
+    pub fn rows(&self) -> impl Iterator<Item = Vec<DataType>> + '_ {
+        let num_rows = self.num_tuples().unwrap_or(0);
+        (0..num_rows).map(move |row| {
+            self.select_columns.iter()
+                .map(|column_name| self.columns.get(column_name).and_then(|column| column.get(row)).cloned().unwrap_or(DataType::Null))
+                .collect()
+        })
+    }
 
+    /// materializes the relation row-major with values in `select_columns` order
+    pub fn to_records(&self) -> Vec<Vec<DataType>> {
+        self.rows().collect()
+    }
 
-impl Relation for ColumnStoreRelation {
+    /// side a hash join with `other` builds its hash table on, the smaller one and the left on ties
+    pub fn hash_join_build_side(&self, other: &ColumnStoreRelation) -> JoinSide {
+        let left_rows = self.num_tuples().unwrap_or(0);
+        let right_rows = other.num_tuples().unwrap_or(0);
+        if right_rows < left_rows {
+            JoinSide::Right
+        } else {
+            JoinSide::Left
+        }
+    }
 
-    fn get_table_name(&self) -> String {
-        return self.name.clone();
+    /// declares the type of every column without one in `fields` from its first non-null value
+    fn infer_missing_fields(&mut self) {
+        for (column_name, values) in &self.columns {
+            if !self.fields.contains_key(column_name) {
+                if let Some(field) = first_value_field(values) {
+                    self.fields.insert(column_name.clone(), field);
+                }
+            }
+        }
     }
 
-    fn num_tuples(&self) -> Result<usize, RelationErrors> {
-        let size = self.columns.iter()
-            .next()  // Retrieves the first (key, value) tuple if there is one
-            .map(|(_, v)| v.len())  // Maps the value to its length
-            .unwrap_or(0);
-        return Ok(size as usize);
+    /// fails unless a set operation can combine both relations, see `schema_diff`
+    fn check_set_schema(&self, other: &ColumnStoreRelation, operation: &str) -> Result<(), RelationErrors> {
+        let differences = self.schema_diff(other);
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(RelationErrors::Error(format!(
+                "Cannot {} {} and {} with different schemas: {}", operation, self.name, other.name, differences.join(", ")
+            )))
+        }
     }
 
-    fn load_csv(
-        &mut self,
-        path: &str,
-        table_name: &str, 
-        delimiter: &str, 
-        select_columns: Vec<&str>,
-    ) -> Result<(), RelationErrors> {
-        self.columns.clear();
-        self.name = table_name.to_string();
-        self.select_columns = select_columns.iter().map(|&s| s.to_string()).collect();
+    /// the first occurrence of every row that passes `keep`, rows are compared by their selected columns
+    fn filter_distinct_rows<F>(&self, keep: F) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&Vec<DataType>) -> bool
+    {
+        let mut seen = HashSet::new();
+        let records: Vec<Vec<DataType>> = self.rows()
+            .filter(|row| keep(row) && seen.insert(row.clone()))
+            .collect();
 
-        let file = File::open(path)?;
-        let mut rdr = csv::Reader::from_reader(file);
-        let headers = rdr.headers()?.clone();
+        let mut result_relation = ColumnStoreRelation::from_records(
+            &self.name,
+            self.select_columns.iter().map(String::as_str).collect(),
+            records,
+        )?;
+        result_relation.fields = self.fields.clone();
+        Ok(result_relation)
+    }
 
+    /// builds a relation holding the given rows of this relation
+    fn rows_relation(&self, rows: &[usize]) -> ColumnStoreRelation {
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = self.name.clone();
+        result_relation.fields = self.fields.clone(); 
 
-        for (_,h) in headers.iter().enumerate() {
-            if select_columns.contains(&h) {
-                self.columns.insert(h.to_string(), Vec::<DataType>::new());
-            }
+        for (key, value) in self.columns.iter() {
+            let filtered_column_data: Vec<DataType> = rows.iter()
+                .filter_map(|&i| value.get(i).cloned())
+                .collect();
+            result_relation.columns.insert(key.clone(), filtered_column_data);
         }
 
-        for result in rdr.records() {
-            let record = result?;
-            for (index, field) in record.iter().enumerate() {
-                if let Some(column_name) = headers.get(index) {
-                    if let Some(column) = self.columns.get_mut(column_name) {
-                        column.push(DataType::from_str(field)); 
+        result_relation.select_columns = self.select_columns.clone();
+        result_relation.infer_missing_fields();
+        result_relation
+    }
+
+    /// matching (left row, right row) pairs of a join in output order, outer joins pair their
+    /// unmatched rows with `NO_ROW` after the matching pairs
+    fn join_pairs<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F, jt: JoinType, max_result_rows: Option<usize>) -> Result<Vec<(usize, usize)>, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool 
+    {
+        // Ensure both columns exist in their respective relations
+        let r_col_data = self.columns.get(r_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(r_col.to_string()))?;
+        let s_col_data = other_relation.columns.get(s_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(s_col.to_string()))?;
+
+        // outer joins match with the hash join and add the unmatched rows afterwards
+        let (keep_left, keep_right) = match jt {
+            JoinType::LeftOuter => (true, false),
+            JoinType::RightOuter => (false, true),
+            JoinType::FullOuter => (true, true),
+            _ => (false, false),
+        };
+
+        let mut pairs = JoinPairs { pairs: Vec::new(), max_result_rows };
+        match jt {
+            JoinType::NestedLoop => {
+                for (i, r_value) in r_col_data.iter().enumerate().filter(|(_, value)| !value.is_null()) {
+                    for (j, s_value) in s_col_data.iter().enumerate().filter(|(_, value)| !value.is_null()) {
+                        if predicate(r_value, s_value) {
+                            pairs.push(i, j)?;
+                        }
+                    }
+                }
+            },
+            JoinType::MergeJoin => {
+                // Check if both columns are sorted, unless a sort already established it
+                let r_sorted = self.sorted_columns.contains(r_col) || is_sorted_ascending(r_col_data);
+                let s_sorted = other_relation.sorted_columns.contains(s_col) || is_sorted_ascending(s_col_data);
+                if !r_sorted || !s_sorted {
+                    return Err(RelationErrors::Error("Columns are not sorted for merge join".to_string()));
+                }
+
+                let mut i = 0;
+                let mut j = 0;
+                while i < r_col_data.len() && j < s_col_data.len() {
+                    if r_col_data[i].is_null() {
+                        i += 1;
+                    } else if s_col_data[j].is_null() {
+                        j += 1;
+                    } else if predicate(&r_col_data[i], &s_col_data[j]) {
+                        // pair the left row with the block of equal right keys, `j` stays at the
+                        // start of the block so the next left row with the same key replays it
+                        let mut k = j;
+                        while k < s_col_data.len() && s_col_data[k] == s_col_data[j] {
+                            pairs.push(i, k)?;
+                            k += 1;
+                        }
+                        i += 1;
+                    } else if compare_values(&r_col_data[i], &s_col_data[j]) == Ordering::Less {
+                        i += 1;
+                    } else {
+                        j += 1;
                     }
                 }
+            },
+            JoinType::HashJoin | JoinType::LeftOuter | JoinType::RightOuter | JoinType::FullOuter => match self.hash_join_build_side(other_relation) {
+                JoinSide::Left => {
+                    // Build the hash table for the first relation and probe it with the second
+                    let mut hash_table: HashMap<&DataType, Vec<usize>> = HashMap::new();
+                    for (i, value) in r_col_data.iter().enumerate().filter(|(_, value)| !value.is_null()) {
+                        hash_table.entry(value).or_default().push(i);
+                    }
+                    for (j, s_value) in s_col_data.iter().enumerate() {
+                        if let Some(indices) = hash_table.get(s_value) {
+                            for &i in indices.iter().filter(|&&i| predicate(&r_col_data[i], s_value)) {
+                                pairs.push(i, j)?;
+                            }
+                        }
+                    }
+                },
+                JoinSide::Right => {
+                    // Build the hash table for the second relation and probe it with the first
+                    let mut hash_table: HashMap<&DataType, Vec<usize>> = HashMap::new();
+                    for (j, value) in s_col_data.iter().enumerate().filter(|(_, value)| !value.is_null()) {
+                        hash_table.entry(value).or_default().push(j);
+                    }
+                    for (i, r_value) in r_col_data.iter().enumerate() {
+                        if let Some(indices) = hash_table.get(r_value) {
+                            for &j in indices.iter().filter(|&&j| predicate(r_value, &s_col_data[j])) {
+                                pairs.push(i, j)?;
+                            }
+                        }
+                    }
+                    // emit the rows in the same order as when building on the left
+                    pairs.pairs.sort_by_key(|&(i, j)| (j, i));
+                },
+            },
+        }
+
+        if keep_left {
+            let mut matched = vec![false; r_col_data.len()];
+            pairs.pairs.iter().for_each(|&(i, _)| matched[i] = true);
+            for i in (0..r_col_data.len()).filter(|&i| !matched[i]) {
+                pairs.push(i, NO_ROW)?;
             }
         }
+        if keep_right {
+            let mut matched = vec![false; s_col_data.len()];
+            pairs.pairs.iter().filter(|&&(_, j)| j != NO_ROW).for_each(|&(_, j)| matched[j] = true);
+            for j in (0..s_col_data.len()).filter(|&j| !matched[j]) {
+                pairs.push(NO_ROW, j)?;
+            }
+        }
+
+        Ok(pairs.pairs)
+    }
 
+    /// sorted rows of the index keys within `range` that pass `filter`, for either kind of index
+    fn index_rows<F>(&self, column_name: &str, range: (Bound<&OrdDataType>, Bound<&OrdDataType>), filter: F) -> Result<Vec<usize>, RelationErrors>
+    where
+        F: Fn(&OrdDataType) -> bool,
+    {
+        let mut rows = self.index_key_rows(column_name, range, filter)?;
+        rows.sort();
+        rows.dedup();
+        Ok(rows)
+    }
+
+    /// rows of the index keys within `range` that pass `filter` in key order, rows sharing a key in row order
+    fn index_key_rows<F>(&self, column_name: &str, range: (Bound<&OrdDataType>, Bound<&OrdDataType>), filter: F) -> Result<Vec<usize>, RelationErrors>
+    where
+        F: Fn(&OrdDataType) -> bool,
+    {
+        let rows: Vec<usize> = if let Some(index) = self.indices.get(column_name) {
+            index.range(range)
+                .filter(|(key, _)| filter(key))
+                .flat_map(|(_, rows)| rows.iter().copied())
+                .collect()
+        } else if let Some(index) = self.compact_indices.get(column_name) {
+            index.range(range)
+                .filter(|(key, _)| filter(key))
+                .flat_map(|(_, rows)| rows.iter())
+                .collect()
+        } else {
+            return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+        };
+
+        Ok(rows)
+    }
+
+    /// recreates every index after row positions changed
+    pub(crate) fn rebuild_indices(&mut self) -> Result<(), RelationErrors> {
+        let indexed_columns: Vec<String> = self.indices.keys().chain(self.compact_indices.keys()).cloned().collect();
+        for column_name in indexed_columns {
+            let kind = if self.indices.contains_key(&column_name) { IndexKind::Rows } else { IndexKind::Compact };
+            self.create_index_with(&column_name, kind).map_err(RelationErrors::Error)?;
+        }
+        let composites: Vec<Vec<String>> = self.composite_indices.keys().cloned().collect();
+        for columns in composites {
+            self.create_composite_index(columns.iter().map(String::as_str).collect()).map_err(RelationErrors::Error)?;
+        }
         Ok(())
     }
 
-    fn save(&self, path: &str) -> Result<(), RelationErrors> {
-        let file = File::create(path).map_err(|e| {
-            RelationErrors::WriteError(e.to_string())
-        })?;
+    /// recreates the existing indices of a column, composite ones included, after its values or
+    /// row positions changed
+    fn rebuild_index(&mut self, column_name: &str) -> Result<(), RelationErrors> {
+        let composites: Vec<Vec<String>> = self.composite_indices.keys()
+            .filter(|columns| columns.iter().any(|name| name == column_name))
+            .cloned()
+            .collect();
+        for columns in composites {
+            self.create_composite_index(columns.iter().map(String::as_str).collect()).map_err(RelationErrors::Error)?;
+        }
+
+        let kind = if self.indices.contains_key(column_name) {
+            IndexKind::Rows
+        } else if self.compact_indices.contains_key(column_name) {
+            IndexKind::Compact
+        } else {
+            return Ok(());
+        };
+        self.create_index_with(column_name, kind).map_err(RelationErrors::Error)
+    }
+
+    /// writes the whole relation to a file in the binary row format, indices are stored as
+    /// the names of the indexed columns and rebuilt by `read_binary`
+    pub(crate) fn write_binary(&self, path: &Path) -> Result<(), RelationErrors> {
+        let file = File::create(path).map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+        let mut writer = BufWriter::new(file);
+        fn names<'a>(names: impl Iterator<Item = &'a String>) -> Vec<DataType> {
+            names.map(|name| DataType::String(name.clone())).collect()
+        }
+
+        write_row(&mut writer, &[DataType::String(self.name.clone())])?;
+        write_row(&mut writer, &names(self.select_columns.iter()))?;
+        write_row(&mut writer, &self.fields.iter()
+            .flat_map(|(name, field)| [DataType::String(name.clone()), field.clone()])
+            .collect::<Vec<DataType>>())?;
+        write_row(&mut writer, &self.defaults.iter()
+            .flat_map(|(name, default)| [DataType::String(name.clone()), default.clone()])
+            .collect::<Vec<DataType>>())?;
+        write_row(&mut writer, &names(self.indices.keys()))?;
+        write_row(&mut writer, &names(self.compact_indices.keys()))?;
+        // every composite index as its number of columns followed by their names
+        write_row(&mut writer, &self.composite_indices.keys()
+            .flat_map(|columns| std::iter::once(DataType::Int(columns.len() as i32)).chain(names(columns.iter())))
+            .collect::<Vec<DataType>>())?;
+        write_row(&mut writer, &names(self.sorted_columns.iter()))?;
+        for (name, values) in &self.columns {
+            write_row(&mut writer, &[DataType::String(name.clone())])?;
+            write_row(&mut writer, values)?;
+        }
+        writer.flush().map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+
+        Ok(())
+    }
 
-        let mut writer = csv::Writer::from_writer(file);
+    /// reads a relation written by `write_binary`
+    pub(crate) fn read_binary(path: &Path) -> Result<ColumnStoreRelation, RelationErrors> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let invalid = || RelationErrors::ReadError(format!("Invalid relation file {}", path.display()));
+        let next_row = |reader: &mut BufReader<File>| read_row(reader)?.ok_or_else(invalid);
+        let names = |row: Vec<DataType>| -> Result<Vec<String>, RelationErrors> {
+            row.into_iter().map(|value| match value {
+                DataType::String(name) => Ok(name),
+                _ => Err(invalid()),
+            }).collect()
+        };
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.name = names(next_row(&mut reader)?)?.pop().ok_or_else(invalid)?;
+        relation.select_columns = names(next_row(&mut reader)?)?;
+        let fields = next_row(&mut reader)?;
+        for pair in fields.chunks(2) {
+            match pair {
+                [DataType::String(name), field] => relation.fields.insert(name.clone(), field.clone()),
+                _ => return Err(invalid()),
+            };
+        }
+        let defaults = next_row(&mut reader)?;
+        for pair in defaults.chunks(2) {
+            match pair {
+                [DataType::String(name), default] => relation.defaults.insert(name.clone(), default.clone()),
+                _ => return Err(invalid()),
+            };
+        }
+        let indexed = names(next_row(&mut reader)?)?;
+        let compact_indexed = names(next_row(&mut reader)?)?;
+        let mut composite_row = next_row(&mut reader)?.into_iter();
+        let mut composite_indexed = Vec::new();
+        while let Some(count) = composite_row.next() {
+            let count = match count {
+                DataType::Int(count) if count > 0 => count as usize,
+                _ => return Err(invalid()),
+            };
+            let columns = names(composite_row.by_ref().take(count).collect())?;
+            if columns.len() != count {
+                return Err(invalid());
+            }
+            composite_indexed.push(columns);
+        }
+        let sorted = names(next_row(&mut reader)?)?;
+        while let Some(name) = read_row(&mut reader)? {
+            let name = names(name)?.pop().ok_or_else(invalid)?;
+            relation.columns.insert(name, next_row(&mut reader)?);
+        }
+
+        for column_name in indexed {
+            relation.create_index_with(&column_name, IndexKind::Rows).map_err(RelationErrors::Error)?;
+        }
+        for column_name in compact_indexed {
+            relation.create_index_with(&column_name, IndexKind::Compact).map_err(RelationErrors::Error)?;
+        }
+        for columns in composite_indexed {
+            relation.create_composite_index(columns.iter().map(String::as_str).collect()).map_err(RelationErrors::Error)?;
+        }
+        relation.sorted_columns = sorted.into_iter().collect();
+
+        Ok(relation)
+    }
+
+    /// writes the relation as csv to `output` and hands it back once everything is flushed
+    fn write_csv<W: Write>(&self, output: W, options: &SaveOptions) -> Result<W, RelationErrors> {
+        let quote_style = match options.quote_style {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .quote_style(quote_style)
+            .delimiter(options.delimiter)
+            .from_writer(output);
 
         if !self.select_columns.is_empty() {
             writer.write_record(&self.select_columns).map_err(|e| {
@@ -104,7 +470,7 @@ impl Relation for ColumnStoreRelation {
 
             for column_name in &self.select_columns {
                 let value = if let Some(column) = self.columns.get(column_name) {
-                    column.get(row_index).map_or(String::new(), |v| v.to_string())
+                    column.get(row_index).map_or(String::new(), |v| format_value(v, options.float_format))
                 } else {
                     String::new()
                 };
@@ -116,287 +482,1484 @@ impl Relation for ColumnStoreRelation {
             })?;
         }
 
-        writer.flush().map_err(|e| {
+        writer.into_inner().map_err(|e| {
             RelationErrors::WriteError(e.to_string())
-        })?;
-
-        Ok(())
+        })
     }
-    
-    fn get_select_columns(&self) -> &Vec<String> {
-        return &self.select_columns;
+
+    /// view of all columns, used to share the printing code with `RelationView`
+    fn as_view(&self) -> RelationView<'_> {
+        RelationView {
+            name: self.name.clone(),
+            select_columns: self.select_columns.clone(),
+            columns: self.columns.iter().map(|(name, data)| (name.clone(), data.as_slice())).collect(),
+        }
     }
 
-    fn pretty_print(&self) {
-        let column_widths = DashMap::new();
+    /// builds the result of a join from pairs of matching (left row, right row) indices
+    ///
+    /// The result holds the selected left columns in their order followed by the selected right
+    /// columns in theirs, a right column named like a kept left column is renamed to `<right name>.<column>`.
+    fn join_rows(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, pairs: &[(usize, usize)], key_policy: JoinKeyPolicy) -> ColumnStoreRelation {
+        // Create a new relation to store the join result
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = format!("{}_{}_join", self.name, other_relation.name);
 
-        // Calculate column widths based on `select_columns` to maintain order
-        self.select_columns.par_iter().for_each(|column_name| {
-            if let Some(data) = self.columns.get(column_name) {
-                if let Some(max_width) = calculate_max_width(data, column_name).ok() {
-                    column_widths.insert(column_name.clone(), max_width);
-                }
+        // join columns left out of the result
+        let keep_left = |key: &String| key_policy != JoinKeyPolicy::DropBoth || key != r_col;
+        let keep_right = |key: &String| key_policy == JoinKeyPolicy::KeepBoth || key != s_col;
+
+        let left_columns: Vec<(&String, &Vec<DataType>)> = self.select_columns.iter()
+            .filter(|key| keep_left(key))
+            .filter_map(|key| self.columns.get(key).map(|values| (key, values)))
+            .collect();
+        let left_names: HashSet<&String> = left_columns.iter().map(|(key, _)| *key).collect();
+        let right_name = |key: &String| if left_names.contains(key) {
+            format!("{}.{}", other_relation.name, key)
+        } else {
+            key.clone()
+        };
+        let right_columns: Vec<(String, &String, &Vec<DataType>)> = other_relation.select_columns.iter()
+            .filter(|key| keep_right(key))
+            .filter_map(|key| other_relation.columns.get(key).map(|values| (right_name(key), key, values)))
+            .collect();
+
+        // Copy the field definitions of the kept columns from both relations
+        for (key, _) in &left_columns {
+            if let Some(field) = self.fields.get(*key) {
+                result_relation.fields.insert(key.to_string(), field.clone());
             }
-        });
+        }
+        for (name, key, _) in &right_columns {
+            if let Some(field) = other_relation.fields.get(*key) {
+                result_relation.fields.insert(name.clone(), field.clone());
+            }
+        }
 
-        // Create horizontal separator lines based on individual column widths
-        let horizontal_line: String = self.select_columns.iter()
-            .filter_map(|name| column_widths.get(name).map(|entry| *entry.value()))
-            .map(|width| "─".repeat(width + 2))
-            .collect::<Vec<String>>()
-            .join("┼");
+        result_relation.select_columns = left_columns.iter().map(|(key, _)| key.to_string())
+            .chain(right_columns.iter().map(|(name, _, _)| name.clone()))
+            .collect();
+        for (key, values) in &left_columns {
+            result_relation.columns.insert(key.to_string(), pairs.iter().map(|&(i, _)| row_value(values, i)).collect());
+        }
+        for (name, _, values) in &right_columns {
+            result_relation.columns.insert(name.clone(), pairs.iter().map(|&(_, j)| row_value(values, j)).collect());
+        }
 
-        let top_border = format!("┌{}┐", horizontal_line.replace('┼', "┬"));
-        let header_separator = format!("├{}┤", horizontal_line);
-        let bottom_border = format!("└{}┘", horizontal_line.replace('┼', "┴"));
+        result_relation.infer_missing_fields();
+        result_relation
+    }
+}
 
-        // Estimate the total capacity needed for the output string
-        let max_rows = self.columns.values().map(|col| col.len()).max().unwrap_or(0);
-        let estimated_capacity = top_border.len() + header_separator.len() + bottom_border.len()
-            + (self.select_columns.iter().map(|col| column_widths.get(col).map_or(0, |entry| *entry.value() + 4)).sum::<usize>() + 3) * (max_rows + 2);
+impl Default for ColumnStoreRelation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        let mut output = String::with_capacity(estimated_capacity);
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            special_floats: SpecialFloatPolicy::default(),
+            skip_rows: 0,
+            comment_char: None,
+            enforce_homogeneous: false,
+            trim: true,
+        }
+    }
+}
 
-        // Collect the top border
-        output.push_str(&top_border);
-        output.push('\n');
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions {
+            quote_style: QuoteStyle::default(),
+            float_format: FloatFormat::default(),
+            delimiter: b',',
+        }
+    }
+}
 
-        // Collect Headers with padding, in the order specified by `select_columns`
-        output.push('│');
-        for column_name in &self.select_columns {
-            if let Some(width) = column_widths.get(column_name).map(|entry| *entry.value()) {
-                output.push_str(&format!(" {:width$} │", column_name, width = width));
-            }
+pub fn calculate_max_width(vec: &[DataType], column_name: &str) -> Result<usize, &'static str> {
+    vec.iter().try_fold(column_name.len(), |max, datum| {
+        let formatted_datum = print_value(datum);
+        Ok(std::cmp::max(max, formatted_datum.len()))
+    }).map_err(|_: String| "Failed to calculate max width")
+}
+
+/// text of a value as it is printed and saved, only floats depend on the format
+pub fn format_value(value: &DataType, float_format: FloatFormat) -> String {
+    match (value, float_format) {
+        (DataType::Float(f), FloatFormat::RoundTrip) => ryu::Buffer::new().format(*f).to_string(),
+        (DataType::Float(f), FloatFormat::Fixed(decimals)) => format!("{:.*}", decimals, f),
+        _ => value.to_string(),
+    }
+}
+
+/// text of a value in `pretty_print` and `print_plain`
+fn print_value(value: &DataType) -> String {
+    match value {
+        DataType::Null => PRINT_NULL.to_string(),
+        _ => format_value(value, PRINT_FLOAT_FORMAT),
+    }
+}
+
+/// builds a predicate for `select` from a filter spec like `{"op": "gt", "value": 30}`
+///
+/// Supported ops are eq, ne, lt, le, gt, ge and contains (substring of a string value). Numbers
+/// are compared numerically, strings lexicographically and false sorts before true, values of
+/// another kind never match.
+pub fn predicate_from_json(spec: &str) -> Result<ValuePredicate, RelationErrors> {
+    let spec: serde_json::Value = serde_json::from_str(spec)
+        .map_err(|e| RelationErrors::InvalidInput(format!("Invalid filter spec: {}", e)))?;
+    let op = spec.get("op").and_then(|op| op.as_str())
+        .ok_or_else(|| RelationErrors::InvalidInput("Filter spec needs an \"op\" string".to_string()))?;
+    let value = match spec.get("value") {
+        Some(serde_json::Value::Number(number)) => match number.as_i64().and_then(|i| i32::try_from(i).ok()) {
+            Some(i) => DataType::Int(i),
+            None => DataType::Float(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        Some(serde_json::Value::String(s)) => DataType::String(s.clone()),
+        Some(serde_json::Value::Bool(b)) => DataType::Bool(*b),
+        _ => return Err(RelationErrors::InvalidInput("Filter spec needs a number, string or boolean \"value\"".to_string())),
+    };
+
+    let op = match op {
+        "eq" => CompareOp::Eq,
+        "ne" => CompareOp::Ne,
+        "lt" => CompareOp::Lt,
+        "le" => CompareOp::Le,
+        "gt" => CompareOp::Gt,
+        "ge" => CompareOp::Ge,
+        "contains" => return match value {
+            DataType::String(needle) => Ok(Box::new(move |cell| matches!(cell, DataType::String(s) if s.contains(&needle)))),
+            _ => Err(RelationErrors::InvalidInput("The contains filter needs a string value".to_string())),
+        },
+        other => return Err(RelationErrors::InvalidInput(format!("Unsupported filter op {}", other))),
+    };
+
+    Ok(Box::new(move |cell| compare_with(cell, op, &value)))
+}
+
+/// compares a value with `value` like the filters of `predicate_from_json`, numbers are compared
+/// numerically, strings lexicographically and false sorts before true, values of another kind
+/// (nulls included) never match
+pub fn compare_with(cell: &DataType, op: CompareOp, value: &DataType) -> bool {
+    let ordering = match (cell, value) {
+        (DataType::String(a), DataType::String(b)) => Some(a.cmp(b)),
+        (DataType::Int(a), DataType::Int(b)) => Some(a.cmp(b)),
+        (DataType::Bool(a), DataType::Bool(b)) => Some(a.cmp(b)),
+        (DataType::Int(_) | DataType::Float(_), DataType::Int(_) | DataType::Float(_)) => {
+            let as_f64 = |value: &DataType| match value {
+                DataType::Int(i) => *i as f64,
+                DataType::Float(f) => *f,
+                _ => f64::NAN,
+            };
+            as_f64(cell).partial_cmp(&as_f64(value))
+        },
+        _ => None,
+    };
+    ordering.is_some_and(|ordering| match op {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::Ne => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Le => ordering != Ordering::Greater,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::Ge => ordering != Ordering::Less,
+    })
+}
+
+/// first and last index key comparing equal to `value`, a number equals both the integer and the
+/// float with its value and the integer sorts first
+fn key_bounds(value: &DataType) -> (OrdDataType, OrdDataType) {
+    match value {
+        DataType::Int(i) => (OrdDataType(DataType::Int(*i)), OrdDataType(DataType::Float(*i as f64))),
+        DataType::Float(f) if f.fract() == 0.0 && *f >= i32::MIN as f64 && *f <= i32::MAX as f64 => {
+            (OrdDataType(DataType::Int(*f as i32)), OrdDataType(DataType::Float(*f)))
+        },
+        value => (OrdDataType(value.clone()), OrdDataType(value.clone())),
+    }
+}
+
+/// type of a loaded column, integers mixed with floats are floats and any other mix is a string
+fn infer_field(column: &[DataType]) -> DataType {
+    let mut field = DataType::Null;
+    for value in column {
+        field = match (&field, value) {
+            (_, DataType::Null) => field,
+            (_, DataType::String(s)) if s.is_empty() => field,
+            (DataType::Null, value) => value.clone(),
+            (DataType::Int(_), DataType::Float(_)) | (DataType::Float(_), DataType::Int(_)) => DataType::Float(0.0),
+            (field, value) if field.same_type(value) => field.clone(),
+            _ => DataType::String(String::new()),
+        };
+    }
+    match field {
+        DataType::Int(_) => DataType::Int(0),
+        DataType::Float(_) => DataType::Float(0.0),
+        DataType::Bool(_) => DataType::Bool(false),
+        _ => DataType::String(String::new()),
+    }
+}
+
+/// type of the first non-null value of a column, `None` if every value is null
+fn first_value_field(column: &[DataType]) -> Option<DataType> {
+    column.iter().find_map(|value| match value {
+        DataType::Int(_) => Some(DataType::Int(0)),
+        DataType::Float(_) => Some(DataType::Float(0.0)),
+        DataType::String(_) => Some(DataType::String(String::new())),
+        DataType::Bool(_) => Some(DataType::Bool(false)),
+        DataType::Null => None,
+    })
+}
+
+/// quotes a string for JSON output
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
         }
-        output.push('\n');
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// the csv reader splits on a single byte, so the delimiter has to be exactly one ASCII character
+fn delimiter_byte(delimiter: &str) -> Result<u8, RelationErrors> {
+    match delimiter.as_bytes() {
+        [byte] if byte.is_ascii() => Ok(*byte),
+        _ => Err(RelationErrors::InvalidInput("delimiter must be a single ASCII byte".to_string())),
+    }
+}
+
+/// executes an aggregate function on the values of a column, see `Relation::aggr`
+fn aggregate_values(column_name: &str, column: &[DataType], aggregation: Aggregation) -> Result<DataType, RelationErrors> {
+    let mut aggregator = Aggregator::new(column_name, aggregation);
+    for value in column {
+        aggregator.update(value);
+    }
+    aggregator.value()
+}
+
+impl Aggregator {
+    pub fn new(column_name: &str, aggregation: Aggregation) -> Aggregator {
+        Aggregator {
+            column_name: column_name.to_string(),
+            aggregation,
+            count: 0,
+            numbers: 0,
+            nulls: 0,
+            sum: 0.0,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// adds a value to the aggregate
+    pub fn update(&mut self, value: &DataType) {
+        self.count += 1;
+        if value.is_null() {
+            self.nulls += 1;
+            return;
+        }
+
+        // numbers take precedence over strings, so strings only decide the result of columns without numbers
+        let value = OrdDataType(value.clone());
+        let replaces = |current: &Option<OrdDataType>, wanted: Ordering| match current {
+            None => true,
+            Some(current) if current.rank() != value.rank() => value.rank() < current.rank(),
+            Some(current) => value.cmp(current) == wanted,
+        };
+        if replaces(&self.min, Ordering::Less) {
+            self.min = Some(value.clone());
+        }
+        if replaces(&self.max, Ordering::Greater) {
+            self.max = Some(value.clone());
+        }
+
+        let number = match value.0 {
+            DataType::Int(i) => i as f64,
+            DataType::Float(f) => f,
+            _ => return,
+        };
+        self.numbers += 1;
+        self.sum += number;
+    }
+
+    /// result of the aggregate over all values added so far, same as `Relation::aggr` on those values
+    pub fn value(&self) -> Result<DataType, RelationErrors> {
+        if self.count == 0 {
+            return match self.aggregation {
+                Aggregation::Count => Ok(DataType::Int(0)),
+                Aggregation::Sum => Ok(DataType::Float(0.0)),
+                Aggregation::Min | Aggregation::Max | Aggregation::Average => Ok(DataType::Null),
+            };
+        }
+
+        // nulls are skipped by all but Count, Sum needs every other value to be a number, Average skips
+        // the others but needs at least one, Min and Max return the smallest or largest value as stored,
+        // comparing strings only without numbers
+        let non_numeric = || RelationErrors::NonNumericColumn(self.column_name.clone());
+        let no_numbers = || if self.nulls == self.count {
+            RelationErrors::EmptyColumn(self.column_name.clone())
+        } else {
+            non_numeric()
+        };
+
+        match self.aggregation {
+            Aggregation::Count => Ok(DataType::Int(self.count as i32)),
+            Aggregation::Sum if self.numbers + self.nulls < self.count => Err(non_numeric()),
+            Aggregation::Sum => Ok(DataType::Float(self.sum)),
+            Aggregation::Min => self.min.as_ref().map(|min| min.0.clone()).ok_or_else(no_numbers),
+            Aggregation::Max => self.max.as_ref().map(|max| max.0.clone()).ok_or_else(no_numbers),
+            Aggregation::Average if self.numbers > 0 => Ok(DataType::Float(self.sum / self.numbers as f64)),
+            Aggregation::Average => Err(no_numbers()),
+        }
+    }
+}
+
+/// name of an aggregate result, e.g. `sum_grade`
+fn aggregation_label(column_name: &str, aggregation: Aggregation) -> String {
+    let prefix = match aggregation {
+        Aggregation::Count => "count",
+        Aggregation::Sum => "sum",
+        Aggregation::Min => "min",
+        Aggregation::Max => "max",
+        Aggregation::Average => "avg",
+    };
+    format!("{}_{}", prefix, column_name)
+}
+
+/// days since 1970-01-01 of a proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// parses a `YYYY-MM-DD` date and formats the first day of its bucket the same way
+fn truncate_date(date: &str, bucket: TimeBucket) -> Option<String> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day < 1 {
+        return None;
+    }
+    // reject days past the end of the month, they would roll over into the next one
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let (year, month, day) = match bucket {
+        TimeBucket::Day => (year, month, day),
+        TimeBucket::Week => civil_from_days(days - (days + 3).rem_euclid(7)),
+        TimeBucket::Month => (year, month, 1),
+        TimeBucket::Year => (year, 1, 1),
+    };
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// row of an outer join pair whose other side had no match
+const NO_ROW: usize = usize::MAX;
+
+/// value of a join result row, `NO_ROW` gives a null
+fn row_value(values: &[DataType], row: usize) -> DataType {
+    if row == NO_ROW {
+        DataType::Null
+    } else {
+        values[row].clone()
+    }
+}
+
+/// matching (left row, right row) pairs of a join, aborts once more than `max_result_rows` are collected
+struct JoinPairs {
+    pairs: Vec<(usize, usize)>,
+    max_result_rows: Option<usize>,
+}
+
+impl JoinPairs {
+    fn push(&mut self, i: usize, j: usize) -> Result<(), RelationErrors> {
+        if let Some(max_rows) = self.max_result_rows {
+            if self.pairs.len() >= max_rows {
+                return Err(RelationErrors::Error(format!("result exceeded {} rows", max_rows)));
+            }
+        }
+        self.pairs.push((i, j));
+        Ok(())
+    }
+}
+
+/// ascending ordering used by the sort operators, `DataType::sql_cmp` with nulls last
+pub fn compare_values(a: &DataType, b: &DataType) -> Ordering {
+    a.sql_cmp(b, NullsOrder::Last)
+}
+
+/// true if no value is smaller than its predecessor, the order merge joins rely on
+fn is_sorted_ascending(column: &[DataType]) -> bool {
+    !column.windows(2).any(|w| compare_values(&w[1], &w[0]) == Ordering::Less)
+}
+
+/// ordering used by the sort operators for the given order, nulls and NaN sort last in both orders
+pub fn compare_values_ordered(a: &DataType, b: &DataType, order: &Order) -> Ordering {
+    match order {
+        Order::Asc => compare_values(a, b),
+        Order::Desc => {
+            let is_nan = |value: &DataType| matches!(value, DataType::Float(f) if f.is_nan());
+            let is_number = |value: &DataType| matches!(value, DataType::Int(_) | DataType::Float(_));
+            match (a, b) {
+                (a, b) if is_nan(a) && is_number(b) && !is_nan(b) => Ordering::Greater,
+                (a, b) if is_nan(b) && is_number(a) && !is_nan(a) => Ordering::Less,
+                _ => b.sql_cmp(a, NullsOrder::First),
+            }
+        },
+    }
+}
+
+/// writes a row as a length-prefixed record of serialized values
+fn write_row<W: Write>(writer: &mut W, row: &[DataType]) -> Result<(), RelationErrors> {
+    let bytes = serialize_data_types(row).map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+    writer.write_all(&(bytes.len() as u64).to_be_bytes()).map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+    writer.write_all(&bytes).map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+    Ok(())
+}
+
+/// reads the next row written by `write_row`, returns None at the end of the stream
+fn read_row<R: Read>(reader: &mut R) -> Result<Option<Vec<DataType>>, RelationErrors> {
+    let mut len_bytes = [0u8; 8];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(()) => {},
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut bytes = vec![0u8; u64::from_be_bytes(len_bytes) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(deserialize_data_types(&bytes)?))
+}
+
+impl Relation for ColumnStoreRelation {
+
+    fn get_table_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn num_tuples(&self) -> Result<usize, RelationErrors> {
+        Ok(self.columns.values().map(|column| column.len()).max().unwrap_or(0))
+    }
+
+    fn validate_column_lengths(&self) -> Result<(), RelationErrors> {
+        let num_rows = self.num_tuples()?;
+        let mut names: Vec<&String> = self.columns.keys().collect();
+        names.sort();
+        match names.into_iter().find(|&name| self.columns[name].len() != num_rows) {
+            Some(name) => Err(RelationErrors::ColumnLengthMismatch(name.clone(), self.columns[name].len(), num_rows)),
+            None => Ok(()),
+        }
+    }
+
+    fn memory_footprint(&self) -> usize {
+        let value_size = |value: &DataType| std::mem::size_of::<DataType>() + match value {
+            DataType::String(s) => s.capacity(),
+            _ => 0,
+        };
+        let name_size = |name: &String| std::mem::size_of::<String>() + name.capacity();
+
+        let columns: usize = self.columns.iter()
+            .map(|(name, values)| name_size(name) + values.iter().map(value_size).sum::<usize>()
+                + (values.capacity() - values.len()) * std::mem::size_of::<DataType>())
+            .sum();
+        let fields: usize = self.fields.iter().chain(&self.defaults).map(|(name, field)| name_size(name) + value_size(field)).sum();
+        let indices: usize = self.indices.iter()
+            .map(|(name, index)| name_size(name) + index.iter()
+                .map(|(key, rows)| value_size(&key.0) + rows.capacity() * std::mem::size_of::<usize>())
+                .sum::<usize>())
+            .sum();
+        let compact_indices: usize = self.compact_indices.iter()
+            .map(|(name, index)| name_size(name) + index.iter()
+                .map(|(key, rows)| value_size(&key.0) + rows.len() * std::mem::size_of::<u16>())
+                .sum::<usize>())
+            .sum();
+
+        let composite_indices: usize = self.composite_indices.iter()
+            .map(|(names, index)| names.iter().map(name_size).sum::<usize>() + index.iter()
+                .map(|(key, rows)| key.iter().map(|k| value_size(&k.0)).sum::<usize>() + rows.capacity() * std::mem::size_of::<usize>())
+                .sum::<usize>())
+            .sum();
+
+        std::mem::size_of::<Self>() + name_size(&self.name) + columns + fields + indices + compact_indices + composite_indices
+            + self.select_columns.iter().chain(&self.sorted_columns).map(name_size).sum::<usize>()
+    }
+
+    fn load_csv(
+        &mut self,
+        path: &str,
+        table_name: &str, 
+        delimiter: &str, 
+        select_columns: Vec<&str>,
+    ) -> Result<(), RelationErrors> {
+        self.load_csv_with_options(path, table_name, delimiter, select_columns, &LoadOptions::default()).map(|_| ())
+    }
+
+    fn load_csv_with_options(
+        &mut self,
+        path: &str,
+        table_name: &str,
+        delimiter: &str,
+        select_columns: Vec<&str>,
+        options: &LoadOptions,
+    ) -> Result<LoadReport, RelationErrors> {
+        let delimiter = if delimiter.is_empty() {
+            ColumnStoreRelation::sniff_delimiter(path)?
+        } else {
+            delimiter_byte(delimiter)?
+        };
+        self.columns.clear();
+        self.fields.clear();
+        self.sorted_columns.clear();
+        self.name = table_name.to_string();
+        self.select_columns = select_columns.iter().map(|&s| s.to_string()).collect();
+
+        let mut file = BufReader::new(File::open(path)?);
+        let mut skipped = String::new();
+        for _ in 0..options.skip_rows {
+            skipped.clear();
+            if file.read_line(&mut skipped)? == 0 {
+                break;
+            }
+        }
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .comment(options.comment_char)
+            .from_reader(file);
+        let headers = rdr.headers()?.clone();
+
+
+        let mut report = LoadReport::default();
+        for h in headers.iter() {
+            if select_columns.contains(&h) {
+                self.columns.insert(h.to_string(), Vec::<DataType>::new());
+                report.null_counts.insert(h.to_string(), 0);
+            }
+        }
+
+        for result in rdr.records() {
+            let record = result?;
+            report.rows_loaded += 1;
+            for (index, field) in record.iter().enumerate() {
+                if let Some(column_name) = headers.get(index) {
+                    if let Some(column) = self.columns.get_mut(column_name) {
+                        let value = match DataType::from_str(field) {
+                            DataType::Float(f) if !f.is_finite() => match options.special_floats {
+                                SpecialFloatPolicy::Keep => DataType::Float(f),
+                                SpecialFloatPolicy::Nullify => DataType::Null,
+                                SpecialFloatPolicy::Reject => return Err(RelationErrors::InvalidInput(
+                                    format!("Special float value {} in column {}", field, column_name)
+                                )),
+                            },
+                            // empty cells are missing values, with trimming also cells holding only whitespace
+                            DataType::String(s) if s.is_empty() && (options.trim || field.is_empty()) => DataType::Null,
+                            DataType::String(_) if !options.trim => DataType::String(field.to_string()),
+                            value => value,
+                        };
+                        if value.is_null() || matches!(&value, DataType::String(s) if s.is_empty()) {
+                            *report.null_counts.get_mut(column_name).unwrap() += 1;
+                        }
+                        column.push(value); 
+                    }
+                }
+            }
+        }
+
+        if options.enforce_homogeneous {
+            for column_name in &self.select_columns {
+                let mut values = self.columns.get(column_name).into_iter().flatten()
+                    .filter(|value| !value.is_null() && !matches!(value, DataType::String(s) if s.is_empty()));
+                if let Some(first) = values.next() {
+                    if let Some(other) = values.find(|value| !value.same_type(first)) {
+                        return Err(RelationErrors::InvalidInput(format!(
+                            "Column {} has mixed types: {} and {}", column_name, first.type_name(), other.type_name()
+                        )));
+                    }
+                }
+            }
+        }
+
+        for (column_name, column) in &self.columns {
+            self.fields.insert(column_name.clone(), infer_field(column));
+        }
+
+        // indices of columns that are loaded again are rebuilt from the new rows, the others are dropped
+        let columns = &self.columns;
+        self.indices.retain(|column_name, _| columns.contains_key(column_name));
+        self.compact_indices.retain(|column_name, _| columns.contains_key(column_name));
+        self.composite_indices.retain(|index_columns, _| index_columns.iter().all(|column_name| columns.contains_key(column_name)));
+        self.rebuild_indices()?;
+
+        Ok(report)
+    }
+
+    fn save(&self, path: &str) -> Result<(), RelationErrors> {
+        self.save_with_options(path, &SaveOptions::default())
+    }
+
+    fn save_with_options(&self, path: &str, options: &SaveOptions) -> Result<(), RelationErrors> {
+        let file = File::create(path).map_err(|e| {
+            RelationErrors::WriteError(e.to_string())
+        })?;
+        self.write_csv(file, options)?;
+
+        Ok(())
+    }
+
+    fn to_csv_string(&self, options: &SaveOptions) -> Result<String, RelationErrors> {
+        let bytes = self.write_csv(Vec::new(), options)?;
+        String::from_utf8(bytes).map_err(|e| RelationErrors::WriteError(e.to_string()))
+    }
+    
+    fn get_select_columns(&self) -> &Vec<String> {
+        &self.select_columns
+    }
+
+    fn schema_compatible(&self, other: &ColumnStoreRelation) -> bool {
+        self.schema_diff(other).is_empty()
+    }
+
+    fn schema_diff(&self, other: &ColumnStoreRelation) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        let num_columns = std::cmp::max(self.select_columns.len(), other.select_columns.len());
+        for position in 0..num_columns {
+            match (self.select_columns.get(position), other.select_columns.get(position)) {
+                (Some(left), Some(right)) if left != right => {
+                    differences.push(format!("column {}: '{}' vs '{}'", position, left, right));
+                },
+                (Some(left), None) => differences.push(format!("column '{}' missing in {}", left, other.name)),
+                (None, Some(right)) => differences.push(format!("column '{}' missing in {}", right, self.name)),
+                _ => {},
+            }
+        }
+
+        // types are only compared where both relations declare one
+        for column_name in &self.select_columns {
+            if let (Some(left), Some(right)) = (self.fields.get(column_name), other.fields.get(column_name)) {
+                if !left.same_type(right) {
+                    differences.push(format!("column '{}': {} vs {}", column_name, left.type_name(), right.type_name()));
+                }
+            }
+        }
+
+        differences
+    }
+
+    fn schema_json(&self) -> String {
+        let columns: Vec<String> = self.select_columns.iter()
+            .map(|column_name| {
+                let field = self.fields.get(column_name).unwrap_or(&DataType::Null);
+                format!("{{\"name\": {}, \"type\": {}}}", json_string(column_name), field.to_json())
+            })
+            .collect();
+        format!("{{\"name\": {}, \"columns\": [{}]}}", json_string(&self.name), columns.join(", "))
+    }
+
+    fn pretty_print(&self) {
+        println!("{}", self.format_pretty());
+    }
+
+    fn format_pretty(&self) -> String {
+        self.as_view().format_pretty()
+    }
+
+    fn print_plain(&self) {
+        print!("{}", self.format_plain());
+    }
+
+    fn format_plain(&self) -> String {
+        let column_widths = self.as_view().column_widths();
+        let column_names: Vec<&String> = self.select_columns.iter()
+            .filter(|name| column_widths.contains_key(*name))
+            .collect();
+
+        let format_line = |cells: Vec<String>| {
+            let line = column_names.iter().zip(cells)
+                .map(|(name, cell)| format!("{:width$}", cell, width = *column_widths.get(*name).unwrap()))
+                .collect::<Vec<String>>()
+                .join("  ");
+            format!("{}\n", line.trim_end())
+        };
+
+        let mut output = format_line(column_names.iter().map(|name| name.to_string()).collect());
+        let max_rows = self.columns.values().map(|col| col.len()).max().unwrap_or(0);
+        for row in 0..max_rows {
+            output.push_str(&format_line(column_names.iter().map(|name| {
+                self.columns[*name].get(row).map_or(String::new(), print_value)
+            }).collect()));
+        }
+        output
+    }
+
+    fn select<F>(&mut self, column_name: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
+    where
+        F: Fn(&DataType) -> bool,
+    {
+        // First, check if the column exists
+        let column_data = self.columns.get(column_name)
+            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        // Collect indices of rows where the predicate is true
+        let matching_indices: Vec<usize> = column_data.iter()
+            .enumerate()
+            .filter_map(|(index, datum)| {
+                if predicate(datum) {
+                    Some(index)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        // Create a new IntermediateRelation to hold the filtered results
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = self.name.clone(); // Assuming you want to keep the same relation name
+
+        // Filter all columns based on matching indices
+        for (key, values) in &self.columns {
+            let filtered_values: Vec<DataType> = matching_indices.iter()
+                .filter_map(|&index| values.get(index).cloned())
+                .collect();
+            result_relation.columns.insert(key.clone(), filtered_values);
+        }
+
+        // the result has indices of the same kinds, built over its own row positions
+        for key in self.indices.keys() {
+            result_relation.indices.insert(key.clone(), BTreeMap::new());
+        }
+        for key in self.compact_indices.keys() {
+            result_relation.compact_indices.insert(key.clone(), BTreeMap::new());
+        }
+        for columns in self.composite_indices.keys() {
+            result_relation.composite_indices.insert(columns.clone(), BTreeMap::new());
+        }
+        result_relation.rebuild_indices()?;
+
+        // Maintain selected column order and fields in the new relation
+        result_relation.select_columns = self.select_columns.clone();
+        result_relation.fields = self.fields.clone();
+        result_relation.infer_missing_fields();
+
+        Ok(result_relation)
+    }
+
+    fn select_indexed<F>(&self, column_name: &str, predicate: F) -> Result<(ColumnStoreRelation, Vec<usize>), RelationErrors>
+    where
+        F: Fn(&DataType) -> bool,
+    {
+        let column_data = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        let matching_indices: Vec<usize> = column_data.iter()
+            .enumerate()
+            .filter(|(_, datum)| predicate(datum))
+            .map(|(index, _)| index)
+            .collect();
+
+        Ok((self.rows_relation(&matching_indices), matching_indices))
+    }
+
+    fn take_rows(&self, indices: &[usize]) -> Result<ColumnStoreRelation, RelationErrors> {
+        let num_rows = self.num_tuples()?;
+        if let Some(&row) = indices.iter().find(|&&row| row >= num_rows) {
+            return Err(RelationErrors::InvalidInput(format!("Row index {} out of range for {} rows", row, num_rows)));
+        }
+
+        Ok(self.rows_relation(indices))
+    }
+
+    fn reindex(&self, key_col: &str, keys: &[DataType], fill: DataType) -> Result<ColumnStoreRelation, RelationErrors> {
+        let key_column = self.columns.get(key_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(key_col.to_string()))?;
+
+        let mut first_rows: HashMap<&DataType, usize> = HashMap::with_capacity(key_column.len());
+        for (row, value) in key_column.iter().enumerate() {
+            first_rows.entry(value).or_insert(row);
+        }
+        let rows: Vec<Option<usize>> = keys.iter().map(|key| first_rows.get(key).copied()).collect();
+
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = self.name.clone();
+        result_relation.fields = self.fields.clone();
+        result_relation.select_columns = self.select_columns.clone();
+        for (column_name, values) in &self.columns {
+            let column = if column_name == key_col {
+                keys.to_vec()
+            } else {
+                rows.iter().map(|row| row.map_or_else(|| fill.clone(), |row| values[row].clone())).collect()
+            };
+            result_relation.columns.insert(column_name.clone(), column);
+        }
+
+        Ok(result_relation)
+    }
+
+    fn any<F>(&self, column_name: &str, predicate: F) -> Result<bool, RelationErrors>
+    where
+        F: Fn(&DataType) -> bool,
+    {
+        let column_data = self.columns.get(column_name)
+            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        Ok(column_data.iter().any(predicate))
+    }
+
+    fn all<F>(&self, column_name: &str, predicate: F) -> Result<bool, RelationErrors>
+    where
+        F: Fn(&DataType) -> bool,
+    {
+        let column_data = self.columns.get(column_name)
+            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        Ok(column_data.iter().all(predicate))
+    }
+
+    fn limit(&self, count: usize, offset: usize) -> Result<ColumnStoreRelation, RelationErrors> {
+        let num_rows = self.num_tuples()?;
+        let start = offset.min(num_rows);
+        let end = start.saturating_add(count).min(num_rows);
+        let rows: Vec<usize> = (start..end).collect();
+        Ok(self.rows_relation(&rows))
+    }
+
+    fn distinct(&self) -> Result<ColumnStoreRelation, RelationErrors> {
+        self.distinct_on(self.select_columns.iter().map(String::as_str).collect())
+    }
+
+    fn distinct_on(&self, columns: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors> {
+        let keys = columns.iter()
+            .map(|&column_name| self.columns.get(column_name).ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string())))
+            .collect::<Result<Vec<&Vec<DataType>>, RelationErrors>>()?;
+
+        let mut seen = HashSet::new();
+        let rows: Vec<usize> = (0..self.num_tuples()?)
+            .filter(|&row| seen.insert(keys.iter().map(|key| key.get(row)).collect::<Vec<Option<&DataType>>>()))
+            .collect();
+        Ok(self.rows_relation(&rows))
+    }
+
+    fn assert_column<F>(&self, column_name: &str, predicate: F) -> Result<(), RelationErrors>
+    where
+        F: Fn(&DataType) -> bool,
+    {
+        let column_data = self.columns.get(column_name)
+            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        match column_data.iter().position(|datum| !predicate(datum)) {
+            Some(row) => Err(RelationErrors::AssertionFailed(column_name.to_string(), row)),
+            None => Ok(()),
+        }
+    }
+
+    fn project(&self, columns_to_keep: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors> {
+        // Create a new ColumnStoreRelation to hold the result
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = self.name.clone(); // Keep the same name
+
+        // Iterate over the specified columns, adding them to the new relation if they exist
+        for column_name in columns_to_keep {
+            if let Some(data) = self.columns.get(column_name) {
+                // If the column exists, copy it and its declared type to the new relation
+                result_relation.columns.insert(column_name.to_string(), data.clone());
+                if let Some(field) = self.fields.get(column_name) {
+                    result_relation.fields.insert(column_name.to_string(), field.clone());
+                }
+                // Also, add to select_columns to maintain the order
+                result_relation.select_columns.push(column_name.to_string());
+            } else {
+                // If a specified column does not exist, you might want to return an error
+                return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+                // Or simply skip this column
+                // continue;
+            }
+        }
+        result_relation.infer_missing_fields();
+
+        Ok(result_relation)
+    }
+
+    fn project_view(&self, columns_to_keep: Vec<&str>) -> Result<RelationView<'_>, RelationErrors> {
+        let mut view = RelationView {
+            name: self.name.clone(),
+            select_columns: Vec::with_capacity(columns_to_keep.len()),
+            columns: HashMap::with_capacity(columns_to_keep.len()),
+        };
+        for column_name in columns_to_keep {
+            let data = self.columns.get(column_name)
+                .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+            view.columns.insert(column_name.to_string(), data.as_slice());
+            view.select_columns.push(column_name.to_string());
+        }
+        Ok(view)
+    }
+
+    fn project_lenient(&self, columns_to_keep: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors> {
+        let existing_columns = columns_to_keep.into_iter()
+            .filter(|column_name| self.columns.contains_key(*column_name))
+            .collect();
+        self.project(existing_columns)
+    }
+
+    fn explode(&self, column_name: &str, delimiter: &str) -> Result<ColumnStoreRelation, RelationErrors> {
+        let column_data = self.columns.get(column_name)
+            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        if delimiter.is_empty() {
+            return Err(RelationErrors::InvalidInput("Delimiter must not be empty".to_string()));
+        }
+
+        let mut source_rows = Vec::new();
+        let mut exploded = Vec::new();
+        for (row, value) in column_data.iter().enumerate() {
+            match value {
+                DataType::String(s) => {
+                    for part in s.split(delimiter) {
+                        source_rows.push(row);
+                        exploded.push(DataType::from_str(part));
+                    }
+                },
+                // non-string values are passed through as a single row
+                other => {
+                    source_rows.push(row);
+                    exploded.push(other.clone());
+                },
+            }
+        }
+
+        // the parts are typed on their own, e.g. a string column may explode into numbers
+        let mut result_relation = self.rows_relation(&source_rows);
+        result_relation.columns.insert(column_name.to_string(), exploded);
+        result_relation.fields.remove(column_name);
+        result_relation.infer_missing_fields();
+        Ok(result_relation)
+    }
+
+    fn distinct_values(&self, column_name: &str) -> Result<Vec<DataType>, RelationErrors> {
+        let column = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        let mut seen = HashSet::new();
+        Ok(column.iter().filter(|value| seen.insert(*value)).cloned().collect())
+    }
+
+    fn candidate_keys(&self) -> Vec<String> {
+        self.select_columns.iter()
+            .filter(|column_name| match self.columns.get(*column_name) {
+                Some(column) => {
+                    let mut seen = HashSet::with_capacity(column.len());
+                    column.iter().all(|value| !value.is_null() && seen.insert(value))
+                },
+                None => false,
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn value_counts(&self, column_name: &str) -> Result<ColumnStoreRelation, RelationErrors> {
+        let column = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        let mut counts: HashMap<&DataType, i32> = HashMap::new();
+        for value in column {
+            *counts.entry(value).or_default() += 1;
+        }
+
+        let mut records: Vec<Vec<DataType>> = self.distinct_values(column_name)?.into_iter()
+            .map(|value| {
+                let count = counts[&value];
+                vec![value, DataType::Int(count)]
+            })
+            .collect();
+        records.sort_by(|a, b| compare_values(&b[1], &a[1]));
+
+        let mut result_relation = ColumnStoreRelation::from_records(&self.name, vec![column_name, "count"], records)?;
+        result_relation.infer_missing_fields();
+        Ok(result_relation)
+    }
+
+    fn aggr(&self, column_name: &str, aggregation: Aggregation) -> Result<DataType, RelationErrors> {
+        match self.columns.get(column_name) {
+            Some(column) => aggregate_values(column_name, column, aggregation),
+            None => Err(RelationErrors::ColumnNotFound(column_name.to_string())),
+        }
+    }
+
+    fn aggr_coerce(&self, column_name: &str, aggregation: Aggregation) -> Result<(DataType, usize), RelationErrors> {
+        let column = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        // strings are parsed, cells that are not numbers are skipped and counted, nulls are skipped
+        let mut skipped = 0;
+        let numbers: Vec<DataType> = column.iter()
+            .filter_map(|value| match value {
+                DataType::Int(_) | DataType::Float(_) => Some(value.clone()),
+                DataType::String(s) => match DataType::from_str(s) {
+                    number @ (DataType::Int(_) | DataType::Float(_)) => Some(number),
+                    _ => {
+                        skipped += 1;
+                        None
+                    },
+                },
+                _ => None,
+            })
+            .collect();
+
+        Ok((aggregate_values(column_name, &numbers, aggregation)?, skipped))
+    }
+
+    fn aggr_labeled(&self, column_name: &str, aggregation: Aggregation) -> Result<(String, DataType), RelationErrors> {
+        let value = self.aggr(column_name, aggregation)?;
+        Ok((aggregation_label(column_name, aggregation), value))
+    }
+
+    fn group_by(&self, group_columns: Vec<&str>, aggregations: Vec<(&str, Aggregation)>) -> Result<ColumnStoreRelation, RelationErrors> {
+        let keys = group_columns.iter()
+            .map(|&column_name| self.columns.get(column_name).ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string())))
+            .collect::<Result<Vec<&Vec<DataType>>, RelationErrors>>()?;
+        let values = aggregations.iter()
+            .map(|&(column_name, _)| self.columns.get(column_name).ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string())))
+            .collect::<Result<Vec<&Vec<DataType>>, RelationErrors>>()?;
+
+        let labels: Vec<String> = aggregations.iter()
+            .map(|&(column_name, aggregation)| aggregation_label(column_name, aggregation))
+            .collect();
+        let mut seen = HashSet::new();
+        if let Some(name) = group_columns.iter().copied().chain(labels.iter().map(String::as_str)).find(|name| !seen.insert(*name)) {
+            return Err(RelationErrors::InvalidInput(format!("Column {} appears more than once in the result", name)));
+        }
+
+        // one running aggregator per group and aggregation, groups in order of their first row
+        let num_rows = keys.iter().chain(&values).map(|column| column.len()).min().unwrap_or(0);
+        let mut positions: HashMap<Vec<&DataType>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<&DataType>, Vec<Aggregator>)> = Vec::new();
+        for row in 0..num_rows {
+            let key: Vec<&DataType> = keys.iter().map(|column| &column[row]).collect();
+            let position = *positions.entry(key.clone()).or_insert_with(|| {
+                let aggregators = aggregations.iter()
+                    .map(|&(column_name, aggregation)| Aggregator::new(column_name, aggregation))
+                    .collect();
+                groups.push((key, aggregators));
+                groups.len() - 1
+            });
+            for (aggregator, column) in groups[position].1.iter_mut().zip(&values) {
+                aggregator.update(&column[row]);
+            }
+        }
+
+        let mut records = Vec::with_capacity(groups.len());
+        for (key, aggregators) in groups {
+            let mut record: Vec<DataType> = key.into_iter().cloned().collect();
+            for aggregator in aggregators {
+                record.push(match aggregator.value() {
+                    Err(RelationErrors::EmptyColumn(_)) => DataType::Null,
+                    value => value?,
+                });
+            }
+            records.push(record);
+        }
+
+        let result_columns = group_columns.iter().copied().chain(labels.iter().map(String::as_str)).collect();
+        let mut result_relation = ColumnStoreRelation::from_records(&self.name, result_columns, records)?;
+        for column_name in &group_columns {
+            if let Some(field) = self.fields.get(*column_name) {
+                result_relation.fields.insert(column_name.to_string(), field.clone());
+            }
+        }
+        result_relation.infer_missing_fields();
+        Ok(result_relation)
+    }
+
+    fn group_by_time(&self, date_col: &str, bucket: TimeBucket, value_col: &str, aggregation: Aggregation) -> Result<ColumnStoreRelation, RelationErrors> {
+        let dates = self.columns.get(date_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(date_col.to_string()))?;
+        if !self.columns.contains_key(value_col) {
+            return Err(RelationErrors::ColumnNotFound(value_col.to_string()));
+        }
+
+        // rows per bucket start, rows without a date are skipped
+        let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (row, value) in dates.iter().enumerate() {
+            match value {
+                DataType::Null => continue,
+                DataType::String(date) => {
+                    let bucket_start = truncate_date(date, bucket)
+                        .ok_or_else(|| RelationErrors::InvalidInput(format!("Invalid date {}", date)))?;
+                    groups.entry(bucket_start).or_default().push(row);
+                },
+                other => return Err(RelationErrors::InvalidInput(format!("Invalid date {}", other))),
+            }
+        }
+
+        let mut records = Vec::with_capacity(groups.len());
+        for (bucket_start, rows) in groups {
+            let value = self.rows_relation(&rows).aggr(value_col, aggregation)?;
+            records.push(vec![DataType::String(bucket_start), value]);
+        }
+        let label = aggregation_label(value_col, aggregation);
+
+        let mut result_relation = ColumnStoreRelation::from_records(&self.name, vec![date_col, &label], records)?;
+        result_relation.fields.insert(date_col.to_string(), DataType::String(String::new()));
+        result_relation.infer_missing_fields();
+        Ok(result_relation)
+    }
+
+    fn correlation(&self, col_a: &str, col_b: &str) -> Result<f64, RelationErrors> {
+        let column_a = self.columns.get(col_a)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(col_a.to_string()))?;
+        let column_b = self.columns.get(col_b)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(col_b.to_string()))?;
+        if column_a.len() != column_b.len() {
+            return Err(RelationErrors::InvalidInput(format!("Columns {} and {} differ in length", col_a, col_b)));
+        }
+
+        let as_f64 = |value: &DataType| match value {
+            DataType::Int(i) => Some(*i as f64),
+            DataType::Float(f) => Some(*f),
+            _ => None,
+        };
+        let pairs: Vec<(f64, f64)> = column_a.iter().zip(column_b.iter())
+            .filter_map(|(a, b)| Some((as_f64(a)?, as_f64(b)?)))
+            .collect();
+        if pairs.len() < 2 {
+            return Err(RelationErrors::InvalidInput("Correlation needs at least two numeric rows".to_string()));
+        }
+
+        let n = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+        let mean_b = pairs.iter().map(|(_, b)| b).sum::<f64>() / n;
+        let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+        for (a, b) in &pairs {
+            covariance += (a - mean_a) * (b - mean_b);
+            variance_a += (a - mean_a).powi(2);
+            variance_b += (b - mean_b).powi(2);
+        }
+        if variance_a == 0.0 || variance_b == 0.0 {
+            return Err(RelationErrors::InvalidInput("Correlation is undefined for a constant column".to_string()));
+        }
+
+        Ok(covariance / (variance_a.sqrt() * variance_b.sqrt()))
+    }
+
+    fn ratio(&self, numerator_col: &str, denominator_col: &str, num_agg: Aggregation, den_agg: Aggregation) -> Result<f64, RelationErrors> {
+        let as_f64 = |value: DataType| match value {
+            DataType::Int(i) => Ok(i as f64),
+            DataType::Float(f) => Ok(f),
+            other => Err(RelationErrors::InvalidInput(format!("Aggregate {} is not numeric", other))),
+        };
+
+        let numerator = as_f64(self.aggr(numerator_col, num_agg)?)?;
+        let denominator = as_f64(self.aggr(denominator_col, den_agg)?)?;
+        if denominator == 0.0 {
+            return Err(RelationErrors::InvalidInput("Division by zero".to_string()));
+        }
+
+        Ok(numerator / denominator)
+    }
+
+    fn rank(&self, partition_col: Option<&str>, order_col: &str, order: Order) -> Result<ColumnStoreRelation, RelationErrors> {
+        let order_column = self.columns.get(order_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(order_col.to_string()))?;
+        if self.columns.contains_key("rank") {
+            return Err(RelationErrors::InvalidInput("Column rank already exists".to_string()));
+        }
+
+        // rows of each partition in their original order
+        let mut partitions: Vec<Vec<usize>> = Vec::new();
+        match partition_col {
+            Some(partition_col) => {
+                let partition_column = self.columns.get(partition_col)
+                    .ok_or_else(|| RelationErrors::ColumnNotFound(partition_col.to_string()))?;
+                let mut positions: HashMap<&DataType, usize> = HashMap::new();
+                for (row, value) in partition_column.iter().enumerate() {
+                    let position = *positions.entry(value).or_insert_with(|| {
+                        partitions.push(Vec::new());
+                        partitions.len() - 1
+                    });
+                    partitions[position].push(row);
+                }
+            },
+            None => partitions.push((0..order_column.len()).collect()),
+        }
+
+        let mut ranks = vec![DataType::Null; order_column.len()];
+        for mut rows in partitions {
+            rows.sort_by(|&a, &b| compare_values_ordered(&order_column[a], &order_column[b], &order));
+            for (position, &row) in rows.iter().enumerate() {
+                let tied = position > 0 && compare_values_ordered(&order_column[rows[position - 1]], &order_column[row], &order) == Ordering::Equal;
+                ranks[row] = if tied { ranks[rows[position - 1]].clone() } else { DataType::Int(position as i32 + 1) };
+            }
+        }
+
+        let mut result_relation = self.clone();
+        result_relation.columns.insert("rank".to_string(), ranks);
+        result_relation.fields.insert("rank".to_string(), DataType::Int(0));
+        result_relation.select_columns.push("rank".to_string());
+        Ok(result_relation)
+    }
+
+    fn cumulative(&self, column_name: &str, aggregation: Aggregation) -> Result<ColumnStoreRelation, RelationErrors> {
+        let column = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        let label = format!("cumulative_{}", aggregation_label(column_name, aggregation));
+        if self.columns.contains_key(&label) {
+            return Err(RelationErrors::InvalidInput(format!("Column {} already exists", label)));
+        }
+
+        let mut aggregator = Aggregator::new(column_name, aggregation);
+        let mut running = Vec::with_capacity(column.len());
+        for value in column {
+            aggregator.update(value);
+            running.push(match aggregator.value() {
+                Err(RelationErrors::EmptyColumn(_)) => DataType::Null,
+                value => value?,
+            });
+        }
+
+        let mut result_relation = self.clone();
+        if let Some(field) = first_value_field(&running) {
+            result_relation.fields.insert(label.clone(), field);
+        }
+        result_relation.columns.insert(label.clone(), running);
+        result_relation.select_columns.push(label);
+        Ok(result_relation)
+    }
 
-        // Separator after header
-        output.push_str(&header_separator);
-        output.push('\n');
+    fn sort(&mut self, column_name: &str, order: Order) -> Result<(), RelationErrors> {
+        self.sort_by(vec![(column_name, order)])
+    }
 
-        // Collect Data Rows with padding, following the order of `select_columns`
-        let rows_output: Vec<String> = (0..max_rows).into_par_iter().map(|row| {
-            let mut row_output = String::with_capacity(self.select_columns.len() * 10); // Adjust the capacity as needed
-            row_output.push('│');
-            for column_name in &self.select_columns {
-                if let Some(data) = self.columns.get(column_name) {
-                    let value = data.get(row).map_or(String::new(), |v| match *v {
-                        DataType::Float(f) => format!("{:.2}", f),
-                        _ => format!("{}", v),
-                    });
-                    if let Some(width) = column_widths.get(column_name).map(|entry| *entry.value()) {
-                        row_output.push_str(&format!(" {:width$} │", value, width = width));
-                    }
-                }
-            }
-            row_output.push('\n');
-            row_output
-        }).collect();
+    fn sort_by(&mut self, keys: Vec<(&str, Order)>) -> Result<(), RelationErrors> {
+        let sort_columns = keys.iter()
+            .map(|(column_name, order)| self.columns.get(*column_name)
+                .map(|column| (column, order))
+                .ok_or(RelationErrors::ColumnNotFound(column_name.to_string())))
+            .collect::<Result<Vec<(&Vec<DataType>, &Order)>, RelationErrors>>()?;
 
-        for row in rows_output {
-            output.push_str(&row);
+        let mut indices: Vec<usize> = (0..self.num_tuples()?).collect();
+        
+        indices.sort_by(|&a, &b| sort_columns.iter()
+            .map(|(column, order)| compare_values_ordered(&column[a], &column[b], order))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal));
+
+        for column in self.columns.values_mut() {
+            let sorted_column: Vec<DataType> = indices.iter().map(|&i| column[i].clone()).collect();
+            *column = sorted_column;
         }
 
-        // Collect the bottom border
-        output.push_str(&bottom_border);
-        output.push('\n');
+        // only the first key is sorted over all rows, it is verified once here like the merge join would
+        self.sorted_columns.clear();
+        if let Some((column_name, Order::Asc)) = keys.first() {
+            if is_sorted_ascending(&self.columns[*column_name]) {
+                self.sorted_columns.insert(column_name.to_string());
+            }
+        }
 
-        // Print the collected output
-        println!("{}", output);
+        // row positions changed, so existing indices are rebuilt
+        self.rebuild_indices()
     }
 
-    fn select<F>(&mut self, column_name: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
-    where
-        F: Fn(&DataType) -> bool,
-    {
-        // First, check if the column exists
-        let column_data = self.columns.get(column_name)
+    fn external_sort(&self, column_name: &str, order: Order, chunk_rows: usize, temp_dir: &str) -> Result<ColumnStoreRelation, RelationErrors> {
+        let sort_column = self.columns.get(column_name)
             .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        if chunk_rows == 0 {
+            return Err(RelationErrors::InvalidInput("Chunk size must be positive".to_string()));
+        }
 
-        // Collect indices of rows where the predicate is true
-        let matching_indices: Vec<usize> = column_data.iter()
-            .enumerate()
-            .filter_map(|(index, datum)| {
-                if predicate(datum) {
-                    Some(index)
-                } else {
-                    None
-                }
-            })
+        // fixed column layout of the spilled rows
+        let mut column_names: Vec<String> = self.select_columns.iter()
+            .filter(|name| self.columns.contains_key(*name))
+            .cloned()
+            .collect();
+        let mut remaining: Vec<String> = self.columns.keys()
+            .filter(|name| !column_names.contains(name))
+            .cloned()
             .collect();
+        remaining.sort();
+        column_names.extend(remaining);
+        let key_position = column_names.iter().position(|name| name == column_name).unwrap();
+
+        let compare = |a: &DataType, b: &DataType| compare_values_ordered(a, b, &order);
+
+        // sort each chunk in memory and spill it to its own temporary file
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < sort_column.len() {
+            let end = std::cmp::min(start + chunk_rows, sort_column.len());
+            let mut rows: Vec<usize> = (start..end).collect();
+            rows.sort_by(|&a, &b| compare(&sort_column[a], &sort_column[b]));
+
+            let file = tempfile::tempfile_in(temp_dir).map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+            let mut writer = BufWriter::new(file);
+            for row in rows {
+                let values: Vec<DataType> = column_names.iter().map(|name| self.columns[name][row].clone()).collect();
+                write_row(&mut writer, &values)?;
+            }
+            let mut file = writer.into_inner().map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+            file.seek(SeekFrom::Start(0))?;
+            chunks.push(BufReader::new(file));
+            start = end;
+        }
 
-        // Create a new IntermediateRelation to hold the filtered results
         let mut result_relation = ColumnStoreRelation::new();
-        result_relation.name = self.name.clone(); // Assuming you want to keep the same relation name
-
-        // Filter all columns based on matching indices
-        for (key, values) in &self.columns {
-            let filtered_values: Vec<DataType> = matching_indices.iter()
-                .filter_map(|&index| values.get(index).cloned())
-                .collect();
-            result_relation.columns.insert(key.clone(), filtered_values);
+        result_relation.name = self.name.clone();
+        result_relation.fields = self.fields.clone();
+        result_relation.select_columns = self.select_columns.clone();
+        for name in &column_names {
+            result_relation.columns.insert(name.clone(), Vec::with_capacity(sort_column.len()));
         }
 
-        // Optionally copy the indices, if they need to be filtered similarly
-        for (key, index_map) in &self.indices {
-            let filtered_index_map: BTreeMap<String, Vec<usize>> = index_map.iter()
-                .filter_map(|(value_key, rows)| {
-                    let filtered_rows: Vec<usize> = rows.iter()
-                        .filter(|row_index| matching_indices.contains(row_index))
-                        .cloned()
-                        .collect();
-                    if !filtered_rows.is_empty() {
-                        Some((value_key.clone(), filtered_rows))
-                    } else {
-                        None
+        // k-way merge, ties are taken from the earliest chunk to keep the sort stable
+        let mut heads = chunks.iter_mut()
+            .map(read_row)
+            .collect::<Result<Vec<Option<Vec<DataType>>>, RelationErrors>>()?;
+        loop {
+            let mut next: Option<usize> = None;
+            for (chunk, head) in heads.iter().enumerate() {
+                if let Some(row) = head {
+                    let is_smaller = match next {
+                        Some(best) => compare(&row[key_position], &heads[best].as_ref().unwrap()[key_position]) == Ordering::Less,
+                        None => true,
+                    };
+                    if is_smaller {
+                        next = Some(chunk);
                     }
-                })
-                .collect();
-            result_relation.indices.insert(key.clone(), filtered_index_map);
-        }
+                }
+            }
 
-        // Maintain selected column order and fields in the new relation
-        result_relation.select_columns = self.select_columns.clone();
-        result_relation.fields = self.fields.clone();
+            let Some(chunk) = next else { break };
+            let row = std::mem::replace(&mut heads[chunk], read_row(&mut chunks[chunk])?).unwrap();
+            for (name, value) in column_names.iter().zip(row) {
+                result_relation.columns.get_mut(name).unwrap().push(value);
+            }
+        }
 
         Ok(result_relation)
     }
 
-    fn project(&self, columns_to_keep: Vec<&str>) -> Result<ColumnStoreRelation, RelationErrors> {
-        // Create a new ColumnStoreRelation to hold the result
-        let mut result_relation = ColumnStoreRelation::new();
-        result_relation.name = self.name.clone(); // Keep the same name
+    fn create_index(&mut self, column_name: &str) -> Result<(), String> {
+        self.create_index_with(column_name, IndexKind::Rows)
+    }
 
-        // Iterate over the specified columns, adding them to the new relation if they exist
-        for column_name in columns_to_keep {
-            if let Some(data) = self.columns.get(column_name) {
-                // If the column exists, copy it to the new relation
-                result_relation.columns.insert(column_name.to_string(), data.clone());
-                // Also, add to select_columns to maintain the order
-                result_relation.select_columns.push(column_name.to_string());
-            } else {
-                // If a specified column does not exist, you might want to return an error
-                return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
-                // Or simply skip this column
-                // continue;
-            }
+    fn create_index_with(&mut self, column_name: &str, kind: IndexKind) -> Result<(), String> {
+        if !self.columns.contains_key(column_name) {
+            return Err("Column not found".to_string());
         }
 
-        Ok(result_relation)
-    }
+        let column_data = self.columns.get(column_name).unwrap();
 
-    fn aggr(&self, column_name: &str, aggregation: Aggregation) -> Result<DataType, RelationErrors> {
-        match self.columns.get(column_name) {
-            Some(column) => {
-                match aggregation {
-                    Aggregation::Count => Ok(DataType::Int(column.len() as i32)),
-                    Aggregation::Sum => {
-                        let sum = column.iter().try_fold(0f64, |acc, val| {
-                            if let DataType::Int(i) = val {
-                                Ok(acc + (*i as f64))
-                            } else if let DataType::Float(f) = val {
-                                Ok(acc + f)
-                            } else {
-                                Err(RelationErrors::Error("Sum operation on non-numeric column".to_string()))
-                            }
-                        })?;
-                        Ok(DataType::Float(sum))
-                    },
-                    Aggregation::Min => {
-                        let min = column.iter().filter_map(|val| match val {
-                            DataType::Int(i) => Some(*i as f64),
-                            DataType::Float(f) => Some(*f),
-                            _ => None,
-                        }).fold(f64::INFINITY, |a, b| a.min(b));
-                    
-                        if min == f64::INFINITY {
-                            Err(RelationErrors::Error("Min operation on non-numeric column or empty column".to_string()))
-                        } else {
-                            Ok(DataType::Float(min))
-                        }
-                    },
-                    Aggregation::Max => {
-                        let max = column.iter().filter_map(|val| match val {
-                            DataType::Int(i) => Some(*i as f64),
-                            DataType::Float(f) => Some(*f),
-                            _ => None,
-                        }).fold(f64::NEG_INFINITY, |a, b| a.max(b));
-                    
-                        if max == f64::NEG_INFINITY {
-                            Err(RelationErrors::Error("Max operation on non-numeric column or empty column".to_string()))
-                        } else {
-                            Ok(DataType::Float(max))
-                        }
-                    },
-                    Aggregation::Average => {
-                        let sum = column.iter().filter_map(|val| match val {
-                            DataType::Int(i) => Some(*i as f64),
-                            DataType::Float(f) => Some(*f),
-                            _ => None,
-                        }).sum::<f64>();
-                        let count = column.iter().filter_map(|val| match val {
-                            DataType::Int(_) | DataType::Float(_) => Some(1),
-                            _ => None,
-                        }).count();
-                        
-                        if count > 0 {
-                            Ok(DataType::Float(sum / count as f64))
-                        } else {
-                            Err(RelationErrors::Error("Average operation on non-numeric column or empty column".to_string()))
-                        }
-                    },
+        match kind {
+            IndexKind::Rows => {
+                let mut index = BTreeMap::new();
+                for (row_idx, value) in column_data.iter().enumerate() {
+                    let key = OrdDataType(value.clone());
+                    index.entry(key).or_insert_with(Vec::new).push(row_idx);
                 }
-            },
-            None => Err(RelationErrors::ColumnNotFound(column_name.to_string())),
+                self.compact_indices.remove(column_name);
+                self.indices.insert(column_name.to_string(), index);
+            }
+            IndexKind::Compact => {
+                let mut index = BTreeMap::new();
+                for (row_idx, value) in column_data.iter().enumerate() {
+                    let key = OrdDataType(value.clone());
+                    index.entry(key).or_insert_with(RowSet::new).insert(row_idx);
+                }
+                self.indices.remove(column_name);
+                self.compact_indices.insert(column_name.to_string(), index);
+            }
         }
+
+        Ok(())
     }
 
-    fn sort(&mut self, column_name: &str, order: Order) -> Result<(), RelationErrors> {
-        let sort_column = self.columns.get(column_name)
-            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+    fn save_index(&self, column_name: &str, path: &str) -> Result<(), RelationErrors> {
+        let index = self.indices.get(column_name)
+            .ok_or_else(|| RelationErrors::Error(format!("No index on column {}", column_name)))?;
+        let num_rows = self.columns.get(column_name).map_or(0, |column| column.len());
 
-        let mut indices: Vec<usize> = (0..sort_column.len()).collect();
-        
-        indices.sort_by(|&a, &b| {
-            let val_a = &sort_column[a];
-            let val_b = &sort_column[b];
-            let cmp = match (val_a, val_b) {
-                (DataType::Int(int_a), DataType::Int(int_b)) => int_a.cmp(int_b),
-                (DataType::Float(float_a), DataType::Float(float_b)) => float_a.partial_cmp(float_b).unwrap_or(std::cmp::Ordering::Equal),
-                (DataType::String(str_a), DataType::String(str_b)) => str_a.cmp(str_b),
-                
-                _ => std::cmp::Ordering::Equal, 
-            };
+        let file = File::create(path).map_err(|e| RelationErrors::WriteError(e.to_string()))?;
+        let mut writer = BufWriter::new(file);
+        let write_u64 = |writer: &mut BufWriter<File>, value: usize| {
+            writer.write_all(&(value as u64).to_be_bytes()).map_err(|e| RelationErrors::WriteError(e.to_string()))
+        };
 
-            match order {
-                Order::Asc => cmp,
-                Order::Desc => cmp.reverse(),
+        // the row count of the column, followed by each key and its rows
+        write_u64(&mut writer, num_rows)?;
+        for (key, rows) in index {
+            write_row(&mut writer, std::slice::from_ref(&key.0))?;
+            write_u64(&mut writer, rows.len())?;
+            for &row in rows {
+                write_u64(&mut writer, row)?;
             }
-        });
-
-        for column in self.columns.values_mut() {
-            let sorted_column: Vec<DataType> = indices.iter().map(|&i| column[i].clone()).collect();
-            *column = sorted_column;
         }
+        writer.flush().map_err(|e| RelationErrors::WriteError(e.to_string()))?;
 
         Ok(())
     }
 
-    fn create_index(&mut self, column_name: &str) -> Result<(), String> {
-        if !self.columns.contains_key(column_name) {
-            return Err("Column not found".to_string());
+    fn load_index(&mut self, column_name: &str, path: &str) -> Result<(), RelationErrors> {
+        let column = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        let mut reader = BufReader::new(File::open(path)?);
+        let read_u64 = |reader: &mut BufReader<File>| -> Result<usize, RelationErrors> {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            Ok(u64::from_be_bytes(bytes) as usize)
+        };
+        let stale = || RelationErrors::InvalidInput(format!("Index file {} does not match column {}", path, column_name));
+
+        if read_u64(&mut reader)? != column.len() {
+            return Err(stale());
         }
 
-        let column_data = self.columns.get(column_name).unwrap();
+        // every row has to be indexed exactly once under its current value
         let mut index = BTreeMap::new();
-
-        for (row_idx, value) in column_data.iter().enumerate() {
-            let key = value.to_str();
-            index.entry(key).or_insert_with(Vec::new).push(row_idx);
+        let mut indexed_rows = 0;
+        while let Some(mut key) = read_row(&mut reader)? {
+            let key = OrdDataType(key.pop().ok_or_else(stale)?);
+            let num_rows = read_u64(&mut reader)?;
+            let mut rows = Vec::with_capacity(num_rows);
+            for _ in 0..num_rows {
+                let row = read_u64(&mut reader)?;
+                match column.get(row) {
+                    Some(value) if OrdDataType(value.clone()) == key => rows.push(row),
+                    _ => return Err(stale()),
+                }
+            }
+            indexed_rows += rows.len();
+            index.insert(key, rows);
+        }
+        if indexed_rows != column.len() {
+            return Err(stale());
         }
 
+        self.compact_indices.remove(column_name);
         self.indices.insert(column_name.to_string(), index);
-
         Ok(())
     }
 
@@ -404,33 +1967,71 @@ impl Relation for ColumnStoreRelation {
     where
         F: Fn(&DataType) -> bool,
     {
-        if let Some(index) = self.indices.get(column_name) {
-            let mut result_relation = ColumnStoreRelation::new();
-            result_relation.name = self.name.clone();
-            result_relation.fields = self.fields.clone(); 
-
-            let mut matched_indices: Vec<usize> = Vec::new();
-            for (value, row_indices) in index {
-                if predicate(&DataType::from_str(value)) {
-                    matched_indices.extend(row_indices);
-                }
-            }
+        let matched_indices = self.index_rows(column_name, (Bound::Unbounded, Bound::Unbounded), |key| predicate(&key.0))?;
+        Ok(self.rows_relation(&matched_indices))
+    }
 
-            matched_indices.sort();
-            matched_indices.dedup();
+    fn index_select_cmp(&self, column_name: &str, op: CompareOp, value: &DataType) -> Result<ColumnStoreRelation, RelationErrors> {
+        let (first, last) = key_bounds(value);
+        let (lower, upper) = match op {
+            CompareOp::Eq => (Bound::Included(&first), Bound::Included(&last)),
+            CompareOp::Lt => (Bound::Unbounded, Bound::Excluded(&first)),
+            CompareOp::Le => (Bound::Unbounded, Bound::Included(&last)),
+            CompareOp::Gt => (Bound::Excluded(&last), Bound::Unbounded),
+            CompareOp::Ge => (Bound::Included(&first), Bound::Unbounded),
+            CompareOp::Ne => (Bound::Unbounded, Bound::Unbounded),
+        };
 
-            for (key, value) in self.columns.iter() {
-                let filtered_column_data: Vec<DataType> = matched_indices.iter()
-                    .filter_map(|&i| value.get(i).cloned())
-                    .collect();
-                result_relation.columns.insert(key.clone(), filtered_column_data);
-            }
+        // the open ends of a range would reach into other kinds of values (e.g. strings after numbers),
+        // the keys are checked like a full scan would check the cells
+        let matched_indices = self.index_rows(column_name, (lower, upper), |k| compare_with(&k.0, op, value))?;
 
-            result_relation.select_columns = self.select_columns.clone();
-            Ok(result_relation)
-        } else {
-            Err(RelationErrors::ColumnNotFound(column_name.to_string()))
+        Ok(self.rows_relation(&matched_indices))
+    }
+
+    fn index_range_select(&self, column_name: &str, low: &DataType, high: &DataType) -> Result<ColumnStoreRelation, RelationErrors> {
+        // integer and float keys equal to an endpoint are both within the range
+        let (low, high) = (key_bounds(low).0, key_bounds(high).1);
+        if low > high {
+            // an empty range, `BTreeMap::range` would panic on it
+            return self.index_key_rows(column_name, (Bound::Included(&low), Bound::Included(&low)), |_| false)
+                .map(|rows| self.rows_relation(&rows));
+        }
+
+        let rows = self.index_key_rows(column_name, (Bound::Included(&low), Bound::Included(&high)), |key| !key.0.is_null())?;
+        Ok(self.rows_relation(&rows))
+    }
+
+    fn create_composite_index(&mut self, columns: Vec<&str>) -> Result<(), String> {
+        if columns.is_empty() {
+            return Err("Composite index needs at least one column".to_string());
+        }
+        let column_data = columns.iter()
+            .map(|&column_name| self.columns.get(column_name).ok_or_else(|| "Column not found".to_string()))
+            .collect::<Result<Vec<&Vec<DataType>>, String>>()?;
+
+        let num_rows = column_data.iter().map(|data| data.len()).min().unwrap_or(0);
+        let mut index = BTreeMap::new();
+        for row_idx in 0..num_rows {
+            let key: Vec<OrdDataType> = column_data.iter().map(|data| OrdDataType(data[row_idx].clone())).collect();
+            index.entry(key).or_insert_with(Vec::new).push(row_idx);
+        }
+        self.composite_indices.insert(columns.iter().map(|&c| c.to_string()).collect(), index);
+
+        Ok(())
+    }
+
+    fn composite_index_select(&self, columns: Vec<&str>, values: &[DataType]) -> Result<ColumnStoreRelation, RelationErrors> {
+        let columns: Vec<String> = columns.iter().map(|&c| c.to_string()).collect();
+        let index = self.composite_indices.get(&columns)
+            .ok_or_else(|| RelationErrors::Error(format!("No index on columns {}", columns.join(", "))))?;
+        if values.len() != columns.len() {
+            return Err(RelationErrors::InvalidInput(format!("Expected {} values for the index, got {}", columns.len(), values.len())));
         }
+
+        let key: Vec<OrdDataType> = values.iter().map(|value| OrdDataType(value.clone())).collect();
+        let rows = index.get(&key).map_or(&[][..], |rows| rows.as_slice());
+        Ok(self.rows_relation(rows))
     }
 
     fn scan<F>(&mut self, select_columns: Vec<&str>, predicate: F) -> Result<ColumnStoreRelation, RelationErrors> 
@@ -443,16 +2044,9 @@ impl Relation for ColumnStoreRelation {
             if let Some(column_data) = self.columns.get(&column_name) {
                 let mut filtered_data = Vec::new();
                 
-                for datum_result in column_data.iter().map(|datum| datum) { 
-                    match datum_result {
-                        datum => {
-                            if predicate(datum) {
-                                filtered_data.push(datum.clone());
-                            }
-                        },
-                        err => {
-                            continue;
-                        }
+                for datum in column_data.iter() { 
+                    if predicate(datum) {
+                        filtered_data.push(datum.clone());
                     }
                 }
 
@@ -466,222 +2060,203 @@ impl Relation for ColumnStoreRelation {
         }
 
         new_relation.select_columns = select_columns.iter().map(|s| s.to_string()).collect();
+        new_relation.infer_missing_fields();
 
         Ok(new_relation)
     }
 
-    fn nested_loop_join<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
-    where F: Fn(&DataType, &DataType) -> bool 
-    {
-        // Ensure both columns exist in their respective relations
-        let r_col_data = self.columns.get(r_col)
-            .ok_or_else(|| RelationErrors::ColumnNotFound(r_col.to_string()))?;
-        let s_col_data = other_relation.columns.get(s_col)
-            .ok_or_else(|| RelationErrors::ColumnNotFound(s_col.to_string()))?;
-
-        // Create a new relation to store the join result
-        let mut result_relation = ColumnStoreRelation::new();
-        result_relation.name = format!("{}_{}_join", self.name, other_relation.name);
-
-        // Copy the field definitions and selected columns from both relations, avoiding duplicate columns
-        for (key, value) in &self.fields {
-            result_relation.fields.insert(key.clone(), value.clone());
-        }
-        for (key, value) in &other_relation.fields {
-            if key != s_col {
-                result_relation.fields.insert(key.clone(), value.clone());
-            }
+    fn union_by_name(&self, other: &ColumnStoreRelation, all: bool) -> Result<ColumnStoreRelation, RelationErrors> {
+        let left: HashSet<&String> = self.select_columns.iter().collect();
+        let right: HashSet<&String> = other.select_columns.iter().collect();
+        if left != right {
+            return Err(RelationErrors::InvalidInput(format!(
+                "Cannot union columns {:?} and {:?}", self.select_columns, other.select_columns
+            )));
         }
 
-        result_relation.select_columns = self.select_columns.iter()
-            .chain(other_relation.select_columns.iter().filter(|&col| col != s_col))
-            .cloned()
+        // the rows of `other` with its values in the column order of this relation
+        let mut reordered = other.clone();
+        reordered.select_columns = self.select_columns.clone();
+
+        let mut seen = HashSet::new();
+        let records: Vec<Vec<DataType>> = self.rows().chain(reordered.rows())
+            .filter(|row| all || seen.insert(row.clone()))
             .collect();
 
-        for column_name in &result_relation.select_columns {
-            result_relation.columns.insert(column_name.clone(), Vec::new());
-        }
+        let mut result_relation = ColumnStoreRelation::from_records(
+            &self.name,
+            self.select_columns.iter().map(String::as_str).collect(),
+            records,
+        )?;
+        result_relation.fields = self.fields.clone();
+        Ok(result_relation)
+    }
 
-        // Perform nested loop join
-        for (i, r_value) in r_col_data.iter().enumerate() {
-            for (j, s_value) in s_col_data.iter().enumerate() {
-                if predicate(r_value, s_value) {
-                    // Add the values from the first relation
-                    for (key, values) in &self.columns {
-                        if let Some(column) = result_relation.columns.get_mut(key) {
-                            column.push(values[i].clone());
-                        }
-                    }
-                    // Add the values from the second relation, except for the join column
-                    for (key, values) in &other_relation.columns {
-                        if key != s_col {
-                            if let Some(column) = result_relation.columns.get_mut(key) {
-                                column.push(values[j].clone());
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    fn union(&self, other: &ColumnStoreRelation) -> Result<ColumnStoreRelation, RelationErrors> {
+        self.check_set_schema(other, "union")?;
+        self.union_by_name(other, false)
+    }
 
-        Ok(result_relation)
+    fn intersect(&self, other: &ColumnStoreRelation) -> Result<ColumnStoreRelation, RelationErrors> {
+        self.check_set_schema(other, "intersect")?;
+        let other_rows: HashSet<Vec<DataType>> = other.rows().collect();
+        self.filter_distinct_rows(|row| other_rows.contains(row))
     }
 
-    fn merge_join<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
-    where F: Fn(&DataType, &DataType) -> bool 
-    {
-        let r_col_data = self.columns.get(r_col)
-            .ok_or_else(|| RelationErrors::ColumnNotFound(r_col.to_string()))?;
-        let s_col_data = other_relation.columns.get(s_col)
-            .ok_or_else(|| RelationErrors::ColumnNotFound(s_col.to_string()))?;
+    fn difference(&self, other: &ColumnStoreRelation) -> Result<ColumnStoreRelation, RelationErrors> {
+        self.check_set_schema(other, "difference")?;
+        let other_rows: HashSet<Vec<DataType>> = other.rows().collect();
+        self.filter_distinct_rows(|row| !other_rows.contains(row))
+    }
 
-        // Check if both columns are sorted
-        let mut r_sorted = true;
-        for i in 1..r_col_data.len() {
-            if r_col_data[i] < r_col_data[i-1] {
-                r_sorted = false;
-                break;
-            }
+    fn diff_keyed(&self, other: &ColumnStoreRelation, key_col: &str) -> Result<(ColumnStoreRelation, ColumnStoreRelation, ColumnStoreRelation), RelationErrors> {
+        let left: HashSet<&String> = self.select_columns.iter().collect();
+        let right: HashSet<&String> = other.select_columns.iter().collect();
+        if left != right {
+            return Err(RelationErrors::InvalidInput(format!(
+                "Cannot diff columns {:?} and {:?}", self.select_columns, other.select_columns
+            )));
         }
-        
-        let mut s_sorted = true;
-        for i in 1..s_col_data.len() {
-            if s_col_data[i] < s_col_data[i-1] {
-                s_sorted = false;
-                break;
+
+        fn key_rows<'a>(relation: &'a ColumnStoreRelation, key_col: &str) -> Result<HashMap<&'a DataType, usize>, RelationErrors> {
+            let keys = relation.columns.get(key_col)
+                .ok_or_else(|| RelationErrors::ColumnNotFound(key_col.to_string()))?;
+            let mut rows = HashMap::with_capacity(keys.len());
+            for (row, key) in keys.iter().enumerate() {
+                if rows.insert(key, row).is_some() {
+                    return Err(RelationErrors::InvalidInput(format!("Duplicate key {} in {}", key, relation.name)));
+                }
             }
+            Ok(rows)
         }
-        
-        if !r_sorted || !s_sorted {
-            return Err(RelationErrors::Error("Columns are not sorted for merge join".to_string()));
+        let old_rows = key_rows(self, key_col)?;
+        let new_rows = key_rows(other, key_col)?;
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for (row, key) in other.columns[key_col].iter().enumerate() {
+            match old_rows.get(key) {
+                None => added.push(row),
+                Some(&old_row) => {
+                    let differs = self.select_columns.iter()
+                        .any(|column_name| self.columns[column_name].get(old_row) != other.columns[column_name].get(row));
+                    if differs {
+                        changed.push(row);
+                    }
+                },
+            }
         }
+        let removed: Vec<usize> = self.columns[key_col].iter()
+            .enumerate()
+            .filter(|(_, key)| !new_rows.contains_key(key))
+            .map(|(row, _)| row)
+            .collect();
 
-        // Create a new relation to store the join result
-        let mut result_relation = ColumnStoreRelation::new();
-        result_relation.name = format!("{}_{}_join", self.name, other_relation.name);
+        // the rows of `other` are returned in the column order of this relation
+        let mut reordered = other.clone();
+        reordered.select_columns = self.select_columns.clone();
+        Ok((reordered.rows_relation(&added), self.rows_relation(&removed), reordered.rows_relation(&changed)))
+    }
 
-        // Copy the field definitions and selected columns from both relations, avoiding duplicate columns
-        for (key, value) in &self.fields {
-            result_relation.fields.insert(key.clone(), value.clone());
-        }
-        for (key, value) in &other_relation.fields {
-            if key != s_col {
-                result_relation.fields.insert(key.clone(), value.clone());
-            }
-        }
+    fn prefix_columns(&self, prefix: &str) -> ColumnStoreRelation {
+        let rename = |column_name: &String| format!("{}.{}", prefix, column_name);
 
-        // Combine the selected columns without duplicating the join column
-        result_relation.select_columns = self.select_columns.iter()
-            .chain(other_relation.select_columns.iter().filter(|&col| col != s_col))
-            .cloned()
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = self.name.clone();
+        result_relation.select_columns = self.select_columns.iter().map(rename).collect();
+        result_relation.fields = self.fields.iter().map(|(k, v)| (rename(k), v.clone())).collect();
+        result_relation.columns = self.columns.iter().map(|(k, v)| (rename(k), v.clone())).collect();
+        result_relation.indices = self.indices.iter().map(|(k, v)| (rename(k), v.clone())).collect();
+        result_relation.compact_indices = self.compact_indices.iter().map(|(k, v)| (rename(k), v.clone())).collect();
+        result_relation.composite_indices = self.composite_indices.iter()
+            .map(|(k, v)| (k.iter().map(rename).collect(), v.clone()))
             .collect();
+        result_relation
+    }
 
-        // Initialize result columns
-        for column_name in &result_relation.select_columns {
-            result_relation.columns.insert(column_name.clone(), Vec::new());
-        }
+    fn nested_loop_join<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool 
+    {
+        self.join_with_limit(other_relation, r_col, s_col, predicate, JoinType::NestedLoop, None)
+    }
 
-        // Perform merge join
-        let mut i = 0;
-        let mut j = 0;
+    fn merge_join<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool 
+    {
+        self.join_with_limit(other_relation, r_col, s_col, predicate, JoinType::MergeJoin, None)
+    }
 
-        while i < r_col_data.len() && j < s_col_data.len() {
-            if predicate(&r_col_data[i], &s_col_data[j]) {
-                let mut k = j;
-                while k < s_col_data.len() && s_col_data[k] == s_col_data[j] {
-                    // Combine the tuples from both relations
-                    for (key, values) in &self.columns {
-                        if let Some(column) = result_relation.columns.get_mut(key) {
-                            column.push(values[i].clone());
-                        }
-                    }
-                    for (key, values) in &other_relation.columns {
-                        if key != s_col {
-                            if let Some(column) = result_relation.columns.get_mut(key) {
-                                column.push(values[k].clone());
-                            }
-                        }
-                    }
-                    k += 1;
-                }
-                i += 1;
-            } else if r_col_data[i] < s_col_data[j] {
-                i += 1;
-            } else {
-                j += 1;
+    fn hash_join<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool 
+    {
+        self.join_with_limit(other_relation, r_col, s_col, predicate, JoinType::HashJoin, None)
+    }
+
+    fn hash_join_normalized<N>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, normalize: N) -> Result<ColumnStoreRelation, RelationErrors>
+    where N: Fn(&DataType) -> DataType
+    {
+        let r_col_data = self.columns.get(r_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(r_col.to_string()))?;
+        let s_col_data = other_relation.columns.get(s_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(s_col.to_string()))?;
+
+        // every key is normalized once, the rows are emitted in the order of `hash_join`
+        let mut hash_table: HashMap<DataType, Vec<usize>> = HashMap::new();
+        for (i, value) in r_col_data.iter().enumerate().filter(|(_, value)| !value.is_null()) {
+            hash_table.entry(normalize(value)).or_default().push(i);
+        }
+        let mut pairs = Vec::new();
+        for (j, s_value) in s_col_data.iter().enumerate().filter(|(_, value)| !value.is_null()) {
+            if let Some(indices) = hash_table.get(&normalize(s_value)) {
+                pairs.extend(indices.iter().map(|&i| (i, j)));
             }
         }
 
-        Ok(result_relation)
+        Ok(self.join_rows(other_relation, r_col, s_col, &pairs, JoinKeyPolicy::KeepBoth))
     }
 
-    fn hash_join<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F) -> Result<ColumnStoreRelation, RelationErrors>
+    fn join_with_limit<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F, jt: JoinType, max_result_rows: Option<usize>) -> Result<ColumnStoreRelation, RelationErrors>
     where F: Fn(&DataType, &DataType) -> bool 
     {
-        // Ensure both columns exist in their respective relations
-        if !self.columns.contains_key(r_col) {
-            return Err(RelationErrors::ColumnNotFound(r_col.to_string()));
-        }
-        if !other_relation.columns.contains_key(s_col) {
-            return Err(RelationErrors::ColumnNotFound(s_col.to_string()));
-        }
-
-        // Create a new relation to store the join result
-        let mut result_relation = ColumnStoreRelation::new();
-        result_relation.name = format!("{}_{}_join", self.name, other_relation.name);
-
-        // Copy the field definitions and selected columns from both relations, avoiding duplicate columns
-        for (key, value) in &self.fields {
-            result_relation.fields.insert(key.clone(), value.clone());
-        }
-        for (key, value) in &other_relation.fields {
-            if key != s_col {
-                result_relation.fields.insert(key.clone(), value.clone());
-            }
-        }
+        let pairs = self.join_pairs(other_relation, r_col, s_col, predicate, jt, max_result_rows)?;
+        Ok(self.join_rows(other_relation, r_col, s_col, &pairs, JoinKeyPolicy::KeepLeft))
+    }
 
-        // Combine the selected columns without duplicating the join column
-        result_relation.select_columns = self.select_columns.iter()
-            .chain(other_relation.select_columns.iter().filter(|&col| col != s_col))
-            .cloned()
-            .collect();
+    fn join_with_key_policy<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, predicate: F, jt: JoinType, key_policy: JoinKeyPolicy) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool 
+    {
+        let pairs = self.join_pairs(other_relation, r_col, s_col, predicate, jt, None)?;
+        Ok(self.join_rows(other_relation, r_col, s_col, &pairs, key_policy))
+    }
 
-        // Initialize result columns
-        for column_name in &result_relation.select_columns {
-            result_relation.columns.insert(column_name.clone(), Vec::new());
-        }
+    fn join_project<F>(&self, other_relation: &ColumnStoreRelation, r_col: &str, s_col: &str, output_columns: Vec<&str>, predicate: F, jt: JoinType) -> Result<ColumnStoreRelation, RelationErrors>
+    where F: Fn(&DataType, &DataType) -> bool 
+    {
+        // resolve every output column before joining, left columns take precedence
+        let sources = output_columns.iter()
+            .map(|&column_name| match (self.columns.get(column_name), other_relation.columns.get(column_name)) {
+                (Some(values), _) => Ok((column_name, JoinSide::Left, values)),
+                (None, Some(values)) if column_name != s_col => Ok((column_name, JoinSide::Right, values)),
+                _ => Err(RelationErrors::ColumnNotFound(column_name.to_string())),
+            })
+            .collect::<Result<Vec<_>, RelationErrors>>()?;
 
-        // Build the hash table for the first relation
-        let mut hash_table: HashMap<&DataType, Vec<usize>> = HashMap::new();
-        let r_col_data = self.columns.get(r_col).unwrap();
-        for (i, value) in r_col_data.iter().enumerate() {
-            hash_table.entry(value).or_insert_with(Vec::new).push(i);
-        }
+        let pairs = self.join_pairs(other_relation, r_col, s_col, predicate, jt, None)?;
 
-        // Probe the hash table with the second relation
-        let s_col_data = other_relation.columns.get(s_col).unwrap();
-        for (j, s_value) in s_col_data.iter().enumerate() {
-            if let Some(indices) = hash_table.get(s_value) {
-                for &i in indices {
-                    if predicate(&r_col_data[i], s_value) {
-                        // Add the values from the first relation
-                        for (key, values) in &self.columns {
-                            if let Some(column) = result_relation.columns.get_mut(key) {
-                                column.push(values[i].clone());
-                            }
-                        }
-                        // Add the values from the second relation, except for the join column
-                        for (key, values) in &other_relation.columns {
-                            if key != s_col {
-                                if let Some(column) = result_relation.columns.get_mut(key) {
-                                    column.push(values[j].clone());
-                                }
-                            }
-                        }
-                    }
-                }
+        let mut result_relation = ColumnStoreRelation::new();
+        result_relation.name = format!("{}_{}_join", self.name, other_relation.name);
+        for (column_name, side, values) in sources {
+            let (relation, column) = match side {
+                JoinSide::Left => (self, pairs.iter().map(|&(i, _)| row_value(values, i)).collect()),
+                JoinSide::Right => (other_relation, pairs.iter().map(|&(_, j)| row_value(values, j)).collect()),
+            };
+            if let Some(field) = relation.fields.get(column_name) {
+                result_relation.fields.insert(column_name.to_string(), field.clone());
             }
+            result_relation.columns.insert(column_name.to_string(), column);
+            result_relation.select_columns.push(column_name.to_string());
         }
+        result_relation.infer_missing_fields();
 
         Ok(result_relation)
     }
@@ -704,10 +2279,45 @@ impl Relation for ColumnStoreRelation {
             // Append the data to the corresponding column
             self.columns.get_mut(column_name).unwrap().push(data);
         }
+        self.sorted_columns.clear();
+
+        Ok(())
+    }
 
+    fn set_default(&mut self, column_name: &str, default: DataType) -> Result<(), RelationErrors> {
+        if !self.columns.contains_key(column_name) {
+            return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+        }
+        if let Some(field) = self.fields.get(column_name) {
+            if !default.is_null() && !field.same_type(&default) {
+                return Err(RelationErrors::InvalidInput(
+                    format!("Default {} of column {} is not of type {}", default, column_name, field.type_name())
+                ));
+            }
+        }
+        self.defaults.insert(column_name.to_string(), default);
         Ok(())
     }
 
+    fn add_partial_tuple(&mut self, values: Vec<(&str, DataType)>) -> Result<(), RelationErrors> {
+        let mut given: HashMap<&str, DataType> = HashMap::new();
+        for (column_name, value) in values {
+            if !self.select_columns.iter().any(|name| name == column_name) {
+                return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+            }
+            if given.insert(column_name, value).is_some() {
+                return Err(RelationErrors::InvalidInput(format!("Column {} is given more than once", column_name)));
+            }
+        }
+
+        let tuple = self.select_columns.iter()
+            .map(|name| given.remove(name.as_str())
+                .or_else(|| self.defaults.get(name).cloned())
+                .unwrap_or(DataType::Null))
+            .collect();
+        self.add_tuple(tuple)
+    }
+
     fn delete_tuple<F>(&mut self, column_name: &str, predicate: F) -> Result<usize, RelationErrors>
     where
         F: Fn(&DataType) -> bool,
@@ -724,22 +2334,41 @@ impl Relation for ColumnStoreRelation {
             .filter_map(|(index, value)| if predicate(value) { Some(index) } else { None })
             .collect();
 
+        self.delete_rows(&rows_to_delete)
+    }
+
+    fn delete_rows(&mut self, indices: &[usize]) -> Result<usize, RelationErrors> {
+        let num_rows = self.num_tuples()?;
+        if let Some(&row) = indices.iter().find(|&&row| row >= num_rows) {
+            return Err(RelationErrors::InvalidInput(format!("Row {} out of range", row)));
+        }
+
         // If no rows to delete, return early
-        if rows_to_delete.is_empty() {
+        if indices.is_empty() {
             return Ok(0);
         }
 
-        // Delete elements in all columns based on the indices found
+        let mut delete_mask = vec![false; num_rows];
+        for &row in indices {
+            delete_mask[row] = true;
+        }
+        let deleted = delete_mask.iter().filter(|&&delete| delete).count();
+        self.sorted_columns.clear();
+
+        // Delete elements in all columns with a single pass each
         for column_data in self.columns.values_mut() {
             let mut i = 0;
             column_data.retain(|_| {
-                let retain = !rows_to_delete.contains(&(i));
+                let retain = !delete_mask.get(i).copied().unwrap_or(false);
                 i += 1;
                 retain
             });
         }
 
-        Ok(rows_to_delete.len())
+        // row positions shifted, so existing indices are rebuilt
+        self.rebuild_indices()?;
+
+        Ok(deleted)
     }
 
     fn update_tuple<F, G>(&mut self, target_column: &str, filter_column: &str, predicate: F, update_func: G) -> Result<usize, RelationErrors>
@@ -755,6 +2384,7 @@ impl Relation for ColumnStoreRelation {
         // Get references to the filter and target columns
         let filter_column_data = self.columns[filter_column].clone();
         let target_column_data = self.columns.get_mut(target_column).unwrap();
+        self.sorted_columns.remove(target_column);
 
         // Iterate over the filter column to find indices of rows to update
         let mut updated_count = 0;
@@ -769,5 +2399,443 @@ impl Relation for ColumnStoreRelation {
         Ok(updated_count)
     }
 
+    fn update_row_by_key(&mut self, key_col: &str, key: &DataType, new_tuple: Vec<DataType>) -> Result<usize, RelationErrors> {
+        let key_column = self.columns.get(key_col)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(key_col.to_string()))?;
+
+        // the whole tuple is validated first so a mismatch leaves the relation untouched
+        if new_tuple.len() != self.select_columns.len() {
+            return Err(RelationErrors::InvalidInput("Tuple does not match relation schema".to_string()));
+        }
+        for (column_name, value) in self.select_columns.iter().zip(&new_tuple) {
+            if !self.columns.contains_key(column_name) {
+                return Err(RelationErrors::ColumnNotFound(column_name.to_string()));
+            }
+            if let Some(field) = self.fields.get(column_name) {
+                if !value.is_null() && !field.same_type(value) {
+                    return Err(RelationErrors::InvalidInput(
+                        format!("Value {} of column {} is not of type {}", value, column_name, field.type_name())
+                    ));
+                }
+            }
+        }
+
+        let rows: Vec<usize> = key_column.iter().enumerate()
+            .filter(|(_, value)| *value == key)
+            .map(|(row, _)| row)
+            .collect();
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        for (column_name, value) in self.select_columns.iter().zip(new_tuple) {
+            let column = self.columns.get_mut(column_name).unwrap();
+            for &row in &rows {
+                column[row] = value.clone();
+            }
+        }
+
+        self.sorted_columns.clear();
+        self.rebuild_indices()?;
+
+        Ok(rows.len())
+    }
+
+    fn update_rows(&mut self, indices: &[usize], column_name: &str, value: DataType) -> Result<usize, RelationErrors> {
+        let num_rows = self.num_tuples()?;
+        if let Some(&row) = indices.iter().find(|&&row| row >= num_rows) {
+            return Err(RelationErrors::InvalidInput(format!("Row {} out of range", row)));
+        }
+        if let Some(field) = self.fields.get(column_name) {
+            if !value.is_null() && !field.same_type(&value) {
+                return Err(RelationErrors::InvalidInput(
+                    format!("Value {} of column {} is not of type {}", value, column_name, field.type_name())
+                ));
+            }
+        }
+        let column = self.columns.get_mut(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        if indices.is_empty() {
+            return Ok(0);
+        }
+
+        let mut update_mask = vec![false; num_rows];
+        for &row in indices {
+            column[row] = value.clone();
+            update_mask[row] = true;
+        }
+        self.sorted_columns.remove(column_name);
+        self.rebuild_index(column_name)?;
+
+        Ok(update_mask.iter().filter(|&&updated| updated).count())
+    }
+
+    fn par_map_column<F>(&mut self, column_name: &str, f: F) -> Result<(), RelationErrors>
+    where F: Fn(&DataType) -> DataType + Sync
+    {
+        let column = self.columns.get_mut(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        #[cfg(feature = "parallel")]
+        let values = column.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let values = column.iter();
+        *column = values.map(&f).collect();
+        self.sorted_columns.remove(column_name);
+
+        // an existing index refers to the old values
+        self.rebuild_index(column_name)?;
+
+        Ok(())
+    }
+
+    fn cast_column(&mut self, column_name: &str, target: &DataType, mode: FloatToIntMode) -> Result<(), RelationErrors> {
+        let column = self.columns.get(column_name)
+            .ok_or_else(|| RelationErrors::ColumnNotFound(column_name.to_string()))?;
+
+        // convert into a new column first so a failing value leaves the relation untouched
+        let casted = column.iter()
+            .map(|value| value.cast(target, mode))
+            .collect::<Result<Vec<DataType>, RelationErrors>>()?;
+
+        self.columns.insert(column_name.to_string(), casted);
+        self.fields.insert(column_name.to_string(), target.clone());
+        self.sorted_columns.remove(column_name);
+
+        // an existing index refers to the old values
+        self.rebuild_index(column_name)?;
+
+        Ok(())
+    }
+
+    fn promote_numeric(&mut self, target: DataType) -> Result<(), RelationErrors> {
+        if !matches!(target, DataType::Int(_) | DataType::Float(_)) {
+            return Err(RelationErrors::InvalidInput(format!("Cannot promote to {}", target.type_name())));
+        }
+
+        let numeric_columns: Vec<String> = self.columns.iter()
+            .filter(|(_, values)| {
+                values.iter().all(|value| matches!(value, DataType::Int(_) | DataType::Float(_) | DataType::Null))
+                    && values.iter().any(|value| !value.is_null())
+            })
+            .map(|(column_name, _)| column_name.clone())
+            .collect();
+
+        // work on a copy so a failing cast leaves the relation untouched
+        let mut promoted = self.clone();
+        for column_name in &numeric_columns {
+            promoted.cast_column(column_name, &target, FloatToIntMode::ErrorOnFractional)?;
+        }
+
+        *self = promoted;
+        Ok(())
+    }
+
+    fn conform_to_schema(&mut self, schema: Vec<(&str, DataType)>, strict: bool) -> Result<(), RelationErrors> {
+        if strict {
+            let missing: Vec<&str> = schema.iter()
+                .map(|(column_name, _)| *column_name)
+                .filter(|column_name| !self.columns.contains_key(*column_name))
+                .collect();
+            let extra: Vec<&String> = self.select_columns.iter()
+                .filter(|column_name| !schema.iter().any(|(name, _)| name == column_name))
+                .collect();
+            if !missing.is_empty() || !extra.is_empty() {
+                return Err(RelationErrors::InvalidInput(format!("Columns missing in data: {:?}, columns missing in schema: {:?}", missing, extra)));
+            }
+        }
+
+        // work on a copy so a failing cast leaves the relation untouched
+        let num_rows = self.num_tuples().unwrap_or(0);
+        let mut conformed = self.clone();
+        conformed.columns.clear();
+        conformed.fields.clear();
+        conformed.indices.clear();
+        conformed.compact_indices.clear();
+        conformed.composite_indices.clear();
+        conformed.select_columns.clear();
+        for (column_name, data_type) in &schema {
+            let column = self.columns.get(*column_name).cloned().unwrap_or_else(|| vec![DataType::Null; num_rows]);
+            conformed.columns.insert(column_name.to_string(), column);
+            conformed.select_columns.push(column_name.to_string());
+            conformed.cast_column(column_name, data_type, FloatToIntMode::ErrorOnFractional)?;
+        }
+
+        *self = conformed;
+        Ok(())
+    }
+
+    fn coalesce_columns(&mut self, new_name: &str, sources: Vec<&str>) -> Result<(), RelationErrors> {
+        if sources.is_empty() {
+            return Err(RelationErrors::InvalidInput("Coalesce requires at least one source column".to_string()));
+        }
+
+        let source_data = sources.iter()
+            .map(|&name| self.columns.get(name).ok_or_else(|| RelationErrors::ColumnNotFound(name.to_string())))
+            .collect::<Result<Vec<&Vec<DataType>>, RelationErrors>>()?;
+
+        let num_rows = source_data.iter().map(|column| column.len()).max().unwrap_or(0);
+        let coalesced: Vec<DataType> = (0..num_rows)
+            .map(|row| {
+                source_data.iter()
+                    .filter_map(|column| column.get(row))
+                    .find(|value| !value.is_null())
+                    .cloned()
+                    .unwrap_or(DataType::Null)
+            })
+            .collect();
+
+        // an existing column (e.g. one of the sources) is replaced in place
+        if !self.columns.contains_key(new_name) {
+            self.select_columns.push(new_name.to_string());
+        }
+        if let Some(field) = sources.iter().find_map(|&name| self.fields.get(name)).cloned() {
+            self.fields.insert(new_name.to_string(), field);
+        }
+        self.columns.insert(new_name.to_string(), coalesced);
+        self.sorted_columns.remove(new_name);
+
+        Ok(())
+    }
+
+    fn add_row_id(&mut self, column_name: &str) -> Result<(), RelationErrors> {
+        if self.columns.contains_key(column_name) {
+            return Err(RelationErrors::InvalidInput(format!("Column {} already exists", column_name)));
+        }
+
+        let num_rows = self.num_tuples()?;
+        let row_ids = (0..num_rows).map(|row| DataType::Int(row as i32)).collect();
+
+        self.insert_column_at(0, column_name, row_ids)?;
+        self.fields.insert(column_name.to_string(), DataType::Int(0));
+        Ok(())
+    }
+
+    fn insert_column_at(&mut self, index: usize, column_name: &str, data: Vec<DataType>) -> Result<(), RelationErrors> {
+        if self.columns.contains_key(column_name) {
+            return Err(RelationErrors::InvalidInput(format!("Column {} already exists", column_name)));
+        }
+        if index > self.select_columns.len() {
+            return Err(RelationErrors::InvalidInput(format!("Position {} is out of range for {} columns", index, self.select_columns.len())));
+        }
+        let num_rows = self.num_tuples()?;
+        if !self.columns.is_empty() && data.len() != num_rows {
+            return Err(RelationErrors::InvalidInput(format!("Column has {} values but the relation has {} rows", data.len(), num_rows)));
+        }
+
+        // the declared type is taken from the first value that is not null
+        if let Some(field) = first_value_field(&data) {
+            self.fields.insert(column_name.to_string(), field);
+        }
+        self.columns.insert(column_name.to_string(), data);
+        self.select_columns.insert(index, column_name.to_string());
+
+        Ok(())
+    }
+
+    fn split_column(&mut self, column_name: &str, delimiter: &str, new_names: Vec<&str>, keep_original: bool) -> Result<(), RelationErrors> {
+        let column_data = self.columns.get(column_name)
+            .ok_or(RelationErrors::ColumnNotFound(column_name.to_string()))?;
+        if delimiter.is_empty() {
+            return Err(RelationErrors::InvalidInput("Delimiter must not be empty".to_string()));
+        }
+        if new_names.is_empty() {
+            return Err(RelationErrors::InvalidInput("Split requires at least one new column".to_string()));
+        }
+        if let Some(name) = new_names.iter().find(|&&name| self.columns.contains_key(name)) {
+            return Err(RelationErrors::InvalidInput(format!("Column {} already exists", name)));
+        }
+
+        let mut parts = vec![Vec::with_capacity(column_data.len()); new_names.len()];
+        for value in column_data {
+            match value {
+                DataType::String(s) => {
+                    let mut split = s.splitn(new_names.len(), delimiter);
+                    for part in parts.iter_mut() {
+                        part.push(split.next().map_or(DataType::Null, DataType::from_str));
+                    }
+                },
+                // non-string values are moved to the first column as they are
+                other => {
+                    parts[0].push(other.clone());
+                    for part in parts.iter_mut().skip(1) {
+                        part.push(DataType::Null);
+                    }
+                },
+            }
+        }
+
+        let position = self.select_columns.iter().position(|name| name == column_name)
+            .map_or(self.select_columns.len(), |position| position + 1);
+        for (offset, (name, data)) in new_names.iter().zip(parts).enumerate() {
+            self.insert_column_at(position + offset, name, data)?;
+        }
+
+        if !keep_original {
+            self.columns.remove(column_name);
+            self.fields.remove(column_name);
+            self.defaults.remove(column_name);
+            self.indices.remove(column_name);
+            self.compact_indices.remove(column_name);
+            self.composite_indices.retain(|columns, _| !columns.iter().any(|name| name == column_name));
+            self.sorted_columns.remove(column_name);
+            self.select_columns.retain(|name| name != column_name);
+        }
+
+        Ok(())
+    }
+
+
+}
+
+impl<'a> RelationView<'a> {
+    /// returns the number of tuples, the length of the longest column like `Relation::num_tuples`
+    pub fn num_tuples(&self) -> Result<usize, RelationErrors> {
+        Ok(self.columns.values().map(|data| data.len()).max().unwrap_or(0))
+    }
+
+    /// prints the view like `Relation::pretty_print`
+    pub fn pretty_print(&self) {
+        println!("{}", self.format_pretty());
+    }
+
+    /// renders the view like `Relation::format_pretty`
+    pub fn format_pretty(&self) -> String {
+        let column_widths = self.column_widths();
+
+        // Create horizontal separator lines based on individual column widths
+        let horizontal_line: String = self.select_columns.iter()
+            .filter_map(|name| column_widths.get(name).map(|entry| *entry.value()))
+            .map(|width| "─".repeat(width + 2))
+            .collect::<Vec<String>>()
+            .join("┼");
+
+        let top_border = format!("┌{}┐", horizontal_line.replace('┼', "┬"));
+        let header_separator = format!("├{}┤", horizontal_line);
+        let bottom_border = format!("└{}┘", horizontal_line.replace('┼', "┴"));
+
+        // Estimate the total capacity needed for the output string
+        let max_rows = self.columns.values().map(|col| col.len()).max().unwrap_or(0);
+        let estimated_capacity = top_border.len() + header_separator.len() + bottom_border.len()
+            + (self.select_columns.iter().map(|col| column_widths.get(col).map_or(0, |entry| *entry.value() + 4)).sum::<usize>() + 3) * (max_rows + 2);
+
+        let mut output = String::with_capacity(estimated_capacity);
+
+        // Collect the top border
+        output.push_str(&top_border);
+        output.push('\n');
+
+        // Collect Headers with padding, in the order specified by `select_columns`
+        output.push('│');
+        for column_name in &self.select_columns {
+            if let Some(width) = column_widths.get(column_name).map(|entry| *entry.value()) {
+                output.push_str(&format!(" {:width$} │", column_name, width = width));
+            }
+        }
+        output.push('\n');
+
+        if max_rows == 0 {
+            output.push_str(&bottom_border);
+            output.push_str("\n(0 rows)\n");
+            return output;
+        }
+
+        // Separator after header
+        output.push_str(&header_separator);
+        output.push('\n');
+
+        // Collect Data Rows with padding, following the order of `select_columns`
+        #[cfg(feature = "parallel")]
+        let rows = (0..max_rows).into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let rows = 0..max_rows;
+        let rows_output: Vec<String> = rows.map(|row| {
+            let mut row_output = String::with_capacity(self.select_columns.len() * 10); // Adjust the capacity as needed
+            row_output.push('│');
+            for column_name in &self.select_columns {
+                if let Some(data) = self.columns.get(column_name) {
+                    let value = data.get(row).map_or(String::new(), print_value);
+                    if let Some(width) = column_widths.get(column_name).map(|entry| *entry.value()) {
+                        row_output.push_str(&format!(" {:width$} │", value, width = width));
+                    }
+                }
+            }
+            row_output.push('\n');
+            row_output
+        }).collect();
+
+        for row in rows_output {
+            output.push_str(&row);
+        }
+
+        // Collect the bottom border
+        output.push_str(&bottom_border);
+        output.push('\n');
+
+        output
+    }
+
+    /// aggregates a column of the view like `Relation::aggr`
+    pub fn aggr(&self, column_name: &str, aggregation: Aggregation) -> Result<DataType, RelationErrors> {
+        match self.columns.get(column_name) {
+            Some(column) => aggregate_values(column_name, column, aggregation),
+            None => Err(RelationErrors::ColumnNotFound(column_name.to_string())),
+        }
+    }
+
+    /// iterates over the rows, each holding the values of the selected columns in order
+    pub fn rows(&self) -> impl Iterator<Item = Vec<&'a DataType>> + '_ {
+        (0..self.num_tuples().unwrap_or(0)).map(move |row| {
+            self.select_columns.iter().filter_map(|column_name| self.columns[column_name].get(row)).collect()
+        })
+    }
+
+    /// display width of every selected column, the longer of its name and its widest value
+    fn column_widths(&self) -> DashMap<String, usize> {
+        let column_widths = DashMap::new();
+
+        // Calculate column widths based on `select_columns` to maintain order
+        #[cfg(feature = "parallel")]
+        let column_names = self.select_columns.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let column_names = self.select_columns.iter();
+        column_names.for_each(|column_name| {
+            if let Some(data) = self.columns.get(column_name) {
+                if let Ok(max_width) = calculate_max_width(data, column_name) {
+                    column_widths.insert(column_name.clone(), max_width);
+                }
+            }
+        });
+
+        column_widths
+    }
+}
 
+#[cfg(feature = "tokio")]
+impl ColumnStoreRelation {
+    /// loads the relation from a csv file without blocking the async executor
+    ///
+    /// The file is read by the regular `load_csv` on tokio's blocking thread pool, so this has to
+    /// be awaited within a tokio runtime. The relation ends up as after `load_csv`.
+    pub async fn load_csv_async(
+        &mut self,
+        path: &str,
+        table_name: &str,
+        delimiter: &str,
+        select_columns: Vec<&str>,
+    ) -> Result<(), RelationErrors> {
+        let path = path.to_string();
+        let table_name = table_name.to_string();
+        let delimiter = delimiter.to_string();
+        let select_columns: Vec<String> = select_columns.iter().map(|&s| s.to_string()).collect();
+
+        // the relation is loaded on the blocking thread and moved back, its indices are rebuilt there as well
+        let mut relation = std::mem::take(self);
+        let (relation, result) = tokio::task::spawn_blocking(move || {
+            let result = relation.load_csv(&path, &table_name, &delimiter, select_columns.iter().map(String::as_str).collect());
+            (relation, result)
+        }).await.map_err(|e| RelationErrors::Error(format!("Loading the csv file failed: {}", e)))?;
+
+        *self = relation;
+        result
+    }
 }
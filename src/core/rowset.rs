@@ -0,0 +1,114 @@
+// Compact set of row numbers for index posting lists, modelled after roaring bitmaps:
+// rows are grouped by their upper bits and every group stores its lower 16 bits either
+// as a sorted array (sparse groups) or as a fixed size bitmap (dense groups)
+
+// groups with more rows than this are stored as bitmaps
+const ARRAY_LIMIT: usize = 4096;
+const BITMAP_WORDS: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap(Box<[u64; BITMAP_WORDS]>),
+}
+
+impl Container {
+    fn insert(&mut self, low: u16) {
+        match self {
+            Container::Array(values) => {
+                if let Err(position) = values.binary_search(&low) {
+                    values.insert(position, low);
+                }
+                if values.len() > ARRAY_LIMIT {
+                    let mut words = Box::new([0u64; BITMAP_WORDS]);
+                    for &value in values.iter() {
+                        words[value as usize / 64] |= 1 << (value % 64);
+                    }
+                    *self = Container::Bitmap(words);
+                }
+            }
+            Container::Bitmap(words) => words[low as usize / 64] |= 1 << (low % 64),
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&low).is_ok(),
+            Container::Bitmap(words) => words[low as usize / 64] & (1 << (low % 64)) != 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap(words) => words.iter().map(|word| word.count_ones() as usize).sum(),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().copied()),
+            Container::Bitmap(words) => Box::new(words.iter().enumerate().flat_map(|(i, &word)| {
+                (0..64).filter(move |bit| word & (1 << bit) != 0).map(move |bit| (i * 64 + bit) as u16)
+            })),
+        }
+    }
+}
+
+/// ordered set of row numbers with a compact memory layout for large posting lists
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RowSet {
+    // upper bits of the rows and the container of their lower 16 bits, sorted by the upper bits
+    containers: Vec<(usize, Container)>,
+}
+
+impl RowSet {
+    pub fn new() -> RowSet {
+        RowSet { containers: Vec::new() }
+    }
+
+    /// adds a row to the set, rows that are already contained are ignored
+    pub fn insert(&mut self, row: usize) {
+        let (high, low) = (row >> 16, row as u16);
+        // index creation inserts rows in ascending order, so the last container is checked first
+        let position = match self.containers.last() {
+            Some((last, _)) if *last == high => Ok(self.containers.len() - 1),
+            Some((last, _)) if *last < high => Err(self.containers.len()),
+            _ => self.containers.binary_search_by_key(&high, |(key, _)| *key),
+        };
+        match position {
+            Ok(position) => self.containers[position].1.insert(low),
+            Err(position) => self.containers.insert(position, (high, Container::Array(vec![low]))),
+        }
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        self.containers.binary_search_by_key(&(row >> 16), |(key, _)| *key)
+            .is_ok_and(|position| self.containers[position].1.contains(row as u16))
+    }
+
+    pub fn len(&self) -> usize {
+        self.containers.iter().map(|(_, container)| container.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// iterates over the rows in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.containers.iter().flat_map(|(high, container)| {
+            container.iter().map(move |low| high << 16 | low as usize)
+        })
+    }
+}
+
+impl FromIterator<usize> for RowSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(rows: I) -> RowSet {
+        let mut set = RowSet::new();
+        for row in rows {
+            set.insert(row);
+        }
+        set
+    }
+}
@@ -3,6 +3,7 @@ mod tests {
     use rustdb::database::*;
     use rustdb::interface::*;
     use rustdb::dtype::*;
+    use rustdb::errors::*;
 
     #[test]
     fn test_execute_sql() {
@@ -34,4 +35,584 @@ mod tests {
             Err(e) => panic!("Test failed with error: {}", e),
         }
     }
+
+    #[test]
+    fn test_refresh_view() {
+        let mut db = Database::new("test_db").unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.select_columns = vec!["id".to_string(), "grade".to_string()];
+        relation.columns.insert("id".to_string(), vec![DataType::Int(1), DataType::Int(2)]);
+        relation.columns.insert("grade".to_string(), vec![DataType::Float(1.5), DataType::Float(3.5)]);
+        db.add_relation("students".to_string(), relation);
+
+        db.create_view("good_students", "students", "grade", |d| matches!(d, DataType::Float(g) if *g < 3.0)).unwrap();
+        assert_eq!(db.get_relation("good_students").unwrap().columns["id"], vec![DataType::Int(1)]);
+
+        // append rows to the source, only the new matching one should be added
+        let students = db.get_relation_mut("students").unwrap();
+        students.add_tuple(vec![DataType::Int(3), DataType::Float(2.0)]).unwrap();
+        students.add_tuple(vec![DataType::Int(4), DataType::Float(4.0)]).unwrap();
+
+        assert_eq!(db.refresh_view("good_students").unwrap(), 1);
+        let view = db.get_relation("good_students").unwrap();
+        assert_eq!(view.columns["id"], vec![DataType::Int(1), DataType::Int(3)]);
+        assert_eq!(view.columns["grade"], vec![DataType::Float(1.5), DataType::Float(2.0)]);
+
+        // nothing new since the last refresh
+        assert_eq!(db.refresh_view("good_students").unwrap(), 0);
+        assert_eq!(db.get_relation("good_students").unwrap().columns["id"].len(), 2);
+    }
+
+    #[test]
+    fn test_live_aggregate() {
+        let mut db = Database::new("test_db").unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.select_columns = vec!["id".to_string(), "amount".to_string()];
+        relation.columns.insert("id".to_string(), vec![DataType::Int(1)]);
+        relation.columns.insert("amount".to_string(), vec![DataType::Int(10)]);
+        db.add_relation("orders".to_string(), relation);
+
+        db.create_live_aggregate("total", "orders", "amount", Aggregation::Sum).unwrap();
+        db.create_live_aggregate("largest", "orders", "amount", Aggregation::Max).unwrap();
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(10.0));
+
+        db.add_tuple("orders", vec![DataType::Int(2), DataType::Float(2.5)]).unwrap();
+        db.add_tuple("orders", vec![DataType::Int(3), DataType::Int(20)]).unwrap();
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(32.5));
+        assert_eq!(db.current_value("largest").unwrap(), DataType::Int(20));
+
+        // a delete is only noticed by a refresh, which recomputes the aggregate
+        db.get_relation_mut("orders").unwrap().delete_rows(&[0, 2]).unwrap();
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(32.5));
+        db.refresh_live_aggregate("total").unwrap();
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(2.5));
+
+        assert!(db.current_value("missing").is_err());
+        assert!(db.create_live_aggregate("total", "orders", "amount", Aggregation::Count).is_err());
+    }
+
+    #[test]
+    fn test_load_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data_1.csv"), "id,name\n1,Alice\n2,Bob\n").unwrap();
+        std::fs::write(dir.path().join("data_2.csv"), "id,name\n3,Charlie\n").unwrap();
+        std::fs::write(dir.path().join("other.csv"), "id,name\n4,David\n").unwrap();
+
+        let mut db = Database::new("test_db").unwrap();
+        db.create_relation("people").unwrap();
+        let pattern = dir.path().join("data_*.csv");
+        db.load_glob("people", pattern.to_str().unwrap(), ",", vec!["id", "name"]).unwrap();
+
+        let people = db.get_relation("people").unwrap();
+        assert_eq!(people.num_tuples().unwrap(), 3);
+        assert_eq!(people.columns["id"], vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+
+        // an index created before reloading covers the rows of every file
+        db.create_index("people", "id").unwrap();
+        std::fs::write(dir.path().join("data_3.csv"), "id,name\n5,Eve\n").unwrap();
+        db.load_glob("people", pattern.to_str().unwrap(), ",", vec!["id", "name"]).unwrap();
+        let people = db.get_relation("people").unwrap();
+        let selected = people.index_select("id", |id| *id == DataType::Int(5)).unwrap();
+        assert_eq!(selected.columns["name"], vec![DataType::String("Eve".to_string())]);
+
+        // a column changing its type between files is rejected
+        std::fs::write(dir.path().join("data_4.csv"), "id,name\nsix,Frank\n").unwrap();
+        assert!(matches!(db.load_glob("people", pattern.to_str().unwrap(), ",", vec!["id", "name"]),
+            Err(RelationErrors::ReadError(e)) if e.contains("Column id")));
+        std::fs::remove_file(dir.path().join("data_4.csv")).unwrap();
+
+        // a file missing one of the columns is rejected
+        std::fs::write(dir.path().join("data_5.csv"), "id\n5\n").unwrap();
+        assert!(db.load_glob("people", pattern.to_str().unwrap(), ",", vec!["id", "name"]).is_err());
+    }
+
+    #[test]
+    fn test_prepared_statement() {
+        let mut db = Database::new("test_db").unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("column1".to_string(), vec![
+            DataType::Int(1), DataType::Int(42), DataType::Int(3)
+        ]);
+        relation.columns.insert("column2".to_string(), vec![
+            DataType::String("a".to_string()), DataType::String("b".to_string()), DataType::String("c".to_string())
+        ]);
+        db.add_relation("table".to_string(), relation);
+
+        let mut statement = db.prepare("SELECT column1, column2 FROM table WHERE column1 = ?").unwrap();
+
+        // executing without a binding fails
+        assert!(statement.execute(&mut db).is_err());
+
+        let result = statement.bind(DataType::Int(42)).execute(&mut db).unwrap();
+        assert_eq!(result.columns["column2"], vec![DataType::String("b".to_string())]);
+
+        let result = statement.clear_bindings().bind(DataType::Int(3)).execute(&mut db).unwrap();
+        assert_eq!(result.columns["column2"], vec![DataType::String("c".to_string())]);
+
+        // bound values keep their type instead of being printed and parsed again
+        db.execute_sql("CREATE TABLE prices (name STRING, price FLOAT)").unwrap();
+        let mut insert = db.prepare("INSERT INTO prices VALUES (?, ?)").unwrap();
+        insert.bind(DataType::String("NULL".to_string())).bind(DataType::Float(4.0)).execute(&mut db).unwrap();
+        let prices = db.get_relation("prices").unwrap();
+        assert_eq!(prices.columns["name"], vec![DataType::String("NULL".to_string())]);
+        assert_eq!(prices.columns["price"], vec![DataType::Float(4.0)]);
+    }
+
+    #[test]
+    fn test_join_aliased_self_join() {
+        let mut db = Database::new("test_db").unwrap();
+        let mut students = ColumnStoreRelation::new();
+        students.load_csv("test.csv", "Students", ",", vec!["Number", "Name", "Grade"]).unwrap();
+        db.add_relation("Students".to_string(), students);
+
+        let result = db.join_aliased("Students", "a", "Number", "Students", "b", "Number", |l, r| l == r, JoinType::HashJoin).unwrap();
+
+        // the right join column is dropped as in every join, the rest is disambiguated by the aliases
+        let mut columns = result.select_columns.clone();
+        columns.sort();
+        assert_eq!(columns, vec!["a.Grade", "a.Name", "a.Number", "b.Grade", "b.Name"]);
+        assert_eq!(result.columns["a.Name"], result.columns["b.Name"]);
+        assert_eq!(result.columns["a.Name"].len(), 3);
+
+        assert!(db.join_aliased("Students", "a", "Number", "Students", "a", "Number", |l, r| l == r, JoinType::HashJoin).is_err());
+    }
+
+    #[test]
+    fn test_max_result_rows() {
+        let mut db = Database::new("test_db").unwrap();
+
+        // every row matches every row, 4 x 4 = 16 result rows
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("key".to_string(), vec![DataType::Int(1); 4]);
+        relation.select_columns = vec!["key".to_string()];
+        db.add_relation("r".to_string(), relation.clone());
+        db.add_relation("s".to_string(), relation);
+
+        db.set_max_result_rows(Some(10));
+        for jt in [JoinType::NestedLoop, JoinType::MergeJoin, JoinType::HashJoin] {
+            let result = db.join("r", "key", "s", "key", |l, r| l == r, jt);
+            assert!(matches!(result, Err(RelationErrors::Error(msg)) if msg == "result exceeded 10 rows"));
+        }
+
+        db.set_max_result_rows(Some(16));
+        let result = db.join("r", "key", "s", "key", |l, r| l == r, JoinType::NestedLoop).unwrap();
+        assert_eq!(result.num_tuples().unwrap(), 16);
+    }
+
+    #[test]
+    fn test_aggregate_join() {
+        let mut db = Database::new("test_db").unwrap();
+        let customers = ColumnStoreRelation::from_records("customers", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string())],
+        ]).unwrap();
+        let orders = ColumnStoreRelation::from_records("orders", vec!["customer", "amount"], vec![
+            vec![DataType::Int(1), DataType::Int(10)],
+            vec![DataType::Int(1), DataType::Int(5)],
+            vec![DataType::Int(3), DataType::Int(100)],
+        ]).unwrap();
+        db.add_relation("customers".to_string(), customers);
+        db.add_relation("orders".to_string(), orders);
+
+        // the order of a missing customer is not part of the inner join
+        let total = db.aggregate_join("customers", "id", "orders", "customer", "amount", Aggregation::Sum, JoinType::HashJoin).unwrap();
+        assert_eq!(total, DataType::Float(15.0));
+
+        // the left outer join keeps the customer without orders
+        let joined = db.join("customers", "id", "orders", "customer", |l, r| l == r, JoinType::LeftOuter).unwrap();
+        assert_eq!(joined.columns["amount"], vec![DataType::Int(10), DataType::Int(5), DataType::Null]);
+        assert_eq!(db.aggregate_join("customers", "id", "orders", "customer", "amount", Aggregation::Count, JoinType::FullOuter).unwrap(), DataType::Int(4));
+
+        assert!(matches!(
+            db.aggregate_join("customers", "id", "missing", "customer", "amount", Aggregation::Sum, JoinType::HashJoin),
+            Err(RelationErrors::RelationNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_copy_relation() {
+        let mut db = Database::new("test_db").unwrap();
+        let relation = ColumnStoreRelation::from_records("people", vec!["id"], vec![vec![DataType::Int(1)]]).unwrap();
+        db.add_relation("people".to_string(), relation);
+
+        db.copy_relation("people", "backup").unwrap();
+        db.get_relation_mut("backup").unwrap().add_tuple(vec![DataType::Int(2)]).unwrap();
+
+        assert_eq!(db.get_relation("people").unwrap().columns["id"], vec![DataType::Int(1)]);
+        assert_eq!(db.get_relation("backup").unwrap().columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(db.get_relation("backup").unwrap().get_table_name(), "backup");
+
+        assert!(matches!(db.copy_relation("people", "backup"), Err(RelationErrors::RelationAlreadyExists)));
+        assert!(matches!(db.copy_relation("missing", "other"), Err(RelationErrors::RelationNotFound)));
+    }
+
+    #[test]
+    fn test_swap_relations() {
+        let mut db = Database::new("test_db").unwrap();
+        db.add_relation("live".to_string(), ColumnStoreRelation::from_records("live", vec!["id"], vec![vec![DataType::Int(1)]]).unwrap());
+        db.add_relation("staging".to_string(), ColumnStoreRelation::from_records("staging", vec!["id"], vec![vec![DataType::Int(2)]]).unwrap());
+
+        db.swap_relations("live", "staging").unwrap();
+        assert_eq!(db.get_relation("live").unwrap().columns["id"], vec![DataType::Int(2)]);
+        assert_eq!(db.get_relation("staging").unwrap().columns["id"], vec![DataType::Int(1)]);
+        assert_eq!(db.get_relation("live").unwrap().get_table_name(), "live");
+
+        assert!(matches!(db.swap_relations("live", "missing"), Err(RelationErrors::RelationNotFound)));
+        assert_eq!(db.get_relation("live").unwrap().columns["id"], vec![DataType::Int(2)]);
+
+        // views and live aggregates follow the name, not the relation they were computed from
+        db.add_tuple("staging", vec![DataType::Int(3)]).unwrap();
+        db.create_view("big", "live", "id", |id| *id > DataType::Int(1)).unwrap();
+        db.create_live_aggregate("total", "staging", "id", Aggregation::Sum).unwrap();
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(4.0));
+        db.swap_relations("live", "staging").unwrap();
+        assert_eq!(db.get_relation("big").unwrap().columns["id"], vec![DataType::Int(3)]);
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(2.0));
+        db.add_tuple("live", vec![DataType::Int(5)]).unwrap();
+        assert_eq!(db.refresh_view("big").unwrap(), 1);
+        assert_eq!(db.get_relation("big").unwrap().columns["id"], vec![DataType::Int(3), DataType::Int(5)]);
+    }
+
+    #[test]
+    fn test_sql_where_uses_column_type() {
+        let mut db = Database::new("test_db").unwrap();
+        let mut relation = ColumnStoreRelation::from_records("addresses", vec!["zip", "distance"], vec![
+            vec![DataType::String("01234".to_string()), DataType::Float(1.5)],
+            vec![DataType::String("1234".to_string()), DataType::Float(2.0)],
+        ]).unwrap();
+        relation.cast_column("zip", &DataType::String(String::new()), FloatToIntMode::Truncate).unwrap();
+        db.add_relation("addresses".to_string(), relation);
+
+        // the literal is compared as a string, not as the number 1234
+        let result = db.execute_sql("SELECT zip FROM addresses WHERE zip = 01234").unwrap();
+        assert_eq!(result.columns["zip"], vec![DataType::String("01234".to_string())]);
+
+        // numeric columns compare by value
+        let result = db.execute_sql("SELECT zip FROM addresses WHERE distance = 2").unwrap();
+        assert_eq!(result.columns["zip"], vec![DataType::String("1234".to_string())]);
+        let result = db.execute_sql("SELECT zip FROM addresses WHERE distance = 1.50").unwrap();
+        assert_eq!(result.columns["zip"], vec![DataType::String("01234".to_string())]);
+
+        assert!(db.execute_sql("SELECT zip FROM addresses WHERE distance = far").is_err());
+    }
+
+    #[test]
+    fn test_sql_where_operators() {
+        let mut db = Database::new("test_db").unwrap();
+        db.add_relation("products".to_string(), ColumnStoreRelation::from_records("products", vec!["id", "name", "price"], vec![
+            vec![DataType::Int(1), DataType::String("tea".to_string()), DataType::Float(2.5)],
+            vec![DataType::Int(2), DataType::String("ice cream".to_string()), DataType::Float(4.0)],
+            vec![DataType::Int(3), DataType::String("coffee".to_string()), DataType::Float(2.49)],
+            vec![DataType::Int(4), DataType::String("it's".to_string()), DataType::Null],
+            vec![DataType::Int(5), DataType::String("x".to_string()), DataType::Float(10.0)],
+        ]).unwrap());
+        let mut ids = |query: &str| db.execute_sql(query).unwrap().columns["id"].clone();
+        let expect = |ids: &[i32]| ids.iter().map(|&i| DataType::Int(i)).collect::<Vec<DataType>>();
+
+        assert_eq!(ids("SELECT id FROM products WHERE id = 3"), expect(&[3]));
+        assert_eq!(ids("SELECT id FROM products WHERE id != 3"), expect(&[1, 2, 4, 5]));
+        assert_eq!(ids("SELECT id FROM products WHERE id <> 3"), expect(&[1, 2, 4, 5]));
+        assert_eq!(ids("SELECT id FROM products WHERE id < 3"), expect(&[1, 2]));
+        assert_eq!(ids("SELECT id FROM products WHERE id >= 4"), expect(&[4, 5]));
+
+        // floats compare numerically, nulls never match
+        assert_eq!(ids("SELECT id FROM products WHERE price <= 2.5"), expect(&[1, 3]));
+        assert_eq!(ids("SELECT id FROM products WHERE price>2.5"), expect(&[2, 5]));
+        assert_eq!(ids("SELECT id FROM products WHERE price != 4"), expect(&[1, 3, 5]));
+
+        // quoted literals keep spaces and may contain doubled quotes
+        assert_eq!(ids("SELECT id FROM products WHERE name = 'ice cream'"), expect(&[2]));
+        assert_eq!(ids("SELECT id FROM products WHERE name = 'it''s'"), expect(&[4]));
+        assert_eq!(ids("SELECT id FROM products WHERE name != 'x'"), expect(&[1, 2, 3, 4]));
+        assert_eq!(ids("SELECT id FROM products WHERE name > 'j' LIMIT 1"), expect(&[1]));
+
+        assert!(db.execute_sql("SELECT id FROM products WHERE name = 'tea").is_err());
+        assert!(db.execute_sql("SELECT id FROM products WHERE id => 3").is_err());
+        assert!(db.execute_sql("SELECT id FROM products WHERE id <").is_err());
+    }
+
+    #[test]
+    fn test_sql_where_and_or() {
+        let mut db = Database::new("test_db").unwrap();
+        let class = |c: &str| DataType::String(c.to_string());
+        db.add_relation("students".to_string(), ColumnStoreRelation::from_records("students", vec!["id", "class", "grade"], vec![
+            vec![DataType::Int(1), class("A"), DataType::Float(1.7)],
+            vec![DataType::Int(2), class("B"), DataType::Float(2.3)],
+            vec![DataType::Int(3), class("A"), DataType::Float(3.3)],
+            vec![DataType::Int(4), class("C"), DataType::Float(1.0)],
+            vec![DataType::Int(5), class("B"), DataType::Float(4.0)],
+        ]).unwrap());
+        let mut ids = |query: &str| db.execute_sql(query).unwrap().columns["id"].clone();
+        let expect = |ids: &[i32]| ids.iter().map(|&i| DataType::Int(i)).collect::<Vec<DataType>>();
+
+        assert_eq!(ids("SELECT id FROM students WHERE grade < 3.0 AND class = 'A'"), expect(&[1]));
+        assert_eq!(ids("SELECT id FROM students WHERE class = 'C' OR grade >= 3.3"), expect(&[3, 4, 5]));
+
+        // AND binds tighter than OR, rows matching several groups appear once in their original order
+        assert_eq!(ids("SELECT id FROM students WHERE class = 'B' AND grade > 3 OR class = 'A' and grade < 2 OR id = 1"), expect(&[1, 5]));
+        assert_eq!(ids("SELECT id FROM students WHERE class = 'A' OR class = 'B' AND grade < 3 LIMIT 2"), expect(&[1, 2]));
+
+        let mut statement = db.prepare("SELECT id FROM students WHERE class = ? AND grade > ?").unwrap();
+        let result = statement.bind(class("B")).bind(DataType::Float(2.0)).execute(&mut db).unwrap();
+        assert_eq!(result.columns["id"], expect(&[2, 5]));
+
+        assert!(matches!(db.execute_sql("SELECT id FROM students WHERE class = 'A' AND"), Err(e) if e == "Expected a condition after AND"));
+        assert!(db.execute_sql("SELECT id FROM students WHERE class = 'A' OR OR id = 1").is_err());
+        assert!(db.execute_sql("SELECT id FROM students WHERE missing = 1 OR id = 1").is_err());
+    }
+
+    #[test]
+    fn test_sql_insert_update_delete() {
+        let mut db = Database::new("test_db").unwrap();
+        let name = |n: &str| DataType::String(n.to_string());
+        db.add_relation("people".to_string(), ColumnStoreRelation::from_records("people", vec!["id", "name", "age"], vec![
+            vec![DataType::Int(1), name("Ann"), DataType::Int(30)],
+        ]).unwrap());
+        let affected = |result: ColumnStoreRelation| result.columns["rows_affected"].clone();
+
+        assert_eq!(affected(db.execute_sql("INSERT INTO people (id, name, age) VALUES (2, 'Bob', 25)").unwrap()), vec![DataType::Int(1)]);
+        assert_eq!(affected(db.execute_sql("INSERT INTO people VALUES (3,'O''Neil',40),(4,'Eve',NULL)").unwrap()), vec![DataType::Int(2)]);
+        assert_eq!(affected(db.execute_sql("INSERT INTO people (name, id) VALUES ('Dan', 5)").unwrap()), vec![DataType::Int(1)]);
+        let people = db.get_relation("people").unwrap();
+        assert_eq!(people.columns["id"], (1..=5).map(DataType::Int).collect::<Vec<DataType>>());
+        assert_eq!(people.columns["name"], vec![name("Ann"), name("Bob"), name("O'Neil"), name("Eve"), name("Dan")]);
+        assert_eq!(people.columns["age"], vec![DataType::Int(30), DataType::Int(25), DataType::Int(40), DataType::Null, DataType::Null]);
+
+        assert_eq!(affected(db.execute_sql("UPDATE people SET age = 26, name = 'Bobby' WHERE id = 2").unwrap()), vec![DataType::Int(1)]);
+        assert_eq!(affected(db.execute_sql("UPDATE people SET age = 0 WHERE age < 30 OR name = 'Dan'").unwrap()), vec![DataType::Int(2)]);
+        let people = db.get_relation("people").unwrap();
+        assert_eq!(people.columns["name"][1], name("Bobby"));
+        assert_eq!(people.columns["age"], vec![DataType::Int(30), DataType::Int(0), DataType::Int(40), DataType::Null, DataType::Int(0)]);
+
+        assert_eq!(affected(db.execute_sql("DELETE FROM people WHERE age = 0").unwrap()), vec![DataType::Int(2)]);
+        assert_eq!(db.get_relation("people").unwrap().columns["id"], vec![DataType::Int(1), DataType::Int(3), DataType::Int(4)]);
+
+        let mut statement = db.prepare("UPDATE people SET name = ? WHERE id = ?").unwrap();
+        statement.bind(name("Anna")).bind(DataType::Int(1)).execute(&mut db).unwrap();
+        assert_eq!(db.get_relation("people").unwrap().columns["name"][0], name("Anna"));
+
+        // a bad row or column changes nothing
+        assert!(matches!(db.execute_sql("INSERT INTO people VALUES (6, 'Fay')"), Err(e) if e == "Expected 3 values but got 2"));
+        assert!(db.execute_sql("INSERT INTO people (id, name, age) VALUES (6, 'Fay', 20), (7, 'Gus', 'old')").is_err());
+        assert!(db.execute_sql("INSERT INTO people (id, missing) VALUES (6, 1)").is_err());
+        assert!(db.execute_sql("UPDATE people SET age = 1, missing = 2").is_err());
+        assert!(db.execute_sql("DELETE FROM people WHERE").is_err());
+        assert!(db.execute_sql("DELETE people").is_err());
+        assert_eq!(db.get_relation("people").unwrap().columns["age"], vec![DataType::Int(30), DataType::Int(40), DataType::Null]);
+
+        assert_eq!(affected(db.execute_sql("DELETE FROM people").unwrap()), vec![DataType::Int(3)]);
+        assert_eq!(db.get_relation("people").unwrap().num_tuples().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sql_dml_updates_dependents_and_indices() {
+        let mut db = Database::new("test_db").unwrap();
+        db.add_relation("orders".to_string(), ColumnStoreRelation::from_records("orders", vec!["id", "amount"], vec![
+            vec![DataType::Int(1), DataType::Int(10)],
+            vec![DataType::Int(2), DataType::Int(20)],
+        ]).unwrap());
+        db.create_index("orders", "id").unwrap();
+        db.create_view("large", "orders", "amount", |amount| *amount > DataType::Int(15)).unwrap();
+        db.create_live_aggregate("total", "orders", "amount", Aggregation::Sum).unwrap();
+
+        db.execute_sql("UPDATE orders SET amount = 30 WHERE id = 1").unwrap();
+        assert_eq!(db.get_relation("large").unwrap().columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(50.0));
+
+        db.execute_sql("INSERT INTO orders VALUES (3, 40)").unwrap();
+        assert_eq!(db.get_relation("large").unwrap().columns["id"], vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(90.0));
+        let selected = db.get_relation("orders").unwrap().index_select("id", |id| *id == DataType::Int(3)).unwrap();
+        assert_eq!(selected.columns["amount"], vec![DataType::Int(40)]);
+
+        db.execute_sql("DELETE FROM orders WHERE amount = 20").unwrap();
+        assert_eq!(db.get_relation("large").unwrap().columns["id"], vec![DataType::Int(1), DataType::Int(3)]);
+        assert_eq!(db.current_value("total").unwrap(), DataType::Float(70.0));
+    }
+
+    #[test]
+    fn test_list_rename_drop_relations() {
+        let mut db = Database::new("test_db").unwrap();
+        assert!(db.list_relations().is_empty());
+        db.create_relation("orders").unwrap();
+        db.add_relation("people".to_string(), ColumnStoreRelation::from_records("people", vec!["id"], vec![
+            vec![DataType::Int(1)], vec![DataType::Int(2)],
+        ]).unwrap());
+        db.create_view("adults", "people", "id", |id| *id > DataType::Int(1)).unwrap();
+        assert_eq!(db.list_relations(), vec!["adults", "orders", "people"]);
+
+        // the view keeps refreshing from its renamed source
+        db.rename_relation("people", "persons").unwrap();
+        assert_eq!(db.list_relations(), vec!["adults", "orders", "persons"]);
+        assert_eq!(db.get_relation("persons").unwrap().name, "persons");
+        db.add_tuple("persons", vec![DataType::Int(3)]).unwrap();
+        assert_eq!(db.refresh_view("adults").unwrap(), 1);
+        assert_eq!(db.get_relation("adults").unwrap().columns["id"], vec![DataType::Int(2), DataType::Int(3)]);
+
+        assert!(matches!(db.rename_relation("persons", "orders"), Err(RelationErrors::RelationAlreadyExists)));
+        assert!(matches!(db.rename_relation("people", "others"), Err(RelationErrors::RelationNotFound)));
+
+        db.drop_relation("orders").unwrap();
+        assert_eq!(db.list_relations(), vec!["adults", "persons"]);
+        assert!(matches!(db.drop_relation("orders"), Err(RelationErrors::RelationNotFound)));
+        db.drop_relation("adults").unwrap();
+        assert!(matches!(db.refresh_view("adults"), Err(RelationErrors::RelationNotFound)));
+
+        // evicted relations are listed and can be renamed and dropped
+        db.add_relation("other".to_string(), ColumnStoreRelation::from_records("other", vec!["id"], vec![vec![DataType::Int(7)]]).unwrap());
+        db.set_memory_budget(Some(1)).unwrap();
+        assert!(db.is_evicted("persons"));
+        assert_eq!(db.list_relations(), vec!["other", "persons"]);
+        db.rename_relation("persons", "people").unwrap();
+        assert_eq!(db.get_relation("people").unwrap().columns["id"].len(), 3);
+        db.drop_relation("other").unwrap();
+        assert_eq!(db.list_relations(), vec!["people"]);
+
+        // views and live aggregates on a dropped relation are dropped with it and do not pick up
+        // a relation created later under the same name
+        db.set_memory_budget(None).unwrap();
+        db.create_view("high", "people", "id", |id| *id > DataType::Int(1)).unwrap();
+        db.create_view("highest", "high", "id", |id| *id > DataType::Int(2)).unwrap();
+        db.create_live_aggregate("total", "people", "id", Aggregation::Sum).unwrap();
+        db.drop_relation("people").unwrap();
+        assert!(db.list_relations().is_empty());
+        db.add_relation("people".to_string(), ColumnStoreRelation::from_records("people", vec!["id"], vec![vec![DataType::Int(9)]]).unwrap());
+        assert!(matches!(db.refresh_view("high"), Err(RelationErrors::RelationNotFound)));
+        assert!(matches!(db.refresh_view("highest"), Err(RelationErrors::RelationNotFound)));
+        assert!(matches!(db.current_value("total"), Err(RelationErrors::RelationNotFound)));
+    }
+
+    #[test]
+    fn test_sql_create_drop_table() {
+        let mut db = Database::new("test_db").unwrap();
+        db.execute_sql("CREATE TABLE items (id INT, name STRING, price FLOAT, available BOOL)").unwrap();
+        let items = db.get_relation("items").unwrap();
+        assert_eq!(items.select_columns, vec!["id", "name", "price", "available"]);
+        assert_eq!(items.fields["id"], DataType::Int(0));
+        assert_eq!(items.fields["name"], DataType::String(String::new()));
+        assert_eq!(items.fields["price"], DataType::Float(0.0));
+        assert_eq!(items.fields["available"], DataType::Bool(false));
+        assert_eq!(items.num_tuples().unwrap(), 0);
+
+        db.execute_sql("INSERT INTO items VALUES (1, 'pen', 1.5, true)").unwrap();
+        let result = db.execute_sql("SELECT name, price FROM items WHERE available = true").unwrap();
+        assert_eq!(result.columns["name"], vec![DataType::String("pen".to_string())]);
+        assert_eq!(result.columns["price"], vec![DataType::Float(1.5)]);
+        assert!(db.execute_sql("INSERT INTO items VALUES (2, 'cup', 2.0, 'yes')").is_err());
+        assert_eq!(db.get_relation("items").unwrap().num_tuples().unwrap(), 1);
+
+        // integer literals and bound integers are stored as floats in a FLOAT column
+        db.execute_sql("INSERT INTO items VALUES (2, 'cup', 3, false)").unwrap();
+        db.execute_sql("UPDATE items SET price = 5 WHERE id = 1").unwrap();
+        let mut update = db.prepare("UPDATE items SET price = ? WHERE id = ?").unwrap();
+        update.bind(DataType::Int(4)).bind(DataType::Int(2)).execute(&mut db).unwrap();
+        assert_eq!(format!("{:?}", db.get_relation("items").unwrap().columns["price"]), "[Float(5.0), Float(4.0)]");
+        db.execute_sql("DELETE FROM items WHERE id = 2").unwrap();
+
+        assert!(matches!(db.execute_sql("CREATE TABLE items (id INT)"), Err(e) if e == format!("{:?}", RelationErrors::RelationAlreadyExists)));
+        assert!(matches!(db.execute_sql("CREATE TABLE other (id DATE)"), Err(e) if e == "Unknown type DATE"));
+        assert!(db.execute_sql("CREATE TABLE other (id INT, id FLOAT)").is_err());
+        assert!(db.execute_sql("CREATE TABLE other (id INT").is_err());
+        assert!(db.execute_sql("CREATE TABLE other (id)").is_err());
+
+        db.execute_sql("DROP TABLE items").unwrap();
+        assert!(matches!(db.get_relation("items"), Err(RelationErrors::RelationNotFound)));
+        assert!(matches!(db.execute_sql("DROP TABLE items"), Err(e) if e == format!("{:?}", RelationErrors::RelationNotFound)));
+        db.execute_sql("create table items (id int)").unwrap();
+        assert_eq!(db.get_relation("items").unwrap().select_columns, vec!["id"]);
+    }
+
+    #[test]
+    fn test_memory_budget_eviction() {
+        let mut db = Database::new("test_db").unwrap();
+        let people = ColumnStoreRelation::from_records("people", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Ann".to_string())],
+            vec![DataType::Int(2), DataType::Null],
+        ]).unwrap();
+        let footprint = people.memory_footprint();
+        db.add_relation("people".to_string(), people);
+        db.add_relation("other".to_string(), ColumnStoreRelation::from_records("other", vec!["id"], vec![vec![DataType::Float(1.5)]]).unwrap());
+
+        // only one of the two relations fits, the least recently used one is evicted
+        db.set_memory_budget(Some(footprint)).unwrap();
+        assert!(db.is_evicted("people"));
+        assert!(!db.is_evicted("other"));
+
+        // a shared lookup does not reload, loading the evicted relation evicts the other one
+        assert!(matches!(db.get_relation("people"), Err(RelationErrors::Error(_))));
+        let people = db.load_relation("people").unwrap();
+        assert_eq!(people.columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(people.columns["name"], vec![DataType::String("Ann".to_string()), DataType::Null]);
+        assert_eq!(people.select_columns, vec!["id".to_string(), "name".to_string()]);
+        assert!(!db.is_evicted("people"));
+        assert!(db.is_evicted("other"));
+        // aggregating an evicted relation reads it from disk without making it resident
+        assert_eq!(db.aggregate("other", "id", Aggregation::Sum).unwrap(), DataType::Float(1.5));
+        assert!(db.is_evicted("other"));
+        db.pretty_print_relation("other").unwrap();
+
+        // without a budget nothing is evicted anymore
+        db.set_memory_budget(None).unwrap();
+        db.load_relation("other").unwrap();
+        assert!(!db.is_evicted("people") && !db.is_evicted("other"));
+        assert!(matches!(db.copy_relation("people", "other"), Err(RelationErrors::RelationAlreadyExists)));
+    }
+
+    #[test]
+    fn test_execute_file() {
+        let mut db = Database::new("test_db").unwrap();
+        db.add_relation("people".to_string(), ColumnStoreRelation::from_records("people", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string())],
+        ]).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("script.sql");
+        std::fs::write(&script, "-- lookups\nSELECT name FROM people WHERE id = 2;\n\nSELECT id FROM people; -- all ids\n;").unwrap();
+
+        let results = db.execute_file(script.to_str().unwrap()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].columns["name"], vec![DataType::String("Bob".to_string())]);
+        assert_eq!(results[1].columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+
+        // `;` and `--` inside a string literal neither end the statement nor start a comment
+        std::fs::write(&script, "CREATE TABLE notes (id INT, text STRING);\n\
+            INSERT INTO notes VALUES (1, 'a; b -- c ''d''; e'); -- trailing comment\n\
+            SELECT text FROM notes WHERE id = 1;").unwrap();
+        let results = db.execute_file(script.to_str().unwrap()).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].columns["text"], vec![DataType::String("a; b -- c 'd'; e".to_string())]);
+
+        std::fs::write(&script, "SELECT id FROM people;\nSELECT id FROM missing;").unwrap();
+        assert!(matches!(db.execute_file(script.to_str().unwrap()), Err(e) if e.starts_with("Statement 2")));
+        assert!(db.execute_file(dir.path().join("missing.sql").to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_sql_limit_offset() {
+        let mut db = Database::new("test_db").unwrap();
+        db.add_relation("numbers".to_string(), ColumnStoreRelation::from_records("numbers", vec!["id", "parity"],
+            (0..20).map(|i| vec![DataType::Int(i), DataType::String(if i % 2 == 0 { "even" } else { "odd" }.to_string())]).collect(),
+        ).unwrap());
+
+        let result = db.execute_sql("SELECT id FROM numbers LIMIT 10 OFFSET 5").unwrap();
+        assert_eq!(result.columns["id"], (5..15).map(DataType::Int).collect::<Vec<DataType>>());
+
+        // the limit applies after the WHERE clause
+        let result = db.execute_sql("SELECT id FROM numbers WHERE parity = odd LIMIT 2").unwrap();
+        assert_eq!(result.columns["id"], vec![DataType::Int(1), DataType::Int(3)]);
+        let result = db.execute_sql("SELECT id FROM numbers OFFSET 18").unwrap();
+        assert_eq!(result.columns["id"], vec![DataType::Int(18), DataType::Int(19)]);
+
+        let result = db.execute_sql("SELECT id, parity FROM numbers LIMIT 0").unwrap();
+        assert_eq!(result.select_columns, vec!["id", "parity"]);
+        assert_eq!(result.num_tuples().unwrap(), 0);
+        assert_eq!(db.execute_sql("SELECT id FROM numbers LIMIT 5 OFFSET 50").unwrap().num_tuples().unwrap(), 0);
+
+        assert!(db.execute_sql("SELECT id FROM numbers LIMIT").is_err());
+        assert!(db.execute_sql("SELECT id FROM numbers LIMIT -1").is_err());
+    }
 }
@@ -90,6 +90,35 @@ mod tests {
         assert_relation_eq!(result_relation, expected_relation);
     }
 
+    #[test]
+    fn test_merge_join_duplicate_keys() {
+        let s = |v: &str| DataType::String(v.to_string());
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Int(2), DataType::Int(2), DataType::Int(2), DataType::Int(3)]),
+                ("value1", vec![s("A"), s("B1"), s("B2"), s("B3"), s("C")]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("id", vec![DataType::Int(2), DataType::Int(2), DataType::Int(3), DataType::Int(3)]),
+                ("value2", vec![s("X1"), s("X2"), s("Y1"), s("Y2")]),
+            ]
+        );
+
+        // every left row of a key block is paired with the whole right block of that key
+        let result_relation = relation1.merge_join(&relation2, "id", "id", |a, b| a == b).unwrap();
+        assert_eq!(result_relation.columns["value1"], vec![s("B1"), s("B1"), s("B2"), s("B2"), s("B3"), s("B3"), s("C"), s("C")]);
+        assert_eq!(result_relation.columns["value2"], vec![s("X1"), s("X2"), s("X1"), s("X2"), s("X1"), s("X2"), s("Y1"), s("Y2")]);
+
+        // the same pairs as the nested loop join
+        let nested = relation1.nested_loop_join(&relation2, "id", "id", |a, b| a == b).unwrap();
+        assert_relation_eq!(result_relation, nested);
+    }
+
     #[test]
     fn test_hash_join() {
         let relation1 = create_test_relation(
@@ -121,4 +150,290 @@ mod tests {
 
         assert_relation_eq!(result_relation, expected_relation);
     }
+
+    #[test]
+    fn test_hash_join_builds_on_smaller_side() {
+        let small = create_test_relation(
+            "small",
+            vec![
+                ("id", vec![DataType::Int(3), DataType::Int(7)]),
+                ("value1", vec![DataType::String("A".to_string()), DataType::String("B".to_string())]),
+            ]
+        );
+
+        let large = create_test_relation(
+            "large",
+            vec![
+                ("id", (0..100).map(|i| DataType::Int(i % 10)).collect()),
+                ("value2", (0..100).map(DataType::Int).collect()),
+            ]
+        );
+
+        // the hash table is built on the smaller relation regardless of the join direction
+        assert_eq!(large.hash_join_build_side(&small), JoinSide::Right);
+        assert_eq!(small.hash_join_build_side(&large), JoinSide::Left);
+
+        let hash_result = large.hash_join(&small, "id", "id", |a, b| a == b).unwrap();
+        let nested_result = large.nested_loop_join(&small, "id", "id", |a, b| a == b).unwrap();
+        assert_eq!(hash_result.columns["id"].len(), 20);
+
+        // both algorithms produce the same rows, only possibly in a different order
+        let mut hash_rows: Vec<String> = (0..20).map(|row| format!("{}-{}", hash_result.columns["value2"][row], hash_result.columns["value1"][row])).collect();
+        let mut nested_rows: Vec<String> = (0..20).map(|row| format!("{}-{}", nested_result.columns["value2"][row], nested_result.columns["value1"][row])).collect();
+        hash_rows.sort();
+        nested_rows.sort();
+        assert_eq!(hash_rows, nested_rows);
+    }
+
+    #[test]
+    fn test_join_prefixed_columns() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]),
+                ("value", vec![DataType::String("A".to_string()), DataType::String("B".to_string()), DataType::String("C".to_string())]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("id", vec![DataType::Int(2), DataType::Int(3), DataType::Int(4)]),
+                ("value", vec![DataType::String("X".to_string()), DataType::String("Y".to_string()), DataType::String("Z".to_string())]),
+            ]
+        );
+
+        let prefixed = relation2.prefix_columns("relation2");
+        assert_eq!(prefixed.select_columns, vec!["relation2.id".to_string(), "relation2.value".to_string()]);
+
+        let result_relation = relation1.hash_join(&prefixed, "id", "relation2.id", |a, b| a == b).unwrap();
+        assert_eq!(result_relation.select_columns, vec!["id".to_string(), "value".to_string(), "relation2.value".to_string()]);
+        assert_eq!(result_relation.columns["value"], vec![DataType::String("B".to_string()), DataType::String("C".to_string())]);
+        assert_eq!(result_relation.columns["relation2.value"], vec![DataType::String("X".to_string()), DataType::String("Y".to_string())]);
+    }
+
+    #[test]
+    fn test_join_project() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]),
+                ("a", vec![DataType::Int(10), DataType::Int(20), DataType::Int(30)]),
+                ("b", vec![DataType::Int(11), DataType::Int(21), DataType::Int(31)]),
+                ("c", vec![DataType::Int(12), DataType::Int(22), DataType::Int(32)]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("ref", vec![DataType::Int(3), DataType::Int(1)]),
+                ("x", vec![DataType::String("X".to_string()), DataType::String("Y".to_string())]),
+                ("y", vec![DataType::String("P".to_string()), DataType::String("Q".to_string())]),
+            ]
+        );
+
+        for jt in [JoinType::NestedLoop, JoinType::HashJoin] {
+            let result = relation1.join_project(&relation2, "id", "ref", vec!["b", "x"], |a, b| a == b, jt).unwrap();
+            assert_eq!(result.select_columns, vec!["b".to_string(), "x".to_string()]);
+            assert_eq!(result.columns.len(), 2);
+            let mut rows: Vec<String> = (0..2).map(|row| format!("{} {}", result.columns["b"][row], result.columns["x"][row])).collect();
+            rows.sort();
+            assert_eq!(rows, vec!["11 Y".to_string(), "31 X".to_string()]);
+            assert_eq!(result.fields.len(), 2);
+        }
+
+        // the right join column is not part of the output, like in every join
+        assert!(relation1.join_project(&relation2, "id", "ref", vec!["b", "ref"], |a, b| a == b, JoinType::HashJoin).is_err());
+        assert!(relation1.join_project(&relation2, "id", "ref", vec!["missing"], |a, b| a == b, JoinType::HashJoin).is_err());
+    }
+
+    #[test]
+    fn test_join_key_policy() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Int(2)]),
+                ("value1", vec![DataType::String("A".to_string()), DataType::String("B".to_string())]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("ref", vec![DataType::Int(2), DataType::Int(3)]),
+                ("value2", vec![DataType::String("X".to_string()), DataType::String("Y".to_string())]),
+            ]
+        );
+
+        let dropped = relation1.join_with_key_policy(&relation2, "id", "ref", |a, b| a == b, JoinType::HashJoin, JoinKeyPolicy::DropBoth).unwrap();
+        let expected_relation = create_test_relation(
+            "relation1_relation2_join",
+            vec![
+                ("value1", vec![DataType::String("B".to_string())]),
+                ("value2", vec![DataType::String("X".to_string())]),
+            ]
+        );
+        assert_relation_eq!(dropped, expected_relation);
+
+        let kept = relation1.join_with_key_policy(&relation2, "id", "ref", |a, b| a == b, JoinType::NestedLoop, JoinKeyPolicy::KeepBoth).unwrap();
+        assert_eq!(kept.select_columns, vec!["id", "value1", "ref", "value2"]);
+        assert_eq!(kept.columns["ref"], vec![DataType::Int(2)]);
+
+        // the default matches the plain joins
+        let left = relation1.join_with_key_policy(&relation2, "id", "ref", |a, b| a == b, JoinType::MergeJoin, JoinKeyPolicy::default()).unwrap();
+        assert_relation_eq!(left, relation1.merge_join(&relation2, "id", "ref", |a, b| a == b).unwrap());
+    }
+
+    #[test]
+    fn test_join_column_order() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("name", vec![DataType::String("A".to_string()), DataType::String("B".to_string())]),
+                ("id", vec![DataType::Int(1), DataType::Int(2)]),
+                ("score", vec![DataType::Int(10), DataType::Int(20)]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("score", vec![DataType::Int(30), DataType::Int(40)]),
+                ("id", vec![DataType::Int(2), DataType::Int(1)]),
+                ("city", vec![DataType::String("X".to_string()), DataType::String("Y".to_string())]),
+            ]
+        );
+
+        // left columns in their order, then the right ones without the join column, a shared name is prefixed
+        for _ in 0..10 {
+            let joined = relation1.nested_loop_join(&relation2, "id", "id", |a, b| a == b).unwrap();
+            assert_eq!(joined.select_columns, vec!["name", "id", "score", "relation2.score", "city"]);
+            assert_eq!(joined.columns.len(), 5);
+            assert_eq!(joined.columns["score"], vec![DataType::Int(10), DataType::Int(20)]);
+            assert_eq!(joined.columns["relation2.score"], vec![DataType::Int(40), DataType::Int(30)]);
+            assert_eq!(joined.fields["relation2.score"], DataType::String(String::new()));
+        }
+
+        // both join columns are kept even though they share a name
+        let kept = relation1.join_with_key_policy(&relation2, "id", "id", |a, b| a == b, JoinType::NestedLoop, JoinKeyPolicy::KeepBoth).unwrap();
+        assert_eq!(kept.select_columns, vec!["name", "id", "score", "relation2.score", "relation2.id", "city"]);
+        assert_eq!(kept.columns["relation2.id"], kept.columns["id"]);
+    }
+
+    #[test]
+    fn test_outer_joins() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]),
+                ("value1", vec![DataType::String("A".to_string()), DataType::String("B".to_string()), DataType::String("C".to_string())]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("ref", vec![DataType::Int(4), DataType::Int(2), DataType::Int(5)]),
+                ("value2", vec![DataType::String("X".to_string()), DataType::String("Y".to_string()), DataType::String("Z".to_string())]),
+            ]
+        );
+
+        let join = |jt| relation1.join_with_key_policy(&relation2, "id", "ref", |a, b| a == b, jt, JoinKeyPolicy::KeepBoth).unwrap();
+
+        // matches first, then the unmatched left rows
+        let left = join(JoinType::LeftOuter);
+        assert_eq!(left.num_tuples().unwrap(), 3);
+        assert_eq!(left.columns["id"], vec![DataType::Int(2), DataType::Int(1), DataType::Int(3)]);
+        assert_eq!(left.columns["ref"], vec![DataType::Int(2), DataType::Null, DataType::Null]);
+        assert_eq!(left.columns["value2"], vec![DataType::String("Y".to_string()), DataType::Null, DataType::Null]);
+
+        let right = join(JoinType::RightOuter);
+        assert_eq!(right.num_tuples().unwrap(), 3);
+        assert_eq!(right.columns["ref"], vec![DataType::Int(2), DataType::Int(4), DataType::Int(5)]);
+        assert_eq!(right.columns["id"], vec![DataType::Int(2), DataType::Null, DataType::Null]);
+        assert_eq!(right.columns["value1"], vec![DataType::String("B".to_string()), DataType::Null, DataType::Null]);
+
+        let full = join(JoinType::FullOuter);
+        assert_eq!(full.num_tuples().unwrap(), 5);
+        assert_eq!(full.columns["id"], vec![DataType::Int(2), DataType::Int(1), DataType::Int(3), DataType::Null, DataType::Null]);
+        assert_eq!(full.columns["ref"], vec![DataType::Int(2), DataType::Null, DataType::Null, DataType::Int(4), DataType::Int(5)]);
+
+        // without any matching key every row is unmatched
+        let empty = create_test_relation("relation3", vec![("ref", vec![DataType::Int(9)]), ("value3", vec![DataType::Int(0)])]);
+        let disjoint = relation1.join_with_limit(&empty, "id", "ref", |a, b| a == b, JoinType::FullOuter, None).unwrap();
+        assert_eq!(disjoint.num_tuples().unwrap(), 4);
+        assert_eq!(disjoint.columns["value3"], vec![DataType::Null, DataType::Null, DataType::Null, DataType::Int(0)]);
+        assert!(relation1.join_with_limit(&empty, "id", "ref", |a, b| a == b, JoinType::FullOuter, Some(3)).is_err());
+    }
+
+    #[test]
+    fn test_hash_join_normalized() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("name", vec![DataType::String("Alice ".to_string()), DataType::String("BOB".to_string()), DataType::Int(7)]),
+                ("value1", vec![DataType::String("A".to_string()), DataType::String("B".to_string()), DataType::String("C".to_string())]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("person", vec![DataType::String("bob".to_string()), DataType::String("alice".to_string()), DataType::String("carol".to_string())]),
+                ("value2", vec![DataType::String("X".to_string()), DataType::String("Y".to_string()), DataType::String("Z".to_string())]),
+            ]
+        );
+
+        let lowercase = |value: &DataType| match value {
+            DataType::String(s) => DataType::String(s.trim().to_lowercase()),
+            other => other.clone(),
+        };
+        let result = relation1.hash_join_normalized(&relation2, "name", "person", lowercase).unwrap();
+        let expected_relation = create_test_relation(
+            "relation1_relation2_join",
+            vec![
+                ("name", vec![DataType::String("BOB".to_string()), DataType::String("Alice ".to_string())]),
+                ("value1", vec![DataType::String("B".to_string()), DataType::String("A".to_string())]),
+                ("person", vec![DataType::String("bob".to_string()), DataType::String("alice".to_string())]),
+                ("value2", vec![DataType::String("X".to_string()), DataType::String("Y".to_string())]),
+            ]
+        );
+        assert_relation_eq!(result, expected_relation);
+
+        // without normalization the keys differ
+        assert_eq!(relation1.hash_join(&relation2, "name", "person", |a, b| a == b).unwrap().num_tuples().unwrap(), 0);
+        assert!(relation1.hash_join_normalized(&relation2, "missing", "person", lowercase).is_err());
+    }
+
+    #[test]
+    fn test_null_keys_never_match() {
+        let relation1 = create_test_relation(
+            "relation1",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Null]),
+                ("value1", vec![DataType::String("A".to_string()), DataType::String("B".to_string())]),
+            ]
+        );
+
+        let relation2 = create_test_relation(
+            "relation2",
+            vec![
+                ("id", vec![DataType::Int(1), DataType::Null]),
+                ("value2", vec![DataType::String("X".to_string()), DataType::String("Y".to_string())]),
+            ]
+        );
+
+        let expected_relation = create_test_relation(
+            "relation1_relation2_join",
+            vec![
+                ("id", vec![DataType::Int(1)]),
+                ("value1", vec![DataType::String("A".to_string())]),
+                ("value2", vec![DataType::String("X".to_string())]),
+            ]
+        );
+        assert_relation_eq!(relation1.nested_loop_join(&relation2, "id", "id", |a, b| a == b).unwrap(), expected_relation);
+        assert_relation_eq!(relation1.merge_join(&relation2, "id", "id", |a, b| a == b).unwrap(), expected_relation);
+        assert_relation_eq!(relation1.hash_join(&relation2, "id", "id", |a, b| a == b).unwrap(), expected_relation);
+    }
 }
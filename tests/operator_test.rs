@@ -2,6 +2,8 @@
 mod tests {
     use rustdb::interface::*;
     use rustdb::dtype::*;
+    use rustdb::errors::*;
+    use rustdb::relation::{compare_with, predicate_from_json};
     
     fn generate_random_data() -> (Vec<DataType>, Vec<DataType>, Vec<DataType>) {
         let ids: Vec<DataType> = (1..=100).map(DataType::Int).collect();
@@ -60,6 +62,115 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_predicate_from_json() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), vec![
+            DataType::Int(1), DataType::Int(2), DataType::Int(3), DataType::Int(4)
+        ]);
+        relation.columns.insert("age".to_string(), vec![
+            DataType::Int(30), DataType::Float(30.5), DataType::Null, DataType::Int(40)
+        ]);
+        relation.select_columns = vec!["id".to_string(), "age".to_string()];
+
+        let predicate = predicate_from_json(r#"{"op": "gt", "value": 30}"#).unwrap();
+        let selected = relation.select("age", predicate).unwrap();
+        assert_eq!(selected.columns["id"], vec![DataType::Int(2), DataType::Int(4)]);
+
+        let contains = predicate_from_json(r#"{"op": "contains", "value": "li"}"#).unwrap();
+        assert!(contains(&DataType::String("Alice".to_string())));
+        assert!(!contains(&DataType::Int(1)));
+        let le = predicate_from_json(r#"{"op": "le", "value": 2.5}"#).unwrap();
+        assert!(le(&DataType::Int(2)) && !le(&DataType::Float(2.6)) && !le(&DataType::String("1".to_string())));
+
+        assert!(predicate_from_json(r#"{"op": "between", "value": 1}"#).is_err());
+        assert!(predicate_from_json(r#"{"op": "eq"}"#).is_err());
+        assert!(predicate_from_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_take_rows() {
+        let relation = ColumnStoreRelation::from_records("people", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string())],
+            vec![DataType::Int(3), DataType::String("Carol".to_string())],
+        ]).unwrap();
+
+        let taken = relation.take_rows(&[2, 0, 2]).unwrap();
+        assert_eq!(taken.columns["id"], vec![DataType::Int(3), DataType::Int(1), DataType::Int(3)]);
+        assert_eq!(taken.columns["name"], vec![
+            DataType::String("Carol".to_string()), DataType::String("Alice".to_string()), DataType::String("Carol".to_string())
+        ]);
+        assert_eq!(taken.select_columns, relation.select_columns);
+
+        assert!(matches!(relation.take_rows(&[0, 3]), Err(RelationErrors::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_reindex() {
+        let relation = ColumnStoreRelation::from_records("prices", vec!["day", "price"], vec![
+            vec![DataType::String("2024-01-01".to_string()), DataType::Float(10.0)],
+            vec![DataType::String("2024-01-03".to_string()), DataType::Float(12.0)],
+            vec![DataType::String("2024-01-02".to_string()), DataType::Float(11.0)],
+        ]).unwrap();
+
+        let days: Vec<DataType> = ["2024-01-03", "2024-01-04", "2024-01-01"].iter()
+            .map(|day| DataType::String(day.to_string()))
+            .collect();
+        let reindexed = relation.reindex("day", &days, DataType::Null).unwrap();
+        assert_eq!(reindexed.columns["day"], days);
+        assert_eq!(reindexed.columns["price"], vec![DataType::Float(12.0), DataType::Null, DataType::Float(10.0)]);
+        assert_eq!(reindexed.select_columns, relation.select_columns);
+
+        let filled = relation.reindex("day", &days[1..2], DataType::Float(0.0)).unwrap();
+        assert_eq!(filled.columns["price"], vec![DataType::Float(0.0)]);
+
+        assert!(matches!(relation.reindex("missing", &days, DataType::Null), Err(RelationErrors::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn test_select_keeps_indices() {
+        let mut relation = ColumnStoreRelation::from_records("people", vec!["id", "age"], vec![
+            vec![DataType::Int(1), DataType::Int(35)],
+            vec![DataType::Int(2), DataType::Int(25)],
+            vec![DataType::Int(3), DataType::Int(40)],
+            vec![DataType::Int(4), DataType::Int(30)],
+        ]).unwrap();
+        relation.create_index("id").unwrap();
+        relation.create_index_with("age", IndexKind::Compact).unwrap();
+        relation.create_composite_index(vec!["id", "age"]).unwrap();
+
+        // every index of the result refers to the positions of its own rows
+        let selected = relation.select("age", |age| matches!(age, DataType::Int(age) if *age >= 30)).unwrap();
+        let by_id = selected.index_select("id", |id| *id == DataType::Int(4)).unwrap();
+        assert_eq!(by_id.columns["age"], vec![DataType::Int(30)]);
+        let by_age = selected.index_select_cmp("age", CompareOp::Gt, &DataType::Int(30)).unwrap();
+        assert_eq!(by_age.columns["id"], vec![DataType::Int(1), DataType::Int(3)]);
+        let by_both = selected.composite_index_select(vec!["id", "age"], &[DataType::Int(3), DataType::Int(40)]).unwrap();
+        assert_eq!(by_both.columns["id"], vec![DataType::Int(3)]);
+    }
+
+    #[test]
+    fn test_select_indexed() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), vec![
+            DataType::Int(1), DataType::Int(2), DataType::Int(3), DataType::Int(4)
+        ]);
+        relation.columns.insert("age".to_string(), vec![
+            DataType::Int(35), DataType::Int(25), DataType::Int(40), DataType::Int(30)
+        ]);
+
+        let (selected, indices) = relation.select_indexed("age", |datum| matches!(datum, DataType::Int(age) if *age >= 30)).unwrap();
+        assert_eq!(indices, vec![0, 2, 3]);
+        for (position, &row) in indices.iter().enumerate() {
+            assert_eq!(selected.columns["id"][position], relation.columns["id"][row]);
+        }
+
+        // the indices can be used to modify the matching rows afterwards
+        relation.delete_rows(&indices).unwrap();
+        assert_eq!(relation.columns["id"], vec![DataType::Int(2)]);
+    }
+
     #[test]
     fn test_select_with_random_data() {
         // Initialize the ColumnStoreRelation and load some random data
@@ -221,6 +332,269 @@ mod tests {
         assert_eq!(relation.get_columns().get("age").unwrap(), &expected_ages);
     }
 
+    #[test]
+    fn test_sorted_column_flag() {
+        let mut relation = ColumnStoreRelation::from_records("people", vec!["id", "age"], vec![
+            vec![DataType::Int(3), DataType::Int(40)],
+            vec![DataType::Int(1), DataType::Int(25)],
+            vec![DataType::Int(2), DataType::Int(35)],
+        ]).unwrap();
+        assert!(relation.sorted_columns.is_empty());
+
+        relation.sort("id", Order::Asc).unwrap();
+        assert!(relation.sorted_columns.contains("id"));
+        assert!(!relation.sorted_columns.contains("age"));
+
+        // sorting by another column or in descending order does not leave a flag behind
+        let mut by_age = relation.clone();
+        by_age.sort("age", Order::Desc).unwrap();
+        assert!(by_age.sorted_columns.is_empty());
+
+        // the flag lets the merge join skip its sortedness scan
+        let other = relation.clone();
+        let joined = relation.merge_join(&other, "id", "id", |a, b| a == b).unwrap();
+        assert_eq!(joined.columns["id"], vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+
+        relation.add_tuple(vec![DataType::Int(0), DataType::Int(50)]).unwrap();
+        assert!(relation.sorted_columns.is_empty());
+        assert!(relation.merge_join(&other, "id", "id", |a, b| a == b).is_err());
+    }
+
+    #[test]
+    fn test_sort_mixed_types_nulls_and_nan() {
+        let values = vec![
+            DataType::String("b".to_string()), DataType::Null, DataType::Float(f64::NAN), DataType::Int(2),
+            DataType::Float(1.5), DataType::Bool(true), DataType::String("a".to_string()), DataType::Int(-3),
+            DataType::Bool(false), DataType::Float(2.0),
+        ];
+        let records = values.iter().enumerate().map(|(i, v)| vec![DataType::Int(i as i32), v.clone()]).collect();
+        let relation = ColumnStoreRelation::from_records("mixed", vec!["id", "value"], records).unwrap();
+
+        // numbers, then strings, then booleans, with NaN after the numbers and nulls last
+        let mut asc = relation.clone();
+        asc.sort("value", Order::Asc).unwrap();
+        assert_eq!(asc.columns["id"], [7, 4, 3, 9, 2, 6, 0, 8, 5, 1].map(DataType::Int).to_vec());
+
+        // descending reverses everything except NaN and nulls, which stay at the end of their group
+        let mut desc = relation.clone();
+        desc.sort("value", Order::Desc).unwrap();
+        assert_eq!(desc.columns["id"], [5, 8, 0, 6, 9, 3, 4, 7, 2, 1].map(DataType::Int).to_vec());
+
+        // the shared order agrees with the index key order apart from where nulls go
+        assert_eq!(DataType::Int(2).sql_cmp(&DataType::Float(2.0), NullsOrder::Last), std::cmp::Ordering::Less);
+        assert_eq!(DataType::Null.sql_cmp(&DataType::Int(0), NullsOrder::First), std::cmp::Ordering::Less);
+        assert_eq!(DataType::Null.sql_cmp(&DataType::Int(0), NullsOrder::Last), std::cmp::Ordering::Greater);
+        assert!(OrdDataType(DataType::Null) < OrdDataType(DataType::Float(f64::NAN)));
+    }
+
+    #[test]
+    fn test_sort_mixed_numbers_is_numeric_and_stable() {
+        let values = vec![
+            DataType::Float(2.5), DataType::Int(10), DataType::Float(-1.0), DataType::Int(2), DataType::Null,
+            DataType::Float(10.0), DataType::Int(2), DataType::Float(0.5), DataType::Null, DataType::Int(-3),
+        ];
+        let records = values.iter().enumerate().map(|(i, v)| vec![DataType::Int(i as i32), v.clone()]).collect();
+        let relation = ColumnStoreRelation::from_records("mixed", vec!["id", "value"], records).unwrap();
+
+        // numeric order across both variants, equal values and nulls keep their original order
+        let mut asc = relation.clone();
+        asc.sort("value", Order::Asc).unwrap();
+        assert_eq!(asc.columns["id"], [9, 2, 7, 3, 6, 0, 1, 5, 4, 8].map(DataType::Int).to_vec());
+
+        let mut desc = relation.clone();
+        desc.sort("value", Order::Desc).unwrap();
+        assert_eq!(desc.columns["id"], [5, 1, 0, 3, 6, 7, 2, 9, 4, 8].map(DataType::Int).to_vec());
+
+        // the external sort produces the same order
+        let temp_dir = tempfile::tempdir().unwrap();
+        let external = relation.external_sort("value", Order::Asc, 3, temp_dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(external.columns["id"], asc.columns["id"]);
+    }
+
+    #[test]
+    fn test_sort_by_multiple_keys() {
+        let name = |s: &str| DataType::String(s.to_string());
+        let mut relation = ColumnStoreRelation::from_records("students", vec!["id", "class", "grade"], vec![
+            vec![DataType::Int(1), name("b"), DataType::Int(70)],
+            vec![DataType::Int(2), name("a"), DataType::Int(85)],
+            vec![DataType::Int(3), name("b"), DataType::Int(90)],
+            vec![DataType::Int(4), name("a"), DataType::Int(85)],
+            vec![DataType::Int(5), name("a"), DataType::Int(95)],
+            vec![DataType::Int(6), name("c"), DataType::Int(60)],
+        ]).unwrap();
+        relation.create_index("id").unwrap();
+        relation.create_index_with("grade", IndexKind::Compact).unwrap();
+        relation.create_composite_index(vec!["class", "grade"]).unwrap();
+
+        // class ascending, then grade descending, ties keep their order
+        relation.sort_by(vec![("class", Order::Asc), ("grade", Order::Desc)]).unwrap();
+        assert_eq!(relation.columns["id"], [5, 2, 4, 3, 1, 6].map(DataType::Int).to_vec());
+        assert_eq!(relation.columns["grade"], [95, 85, 85, 90, 70, 60].map(DataType::Int).to_vec());
+        assert!(relation.sorted_columns.contains("class"));
+        assert!(!relation.sorted_columns.contains("grade"));
+
+        // the indices point to the new row positions
+        let selected = relation.index_select("id", |id| *id == DataType::Int(1)).unwrap();
+        assert_eq!(selected.columns["grade"], vec![DataType::Int(70)]);
+        let selected = relation.index_select_cmp("grade", CompareOp::Eq, &DataType::Int(85)).unwrap();
+        assert_eq!(selected.columns["id"], vec![DataType::Int(2), DataType::Int(4)]);
+        let selected = relation.composite_index_select(vec!["class", "grade"], &[name("c"), DataType::Int(60)]).unwrap();
+        assert_eq!(selected.columns["id"], vec![DataType::Int(6)]);
+
+        assert!(matches!(
+            relation.sort_by(vec![("class", Order::Asc), ("missing", Order::Desc)]),
+            Err(RelationErrors::ColumnNotFound(_))
+        ));
+        assert_eq!(relation.columns["id"], [5, 2, 4, 3, 1, 6].map(DataType::Int).to_vec());
+
+        relation.sort("id", Order::Desc).unwrap();
+        let selected = relation.index_select("id", |id| *id == DataType::Int(2)).unwrap();
+        assert_eq!(selected.columns["grade"], vec![DataType::Int(85)]);
+    }
+
+    #[test]
+    fn test_merge_join_mixed_numeric_keys() {
+        let left = ColumnStoreRelation::from_records("left", vec!["k", "l"], vec![
+            vec![DataType::Int(1), DataType::Int(10)],
+            vec![DataType::Float(2.5), DataType::Int(20)],
+            vec![DataType::Int(3), DataType::Int(30)],
+        ]).unwrap();
+        let right = ColumnStoreRelation::from_records("right", vec!["k", "r"], vec![
+            vec![DataType::Float(1.0), DataType::Int(100)],
+            vec![DataType::Int(2), DataType::Int(200)],
+            vec![DataType::Float(3.0), DataType::Int(300)],
+        ]).unwrap();
+
+        // a numeric comparison predicate only matches if the join advances on numeric order
+        let numeric_eq = |a: &DataType, b: &DataType| a.sql_cmp(b, NullsOrder::Last) == std::cmp::Ordering::Equal
+            || matches!((a, b), (DataType::Int(x), DataType::Float(y)) if *x as f64 == *y);
+        let joined = left.merge_join(&right, "k", "k", numeric_eq).unwrap();
+        assert_eq!(joined.columns["l"], vec![DataType::Int(10), DataType::Int(30)]);
+    }
+
+    #[test]
+    fn test_par_map_column() {
+        let (ids, names, ages) = generate_random_data();
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), ids);
+        relation.columns.insert("name".to_string(), names);
+        relation.columns.insert("age".to_string(), ages.clone());
+        relation.create_index("age").unwrap();
+
+        let double = |value: &DataType| match value {
+            DataType::Int(i) => DataType::Int(i * 2),
+            other => other.clone(),
+        };
+        let expected: Vec<DataType> = ages.iter().map(double).collect();
+
+        relation.par_map_column("age", double).unwrap();
+        assert_eq!(relation.columns["age"], expected);
+        assert!(relation.indices["age"].contains_key(&OrdDataType(DataType::Int(36))));
+        assert!(!relation.indices["age"].contains_key(&OrdDataType(DataType::Int(19))));
+
+        assert!(relation.par_map_column("missing", double).is_err());
+    }
+
+    #[test]
+    fn test_distinct_values() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("city".to_string(), vec![
+            DataType::String("Berlin".to_string()), DataType::String("Paris".to_string()),
+            DataType::String("Berlin".to_string()), DataType::Null,
+            DataType::String("Rome".to_string()), DataType::String("Paris".to_string())
+        ]);
+
+        let distinct = relation.distinct_values("city").unwrap();
+        assert_eq!(distinct, vec![
+            DataType::String("Berlin".to_string()), DataType::String("Paris".to_string()),
+            DataType::Null, DataType::String("Rome".to_string())
+        ]);
+
+        assert!(matches!(relation.distinct_values("country"), Err(RelationErrors::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn test_candidate_keys() {
+        let relation = ColumnStoreRelation::from_records("people", vec!["id", "name", "email"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string()), DataType::String("a@x.org".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string()), DataType::Null],
+            vec![DataType::Int(3), DataType::String("Alice".to_string()), DataType::String("c@x.org".to_string())],
+        ]).unwrap();
+
+        // name has a duplicate and email a null
+        assert_eq!(relation.candidate_keys(), vec!["id".to_string()]);
+    }
+
+    #[test]
+    fn test_value_counts() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("letter".to_string(), vec![
+            DataType::String("b".to_string()), DataType::String("a".to_string()), DataType::String("a".to_string())
+        ]);
+
+        let counts = relation.value_counts("letter").unwrap();
+        assert_eq!(counts.select_columns, vec!["letter", "count"]);
+        assert_eq!(counts.columns["letter"], vec![DataType::String("a".to_string()), DataType::String("b".to_string())]);
+        assert_eq!(counts.columns["count"], vec![DataType::Int(2), DataType::Int(1)]);
+    }
+
+    #[test]
+    fn test_aggr_empty() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("age".to_string(), Vec::new());
+
+        assert_eq!(relation.aggr("age", Aggregation::Count).unwrap(), DataType::Int(0));
+        assert_eq!(relation.aggr("age", Aggregation::Sum).unwrap(), DataType::Float(0.0));
+        assert_eq!(relation.aggr("age", Aggregation::Min).unwrap(), DataType::Null);
+        assert_eq!(relation.aggr("age", Aggregation::Max).unwrap(), DataType::Null);
+        assert_eq!(relation.aggr("age", Aggregation::Average).unwrap(), DataType::Null);
+    }
+
+    #[test]
+    fn test_aggr_errors() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("nulls".to_string(), vec![DataType::Null, DataType::Null]);
+        relation.columns.insert("names".to_string(), vec![DataType::String("a".to_string()), DataType::Null]);
+
+        assert!(matches!(relation.aggr("missing", Aggregation::Sum), Err(RelationErrors::ColumnNotFound(c)) if c == "missing"));
+        for aggregation in [Aggregation::Min, Aggregation::Max, Aggregation::Average] {
+            assert!(matches!(relation.aggr("nulls", aggregation), Err(RelationErrors::EmptyColumn(c)) if c == "nulls"));
+        }
+        assert!(matches!(relation.aggr("names", Aggregation::Average), Err(RelationErrors::NonNumericColumn(c)) if c == "names"));
+        assert!(matches!(relation.aggr("names", Aggregation::Sum), Err(RelationErrors::NonNumericColumn(_))));
+    }
+
+    #[test]
+    fn test_format_pretty() {
+        // runs the parallel path by default and the serial one with `--no-default-features`
+        let relation = ColumnStoreRelation::from_records("people", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(22), DataType::String("Bob".to_string())],
+        ]).unwrap();
+
+        assert_eq!(
+            relation.format_pretty(),
+            "┌────┬───────┐\n│ id │ name  │\n├────┼───────┤\n│ 1  │ Alice │\n│ 22 │ Bob   │\n└────┴───────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_format_plain() {
+        let relation = ColumnStoreRelation::from_records("people", vec!["id", "name", "grade"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string()), DataType::Float(1.5)],
+            vec![DataType::Int(22), DataType::String("Bob".to_string()), DataType::Null],
+        ]).unwrap();
+
+        assert_eq!(relation.format_plain(), "id  name   grade\n1   Alice  1.50\n22  Bob    NULL\n");
+    }
+
+    #[test]
+    fn test_format_pretty_empty() {
+        let relation = ColumnStoreRelation::from_records("people", vec!["id", "name"], Vec::new()).unwrap();
+
+        assert_eq!(relation.format_pretty(), "┌────┬──────┐\n│ id │ name │\n└────┴──────┘\n(0 rows)\n");
+    }
+
     #[test]
     fn test_aggr_count() {
         // Initialize the ColumnStoreRelation and load some data
@@ -266,7 +640,7 @@ mod tests {
 
         // Ensure the result is Ok and correct
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), DataType::Float(25.0)); // Min: 25
+        assert_eq!(result.unwrap(), DataType::Int(25)); // Min: 25
     }
 
     #[test]
@@ -282,7 +656,29 @@ mod tests {
 
         // Ensure the result is Ok and correct
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), DataType::Float(40.0)); // Max: 40
+        assert_eq!(result.unwrap(), DataType::Int(40)); // Max: 40
+    }
+
+    #[test]
+    fn test_aggr_min_max_keep_type() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("age".to_string(), vec![DataType::Int(30), DataType::Null, DataType::Int(25)]);
+        relation.columns.insert("score".to_string(), vec![DataType::Int(3), DataType::Float(2.5), DataType::Int(4)]);
+        relation.columns.insert("name".to_string(), vec![
+            DataType::String("bob".to_string()), DataType::String("Carl".to_string()), DataType::Null, DataType::String("alice".to_string())
+        ]);
+        relation.columns.insert("mixed".to_string(), vec![DataType::String("z".to_string()), DataType::Int(7)]);
+
+        assert_eq!(relation.aggr("age", Aggregation::Min).unwrap(), DataType::Int(25));
+        assert_eq!(relation.aggr("score", Aggregation::Min).unwrap(), DataType::Float(2.5));
+        assert_eq!(relation.aggr("score", Aggregation::Max).unwrap(), DataType::Int(4));
+
+        // strings compare lexically, uppercase letters sort first
+        assert_eq!(relation.aggr("name", Aggregation::Min).unwrap(), DataType::String("Carl".to_string()));
+        assert_eq!(relation.aggr("name", Aggregation::Max).unwrap(), DataType::String("bob".to_string()));
+
+        // numbers take precedence over strings in mixed columns
+        assert_eq!(relation.aggr("mixed", Aggregation::Max).unwrap(), DataType::Int(7));
     }
 
     #[test]
@@ -301,6 +697,30 @@ mod tests {
         assert_eq!(result.unwrap(), DataType::Float(32.5)); // Average: (30 + 25 + 35 + 40) / 4 = 32.5
     }
 
+    #[test]
+    fn test_ord_data_type_keys() {
+        use std::collections::BTreeMap;
+
+        let values = vec![
+            DataType::Float(f64::NAN), DataType::String("b".to_string()), DataType::Float(1.5),
+            DataType::Int(2), DataType::Null, DataType::Float(f64::NEG_INFINITY),
+            DataType::Float(2.0), DataType::String("a".to_string()), DataType::Int(-3),
+        ];
+        let mut map = BTreeMap::new();
+        for (i, value) in values.into_iter().enumerate() {
+            map.insert(OrdDataType::from(value), i);
+        }
+
+        // null first, numbers numerically with an int before an equal float and NaN last, then strings
+        let order: Vec<usize> = map.values().cloned().collect();
+        assert_eq!(order, vec![4, 5, 8, 2, 3, 6, 0, 7, 1]);
+
+        // NaN keys are found again
+        assert_eq!(map.get(&OrdDataType(DataType::Float(f64::NAN))), Some(&0));
+        map.insert(OrdDataType(DataType::Float(f64::NAN)), 9);
+        assert_eq!(map.len(), 9);
+    }
+
     #[test]
     fn test_create_index() {
         let mut relation = ColumnStoreRelation::new();
@@ -323,9 +743,34 @@ mod tests {
 
         // Verify the index
         let index = relation.indices.get("column1").expect("Index not found");
-        assert_eq!(index.get("1").unwrap(), &vec![0]);
-        assert_eq!(index.get("2").unwrap(), &vec![1, 2]);
-        assert_eq!(index.get("3").unwrap(), &vec![3]);
+        assert_eq!(index.get(&OrdDataType(DataType::Int(1))).unwrap(), &vec![0]);
+        assert_eq!(index.get(&OrdDataType(DataType::Int(2))).unwrap(), &vec![1, 2]);
+        assert_eq!(index.get(&OrdDataType(DataType::Int(3))).unwrap(), &vec![3]);
+    }
+
+    #[test]
+    fn test_save_and_load_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("age.idx");
+        let path = path.to_str().unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("age".to_string(), vec![
+            DataType::Int(30), DataType::Null, DataType::Float(2.5), DataType::Int(30), DataType::String("n/a".to_string())
+        ]);
+        relation.create_index("age").unwrap();
+        relation.save_index("age", path).unwrap();
+
+        let mut reloaded = relation.clone();
+        reloaded.indices.clear();
+        reloaded.load_index("age", path).unwrap();
+        assert_eq!(reloaded.indices["age"], relation.indices["age"]);
+
+        // an index of different column contents is rejected
+        relation.columns.get_mut("age").unwrap()[0] = DataType::Int(31);
+        assert!(relation.load_index("age", path).is_err());
+        relation.columns.get_mut("age").unwrap().push(DataType::Int(30));
+        assert!(relation.load_index("age", path).is_err());
     }
 
     #[test]
@@ -362,4 +807,625 @@ mod tests {
         assert_eq!(result.columns["column2"], vec![DataType::String("b".to_string()), DataType::String("b".to_string())]);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_coalesce_columns() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("phone".to_string(), vec![
+            DataType::String("111".to_string()), DataType::Null, DataType::Null
+        ]);
+        relation.columns.insert("mobile".to_string(), vec![
+            DataType::String("222".to_string()), DataType::String("333".to_string()), DataType::Null
+        ]);
+        relation.select_columns = vec!["phone".to_string(), "mobile".to_string()];
+
+        relation.coalesce_columns("contact", vec!["phone", "mobile"]).expect("Failed to coalesce columns");
+
+        assert_eq!(relation.columns["contact"], vec![
+            DataType::String("111".to_string()), DataType::String("333".to_string()), DataType::Null
+        ]);
+        assert_eq!(relation.select_columns, vec!["phone".to_string(), "mobile".to_string(), "contact".to_string()]);
+
+        // missing source columns are reported
+        assert!(matches!(relation.coalesce_columns("contact", vec!["phone", "fax"]), Err(RelationErrors::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn test_external_sort() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), (0..20).map(DataType::Int).collect());
+        relation.columns.insert("score".to_string(), (0..20).map(|i| DataType::Int((i * 7) % 5)).collect());
+        relation.columns.insert("name".to_string(), (0..20).map(|i| DataType::String(format!("name{}", i))).collect());
+        relation.select_columns = vec!["id".to_string(), "score".to_string(), "name".to_string()];
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (external_order, in_memory_order) in [(Order::Asc, Order::Asc), (Order::Desc, Order::Desc)] {
+            let external = relation.external_sort("score", external_order, 3, temp_dir.path().to_str().unwrap())
+                .expect("Failed to sort externally");
+
+            let mut in_memory = relation.clone();
+            in_memory.sort("score", in_memory_order).unwrap();
+
+            assert_eq!(external.select_columns, in_memory.select_columns);
+            for column in ["id", "score", "name"] {
+                assert_eq!(external.columns[column], in_memory.columns[column]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_any_all() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("age".to_string(), vec![
+            DataType::Int(30), DataType::Int(25), DataType::Int(35), DataType::Int(40)
+        ]);
+
+        assert!(relation.any("age", |d| matches!(d, DataType::Int(age) if *age > 35)).unwrap());
+        assert!(!relation.any("age", |d| matches!(d, DataType::Int(age) if *age > 40)).unwrap());
+
+        assert!(relation.all("age", |d| matches!(d, DataType::Int(age) if *age >= 25)).unwrap());
+        assert!(!relation.all("age", |d| matches!(d, DataType::Int(age) if *age > 25)).unwrap());
+
+        assert!(relation.any("missing", |_| true).is_err());
+    }
+
+    #[test]
+    fn test_limit() {
+        let relation = ColumnStoreRelation::from_records("numbers", vec!["id", "name"],
+            (0..5).map(|i| vec![DataType::Int(i), DataType::String(format!("n{}", i))]).collect(),
+        ).unwrap();
+
+        let result = relation.limit(2, 1).unwrap();
+        assert_eq!(result.columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(result.columns["name"], vec![DataType::String("n1".to_string()), DataType::String("n2".to_string())]);
+
+        // counts past the end are clamped
+        assert_eq!(relation.limit(10, 3).unwrap().columns["id"], vec![DataType::Int(3), DataType::Int(4)]);
+
+        for empty in [relation.limit(2, 5).unwrap(), relation.limit(0, 0).unwrap()] {
+            assert_eq!(empty.select_columns, vec!["id", "name"]);
+            assert_eq!(empty.columns.len(), 2);
+            assert_eq!(empty.num_tuples().unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn test_distinct() {
+        let name = |s: &str| DataType::String(s.to_string());
+        let mut relation = ColumnStoreRelation::from_records("visits", vec!["city", "day", "count"], vec![
+            vec![name("Berlin"), DataType::Int(1), DataType::Int(3)],
+            vec![name("Paris"), DataType::Int(1), DataType::Int(5)],
+            vec![name("Berlin"), DataType::Int(1), DataType::Int(3)],
+            vec![name("Berlin"), DataType::Int(2), DataType::Null],
+            vec![name("Paris"), DataType::Int(1), DataType::Int(5)],
+            vec![name("Berlin"), DataType::Int(2), DataType::Null],
+        ]).unwrap();
+        relation.fields.insert("count".to_string(), DataType::Float(0.0));
+
+        // first occurrences in their original order, nulls compare equal to each other
+        let unique = relation.distinct().unwrap();
+        assert_eq!(unique.num_tuples().unwrap(), 3);
+        assert_eq!(unique.columns["city"], vec![name("Berlin"), name("Paris"), name("Berlin")]);
+        assert_eq!(unique.columns["count"], vec![DataType::Int(3), DataType::Int(5), DataType::Null]);
+        assert_eq!(unique.select_columns, relation.select_columns);
+        assert_eq!(unique.fields["count"], DataType::Float(0.0));
+
+        let by_city = relation.distinct_on(vec!["city"]).unwrap();
+        assert_eq!(by_city.columns["city"], vec![name("Berlin"), name("Paris")]);
+        assert_eq!(by_city.columns["day"], vec![DataType::Int(1), DataType::Int(1)]);
+
+        assert!(matches!(relation.distinct_on(vec!["missing"]), Err(RelationErrors::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn test_assert_column() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("age".to_string(), vec![
+            DataType::Int(30), DataType::Int(25), DataType::Int(130), DataType::Int(-1)
+        ]);
+        let in_range = |d: &DataType| matches!(d, DataType::Int(age) if (0..=120).contains(age));
+
+        assert!(relation.assert_column("age", |d| matches!(d, DataType::Int(_))).is_ok());
+        assert!(matches!(relation.assert_column("age", in_range), Err(RelationErrors::AssertionFailed(c, 2)) if c == "age"));
+        assert!(matches!(relation.assert_column("missing", in_range), Err(RelationErrors::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn test_project_infers_fields() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), vec![DataType::Null, DataType::Int(2)]);
+        relation.columns.insert("score".to_string(), vec![DataType::Float(1.5), DataType::Null]);
+        relation.columns.insert("zip".to_string(), vec![DataType::Int(1234), DataType::Int(5678)]);
+        relation.columns.insert("empty".to_string(), vec![DataType::Null, DataType::Null]);
+        relation.select_columns = vec!["id".to_string(), "score".to_string(), "zip".to_string(), "empty".to_string()];
+        relation.fields.insert("zip".to_string(), DataType::String(String::new()));
+
+        // the first non-null value decides, declared types are kept
+        let projected = relation.project(vec!["zip", "id", "score", "empty"]).unwrap();
+        assert_eq!(projected.fields.len(), 3);
+        assert_eq!(projected.fields["id"], DataType::Int(0));
+        assert_eq!(projected.fields["score"], DataType::Float(0.0));
+        assert_eq!(projected.fields["zip"], DataType::String(String::new()));
+        assert!(projected.schema_json().contains(r#"{"name": "id", "type": "Integer"}"#));
+
+        let selected = relation.select("id", |value| !value.is_null()).unwrap();
+        assert_eq!(selected.fields["id"], DataType::Int(0));
+        assert!(!selected.fields.contains_key("score"));
+    }
+
+    #[test]
+    fn test_project_lenient() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), vec![DataType::Int(1), DataType::Int(2)]);
+        relation.columns.insert("name".to_string(), vec![
+            DataType::String("Alice".to_string()), DataType::String("Bob".to_string())
+        ]);
+        relation.select_columns = vec!["id".to_string(), "name".to_string()];
+
+        // the strict projection fails on the unknown column
+        assert!(relation.project(vec!["id", "nonexistent"]).is_err());
+
+        let projected_relation = relation.project_lenient(vec!["id", "nonexistent"]).unwrap();
+        assert_eq!(projected_relation.get_select_columns(), &vec!["id".to_string()]);
+        assert_eq!(projected_relation.columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(projected_relation.columns.len(), 1);
+    }
+
+    #[test]
+    fn test_project_view() {
+        let (ids, names, ages) = generate_random_data();
+        let mut relation = ColumnStoreRelation::new();
+        relation.name = "people".to_string();
+        relation.columns.insert("id".to_string(), ids);
+        relation.columns.insert("name".to_string(), names);
+        relation.columns.insert("age".to_string(), ages);
+        relation.select_columns = vec!["id".to_string(), "name".to_string(), "age".to_string()];
+
+        let view = relation.project_view(vec!["age", "id"]).unwrap();
+        assert_eq!(view.select_columns, vec!["age".to_string(), "id".to_string()]);
+        assert_eq!(view.num_tuples().unwrap(), 100);
+
+        // the view points at the relation's storage instead of a copy
+        assert_eq!(view.columns["age"].as_ptr(), relation.columns["age"].as_ptr());
+        assert_eq!(view.columns["id"].as_ptr(), relation.columns["id"].as_ptr());
+
+        // reads agree with the copying projection
+        let projected = relation.project(vec!["age", "id"]).unwrap();
+        assert_eq!(view.format_pretty(), projected.format_pretty());
+        assert_eq!(view.aggr("age", Aggregation::Sum).unwrap(), projected.aggr("age", Aggregation::Sum).unwrap());
+        assert!(matches!(view.aggr("name", Aggregation::Count), Err(RelationErrors::ColumnNotFound(_))));
+
+        let rows: Vec<Vec<&DataType>> = view.rows().collect();
+        assert_eq!(rows.len(), 100);
+        assert_eq!(rows[1], vec![&DataType::Int(19), &DataType::Int(2)]);
+
+        assert!(matches!(relation.project_view(vec!["id", "nonexistent"]), Err(RelationErrors::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn test_index_range_select() {
+        let mut relation = ColumnStoreRelation::from_records("items", vec!["id", "price"], vec![
+            vec![DataType::Int(1), DataType::Int(9)],
+            vec![DataType::Int(2), DataType::Int(10)],
+            vec![DataType::Int(3), DataType::Null],
+            vec![DataType::Int(4), DataType::Int(100)],
+            vec![DataType::Int(5), DataType::Float(9.5)],
+            vec![DataType::Int(6), DataType::Int(2)],
+            vec![DataType::Int(7), DataType::Int(10)],
+        ]).unwrap();
+        relation.create_index("price").unwrap();
+
+        // numeric order, not "10" < "9", and rows come in index order
+        let result = relation.index_range_select("price", &DataType::Int(9), &DataType::Int(10)).unwrap();
+        assert_eq!(result.columns["id"], vec![DataType::Int(1), DataType::Int(5), DataType::Int(2), DataType::Int(7)]);
+        assert_eq!(result.columns["price"], vec![DataType::Int(9), DataType::Float(9.5), DataType::Int(10), DataType::Int(10)]);
+
+        // the bounds are inclusive and nulls are never part of a range
+        let result = relation.index_range_select("price", &DataType::Null, &DataType::Int(2)).unwrap();
+        assert_eq!(result.columns["id"], vec![DataType::Int(6)]);
+
+        // compact indices are scanned the same way, an inverted range is empty
+        relation.create_index_with("price", IndexKind::Compact).unwrap();
+        let result = relation.index_range_select("price", &DataType::Float(9.5), &DataType::Int(1000)).unwrap();
+        assert_eq!(result.columns["id"], vec![DataType::Int(5), DataType::Int(2), DataType::Int(7), DataType::Int(4)]);
+        assert_eq!(relation.index_range_select("price", &DataType::Int(10), &DataType::Int(9)).unwrap().num_tuples().unwrap(), 0);
+
+        // integer and float keys equal to an endpoint are both in range, whatever type the endpoint has
+        let mut mixed = ColumnStoreRelation::from_records("mixed", vec!["x"], vec![
+            vec![DataType::Int(3)], vec![DataType::Float(2.0)], vec![DataType::Float(3.0)], vec![DataType::Int(2)], vec![DataType::Float(3.5)],
+        ]).unwrap();
+        mixed.create_index("x").unwrap();
+        let result = mixed.index_range_select("x", &DataType::Float(2.0), &DataType::Int(3)).unwrap();
+        assert_eq!(format!("{:?}", result.columns["x"]), "[Int(2), Float(2.0), Int(3), Float(3.0)]");
+        let result = mixed.index_range_select("x", &DataType::Float(3.0), &DataType::Int(3)).unwrap();
+        assert_eq!(format!("{:?}", result.columns["x"]), "[Int(3), Float(3.0)]");
+
+        assert!(matches!(
+            relation.index_range_select("id", &DataType::Int(1), &DataType::Int(2)),
+            Err(RelationErrors::ColumnNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_composite_index() {
+        let name = |s: &str| DataType::String(s.to_string());
+        let mut relation = ColumnStoreRelation::from_records("people", vec!["last_name", "first_name", "age"], vec![
+            vec![name("Smith"), name("Anna"), DataType::Int(30)],
+            vec![name("Jones"), name("Anna"), DataType::Int(41)],
+            vec![name("Smith"), name("Bob"), DataType::Int(25)],
+            vec![name("Smith"), name("Anna"), DataType::Int(52)],
+        ]).unwrap();
+        relation.create_composite_index(vec!["last_name", "first_name"]).unwrap();
+
+        // the same rows as filtering one column after the other
+        let indexed = relation.composite_index_select(vec!["last_name", "first_name"], &[name("Smith"), name("Anna")]).unwrap();
+        let chained = relation.select("last_name", |v| *v == name("Smith")).unwrap()
+            .select("first_name", |v| *v == name("Anna")).unwrap();
+        assert_eq!(indexed.columns, chained.columns);
+        assert_eq!(indexed.columns["age"], vec![DataType::Int(30), DataType::Int(52)]);
+
+        let none = relation.composite_index_select(vec!["last_name", "first_name"], &[name("Jones"), name("Bob")]).unwrap();
+        assert_eq!(none.num_tuples().unwrap(), 0);
+
+        // the index follows deleted rows
+        relation.delete_tuple("age", |v| *v == DataType::Int(30)).unwrap();
+        let indexed = relation.composite_index_select(vec!["last_name", "first_name"], &[name("Smith"), name("Anna")]).unwrap();
+        assert_eq!(indexed.columns["age"], vec![DataType::Int(52)]);
+
+        assert_eq!(relation.create_composite_index(vec!["last_name", "missing"]), Err("Column not found".to_string()));
+        assert!(relation.composite_index_select(vec!["first_name", "last_name"], &[name("Anna"), name("Smith")]).is_err());
+        assert!(matches!(
+            relation.composite_index_select(vec!["last_name", "first_name"], &[name("Smith")]),
+            Err(RelationErrors::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_index_select_cmp() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), vec![
+            DataType::Int(10), DataType::Int(2), DataType::Int(9), DataType::Int(1), DataType::Int(3)
+        ]);
+        relation.columns.insert("name".to_string(), vec![
+            DataType::String("a".to_string()), DataType::String("b".to_string()), DataType::String("c".to_string()),
+            DataType::String("d".to_string()), DataType::String("e".to_string())
+        ]);
+        relation.select_columns = vec!["id".to_string(), "name".to_string()];
+        relation.create_index("id").expect("Failed to create index on id");
+
+        let indexed = relation.index_select_cmp("id", CompareOp::Gt, &DataType::Int(2)).unwrap();
+        let scanned = relation.select("id", |d| matches!(d, DataType::Int(id) if *id > 2)).unwrap();
+
+        // keys are ordered numerically, so 10 is included and rows keep their original order
+        assert_eq!(indexed.columns["id"], vec![DataType::Int(10), DataType::Int(9), DataType::Int(3)]);
+        assert_eq!(indexed.columns["id"], scanned.columns["id"]);
+        assert_eq!(indexed.columns["name"], scanned.columns["name"]);
+
+        let indexed = relation.index_select_cmp("id", CompareOp::Le, &DataType::Int(2)).unwrap();
+        assert_eq!(indexed.columns["id"], vec![DataType::Int(2), DataType::Int(1)]);
+
+        // on a mixed column integers and floats with the same value are the same key
+        let mut mixed = ColumnStoreRelation::from_records("mixed", vec!["x"], vec![
+            vec![DataType::Int(3)], vec![DataType::Float(2.0)], vec![DataType::Float(3.0)], vec![DataType::Int(2)],
+            vec![DataType::Null], vec![DataType::Float(f64::NAN)], vec![DataType::String("3".to_string())],
+        ]).unwrap();
+        mixed.create_index("x").unwrap();
+        let ops = [CompareOp::Eq, CompareOp::Ne, CompareOp::Lt, CompareOp::Le, CompareOp::Gt, CompareOp::Ge];
+        for value in [DataType::Int(2), DataType::Float(2.0), DataType::Int(3), DataType::Float(3.0), DataType::Float(2.5)] {
+            for op in ops {
+                let indexed = mixed.index_select_cmp("x", op, &value).unwrap();
+                let scanned = mixed.select("x", |d| compare_with(d, op, &value)).unwrap();
+                assert_eq!(format!("{:?}", indexed.columns["x"]), format!("{:?}", scanned.columns["x"]), "{:?} {:?}", op, value);
+            }
+        }
+        let indexed = mixed.index_select_cmp("x", CompareOp::Ge, &DataType::Float(3.0)).unwrap();
+        assert_eq!(indexed.columns["x"], vec![DataType::Int(3), DataType::Float(3.0)]);
+    }
+
+    #[test]
+    fn test_compact_index() {
+        // a skewed column, two thirds of the rows share one value and spread over several row groups
+        let num_rows = 150_000;
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), (0..num_rows).map(DataType::Int).collect());
+        relation.columns.insert("kind".to_string(), (0..num_rows).map(|i| DataType::Int(if i % 3 == 0 { i % 7 } else { 100 })).collect());
+        relation.select_columns = vec!["id".to_string(), "kind".to_string()];
+
+        let mut compact = relation.clone();
+        relation.create_index_with("kind", IndexKind::Rows).unwrap();
+        compact.create_index_with("kind", IndexKind::Compact).unwrap();
+        assert!(compact.indices.is_empty());
+        assert_eq!(compact.compact_indices["kind"][&OrdDataType(DataType::Int(100))].len(), 100_000);
+
+        let predicates: Vec<fn(&DataType) -> bool> = vec![
+            |d| matches!(d, DataType::Int(100)),
+            |d| matches!(d, DataType::Int(k) if *k < 3),
+            |_| false,
+        ];
+        for predicate in predicates {
+            assert_eq!(relation.index_select("kind", predicate).unwrap().columns["id"],
+                compact.index_select("kind", predicate).unwrap().columns["id"]);
+        }
+        for op in [CompareOp::Eq, CompareOp::Ne, CompareOp::Lt, CompareOp::Ge] {
+            assert_eq!(relation.index_select_cmp("kind", op, &DataType::Int(4)).unwrap().columns["id"],
+                compact.index_select_cmp("kind", op, &DataType::Int(4)).unwrap().columns["id"]);
+        }
+
+        // the kind of index is kept when rows are deleted
+        compact.delete_rows(&[0, 1, 2]).unwrap();
+        assert!(compact.compact_indices.contains_key("kind"));
+        let selected = compact.index_select_cmp("kind", CompareOp::Eq, &DataType::Int(3)).unwrap();
+        assert_eq!(selected.columns["id"][0], DataType::Int(3));
+    }
+
+    #[test]
+    fn test_aggr_coerce() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("amount".to_string(), vec![
+            DataType::String("10".to_string()), DataType::String("2.5".to_string()),
+            DataType::String("n/a".to_string()), DataType::Int(3), DataType::Null
+        ]);
+
+        assert!(relation.aggr("amount", Aggregation::Sum).is_err());
+        let (sum, skipped) = relation.aggr_coerce("amount", Aggregation::Sum).unwrap();
+        assert_eq!(sum, DataType::Float(15.5));
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_aggr_labeled() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("grade".to_string(), vec![
+            DataType::Int(1), DataType::Int(2), DataType::Int(3)
+        ]);
+
+        let (label, value) = relation.aggr_labeled("grade", Aggregation::Sum).unwrap();
+        assert_eq!(label, "sum_grade");
+        assert_eq!(value, DataType::Float(6.0));
+
+        let (label, _) = relation.aggr_labeled("grade", Aggregation::Average).unwrap();
+        assert_eq!(label, "avg_grade");
+    }
+
+    #[test]
+    fn test_group_by() {
+        let relation = ColumnStoreRelation::from_records("grades", vec!["student", "class", "grade"], vec![
+            vec![DataType::String("Ann".to_string()), DataType::String("math".to_string()), DataType::Int(2)],
+            vec![DataType::String("Ben".to_string()), DataType::String("art".to_string()), DataType::Float(1.5)],
+            vec![DataType::String("Cem".to_string()), DataType::String("math".to_string()), DataType::Int(3)],
+            vec![DataType::String("Dan".to_string()), DataType::String("math".to_string()), DataType::Null],
+        ]).unwrap();
+
+        let result = relation.group_by(vec!["class"], vec![("grade", Aggregation::Count), ("grade", Aggregation::Average)]).unwrap();
+        assert_eq!(result.select_columns, vec!["class", "count_grade", "avg_grade"]);
+        assert_eq!(result.columns["class"], vec![DataType::String("math".to_string()), DataType::String("art".to_string())]);
+        assert_eq!(result.columns["count_grade"], vec![DataType::Int(3), DataType::Int(1)]);
+        assert_eq!(result.columns["avg_grade"], vec![DataType::Float(2.5), DataType::Float(1.5)]);
+        assert_eq!(result.fields["avg_grade"], DataType::Float(0.0));
+
+        // groups over several columns
+        let result = relation.group_by(vec!["class", "student"], vec![("grade", Aggregation::Max)]).unwrap();
+        assert_eq!(result.num_tuples().unwrap(), 4);
+        assert_eq!(result.columns["max_grade"][3], DataType::Null);
+
+        let empty = ColumnStoreRelation::from_records("grades", vec!["class", "grade"], Vec::new()).unwrap();
+        let result = empty.group_by(vec!["class"], vec![("grade", Aggregation::Sum)]).unwrap();
+        assert_eq!(result.select_columns, vec!["class", "sum_grade"]);
+        assert_eq!(result.num_tuples().unwrap(), 0);
+
+        assert!(matches!(relation.group_by(vec!["class"], vec![("student", Aggregation::Sum)]), Err(RelationErrors::NonNumericColumn(_))));
+        assert!(matches!(relation.group_by(vec!["missing"], vec![]), Err(RelationErrors::ColumnNotFound(_))));
+        assert!(relation.group_by(vec!["class"], vec![("grade", Aggregation::Sum), ("grade", Aggregation::Sum)]).is_err());
+    }
+
+    #[test]
+    fn test_group_by_time() {
+        let dates = ["2024-01-30", "2024-01-31", "2024-02-01", "2024-02-29", "2024-03-04"];
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("day".to_string(), dates.iter().map(|d| DataType::String(d.to_string())).collect());
+        relation.columns.insert("amount".to_string(), vec![
+            DataType::Int(1), DataType::Int(2), DataType::Int(3), DataType::Int(4), DataType::Int(5)
+        ]);
+
+        let monthly = relation.group_by_time("day", TimeBucket::Month, "amount", Aggregation::Sum).unwrap();
+        assert_eq!(monthly.select_columns, vec!["day", "sum_amount"]);
+        assert_eq!(monthly.columns["day"], vec![
+            DataType::String("2024-01-01".to_string()), DataType::String("2024-02-01".to_string()),
+            DataType::String("2024-03-01".to_string())
+        ]);
+        assert_eq!(monthly.columns["sum_amount"], vec![DataType::Float(3.0), DataType::Float(7.0), DataType::Float(5.0)]);
+
+        // 2024-01-29 and 2024-02-26 are mondays
+        let weekly = relation.group_by_time("day", TimeBucket::Week, "amount", Aggregation::Count).unwrap();
+        assert_eq!(weekly.columns["day"], vec![
+            DataType::String("2024-01-29".to_string()), DataType::String("2024-02-26".to_string()),
+            DataType::String("2024-03-04".to_string())
+        ]);
+        assert_eq!(weekly.columns["count_amount"], vec![DataType::Int(3), DataType::Int(1), DataType::Int(1)]);
+
+        relation.columns.insert("bad".to_string(), vec![DataType::String("2023-02-29".to_string()); 5]);
+        assert!(relation.group_by_time("bad", TimeBucket::Day, "amount", Aggregation::Sum).is_err());
+    }
+
+    #[test]
+    fn test_rank() {
+        let relation = ColumnStoreRelation::from_records("employees", vec!["department", "score"], vec![
+            vec![DataType::String("sales".to_string()), DataType::Int(70)],
+            vec![DataType::String("it".to_string()), DataType::Int(90)],
+            vec![DataType::String("sales".to_string()), DataType::Int(95)],
+            vec![DataType::String("sales".to_string()), DataType::Int(70)],
+            vec![DataType::String("it".to_string()), DataType::Int(80)],
+            vec![DataType::String("sales".to_string()), DataType::Int(60)],
+        ]).unwrap();
+
+        // the tied 70s share rank 2 and the next score gets rank 4
+        let ranked = relation.rank(Some("department"), "score", Order::Desc).unwrap();
+        assert_eq!(ranked.select_columns, vec!["department", "score", "rank"]);
+        assert_eq!(ranked.columns["rank"], vec![
+            DataType::Int(2), DataType::Int(1), DataType::Int(1), DataType::Int(2), DataType::Int(2), DataType::Int(4)
+        ]);
+
+        let ranked = relation.rank(None, "score", Order::Asc).unwrap();
+        assert_eq!(ranked.columns["rank"], vec![
+            DataType::Int(2), DataType::Int(5), DataType::Int(6), DataType::Int(2), DataType::Int(4), DataType::Int(1)
+        ]);
+    }
+
+    #[test]
+    fn test_cumulative() {
+        let relation = ColumnStoreRelation::from_records("sales", vec!["amount", "region"], vec![
+            vec![DataType::Int(1), DataType::Null],
+            vec![DataType::Int(2), DataType::String("west".to_string())],
+            vec![DataType::Int(3), DataType::String("east".to_string())],
+        ]).unwrap();
+
+        let result = relation.cumulative("amount", Aggregation::Sum).unwrap();
+        assert_eq!(result.select_columns, vec!["amount", "region", "cumulative_sum_amount"]);
+        assert_eq!(result.columns["cumulative_sum_amount"], vec![DataType::Float(1.0), DataType::Float(3.0), DataType::Float(6.0)]);
+        assert_eq!(result.fields["cumulative_sum_amount"], DataType::Float(0.0));
+
+        let result = relation.cumulative("amount", Aggregation::Count).unwrap();
+        assert_eq!(result.columns["cumulative_count_amount"], vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+
+        // the leading null has no minimum yet
+        let result = relation.cumulative("region", Aggregation::Min).unwrap();
+        assert_eq!(result.columns["cumulative_min_region"], vec![
+            DataType::Null, DataType::String("west".to_string()), DataType::String("east".to_string())
+        ]);
+
+        assert!(matches!(relation.cumulative("region", Aggregation::Sum), Err(RelationErrors::NonNumericColumn(_))));
+        assert!(matches!(relation.cumulative("missing", Aggregation::Max), Err(RelationErrors::ColumnNotFound(_))));
+        assert!(result.cumulative("region", Aggregation::Min).is_err());
+    }
+
+    #[test]
+    fn test_diff_keyed() {
+        let old = ColumnStoreRelation::from_records("people", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string())],
+            vec![DataType::Int(3), DataType::String("Carol".to_string())],
+        ]).unwrap();
+        let new = ColumnStoreRelation::from_records("people", vec!["name", "id"], vec![
+            vec![DataType::String("Alice".to_string()), DataType::Int(1)],
+            vec![DataType::String("Carla".to_string()), DataType::Int(3)],
+            vec![DataType::String("Dave".to_string()), DataType::Int(4)],
+        ]).unwrap();
+
+        let (added, removed, changed) = old.diff_keyed(&new, "id").unwrap();
+        assert_eq!(added.select_columns, vec!["id", "name"]);
+        assert_eq!(added.columns["id"], vec![DataType::Int(4)]);
+        assert_eq!(removed.columns["id"], vec![DataType::Int(2)]);
+        assert_eq!(changed.columns["id"], vec![DataType::Int(3)]);
+        assert_eq!(changed.columns["name"], vec![DataType::String("Carla".to_string())]);
+
+        assert!(old.diff_keyed(&new, "missing").is_err());
+    }
+
+    #[test]
+    fn test_union_by_name() {
+        let left = ColumnStoreRelation::from_records("left", vec!["id", "name"], vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string())],
+        ]).unwrap();
+        let right = ColumnStoreRelation::from_records("right", vec!["name", "id"], vec![
+            vec![DataType::String("Bob".to_string()), DataType::Int(2)],
+            vec![DataType::String("Carol".to_string()), DataType::Int(3)],
+        ]).unwrap();
+
+        let union_all = left.union_by_name(&right, true).unwrap();
+        assert_eq!(union_all.select_columns, vec!["id", "name"]);
+        assert_eq!(union_all.columns["id"], vec![DataType::Int(1), DataType::Int(2), DataType::Int(2), DataType::Int(3)]);
+
+        let union = left.union_by_name(&right, false).unwrap();
+        assert_eq!(union.columns["id"], vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+        assert_eq!(union.columns["name"], vec![
+            DataType::String("Alice".to_string()), DataType::String("Bob".to_string()), DataType::String("Carol".to_string())
+        ]);
+
+        let other = ColumnStoreRelation::from_records("other", vec!["id", "email"], Vec::new()).unwrap();
+        assert!(matches!(left.union_by_name(&other, true), Err(RelationErrors::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_set_operators() {
+        let records = |ids: &[i32]| ids.iter().map(|&i| vec![DataType::Int(i), DataType::String(format!("n{}", i))]).collect();
+        let left = ColumnStoreRelation::from_records("left", vec!["id", "name"], records(&[1, 2, 2, 3, 4])).unwrap();
+        let right = ColumnStoreRelation::from_records("right", vec!["id", "name"], records(&[4, 3, 5, 5])).unwrap();
+        let ids = |relation: ColumnStoreRelation| relation.columns["id"].clone();
+
+        assert_eq!(ids(left.union(&right).unwrap()), [1, 2, 3, 4, 5].map(DataType::Int).to_vec());
+        assert_eq!(ids(left.intersect(&right).unwrap()), [3, 4].map(DataType::Int).to_vec());
+        assert_eq!(ids(left.difference(&right).unwrap()), [1, 2].map(DataType::Int).to_vec());
+        assert_eq!(ids(right.difference(&left).unwrap()), [5].map(DataType::Int).to_vec());
+        assert_eq!(left.union(&right).unwrap().columns["name"][4], DataType::String("n5".to_string()));
+
+        // a different column order is a different schema
+        let mut reordered = right.clone();
+        reordered.select_columns = vec!["name".to_string(), "id".to_string()];
+        for result in [left.union(&reordered), left.intersect(&reordered), left.difference(&reordered)] {
+            assert!(matches!(result, Err(RelationErrors::Error(msg)) if msg.contains("different schemas")));
+        }
+    }
+
+    #[test]
+    fn test_ratio() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("passed".to_string(), vec![
+            DataType::Int(3), DataType::Int(5), DataType::Int(0)
+        ]);
+        relation.columns.insert("total".to_string(), vec![
+            DataType::Int(4), DataType::Int(10), DataType::Int(6)
+        ]);
+        relation.columns.insert("none".to_string(), vec![
+            DataType::Int(0), DataType::Int(0), DataType::Int(0)
+        ]);
+
+        let ratio = relation.ratio("passed", "total", Aggregation::Sum, Aggregation::Sum).unwrap();
+        assert_eq!(ratio, 0.4); // 8 / 20
+
+        let ratio = relation.ratio("passed", "total", Aggregation::Sum, Aggregation::Count).unwrap();
+        assert!((ratio - 8.0 / 3.0).abs() < 1e-9);
+
+        // dividing by a zero aggregate is rejected
+        assert!(matches!(
+            relation.ratio("passed", "none", Aggregation::Sum, Aggregation::Sum),
+            Err(RelationErrors::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_explode() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("id".to_string(), vec![DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+        relation.columns.insert("tags".to_string(), vec![
+            DataType::String("x;y".to_string()), DataType::String("z".to_string()), DataType::Int(7)
+        ]);
+        relation.select_columns = vec!["id".to_string(), "tags".to_string()];
+
+        let exploded = relation.explode("tags", ";").expect("Failed to explode column");
+
+        assert_eq!(exploded.columns["id"], vec![DataType::Int(1), DataType::Int(1), DataType::Int(2), DataType::Int(3)]);
+        assert_eq!(exploded.columns["tags"], vec![
+            DataType::String("x".to_string()), DataType::String("y".to_string()),
+            DataType::String("z".to_string()), DataType::Int(7)
+        ]);
+        assert_eq!(exploded.select_columns, relation.select_columns);
+    }
+
+    #[test]
+    fn test_correlation() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.columns.insert("x".to_string(), vec![
+            DataType::Int(1), DataType::Int(2), DataType::Null, DataType::Int(3), DataType::Int(4)
+        ]);
+        relation.columns.insert("y".to_string(), vec![
+            DataType::Float(2.5), DataType::Float(4.5), DataType::Float(100.0), DataType::Float(6.5), DataType::Float(8.5)
+        ]);
+        relation.columns.insert("constant".to_string(), vec![DataType::Int(1); 5]);
+
+        // the row with a null is skipped
+        let correlation = relation.correlation("x", "y").unwrap();
+        assert!((correlation - 1.0).abs() < 1e-9);
+
+        assert!(relation.correlation("x", "constant").is_err());
+    }
+}
@@ -2,7 +2,9 @@
 mod tests {
     use rustdb::interface::*;
     use rustdb::dtype::*;
-    use std::collections::HashMap;
+    use rustdb::errors::*;
+    use std::collections::{HashMap, HashSet};
+    use std::io::Write;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -12,10 +14,7 @@ mod tests {
         let file_path = "test.csv";
         let tbl_name = "students";
 
-        match tbl.load_csv(file_path, tbl_name, ",", vec!["Number", "Name", "Grade"]) {
-            Ok(()) => assert!(true),
-            Err(_) => assert!(false),
-        }  
+        assert!(tbl.load_csv(file_path, tbl_name, ",", vec!["Number", "Name", "Grade"]).is_ok());
 
         // check name and number of tuples
         let tpls = tbl.num_tuples().expect("Error obtaining number of tuples.");
@@ -33,22 +32,16 @@ mod tests {
         let grades = [1.5, 2.5, 3.33];
         let numbers = [0, 1, 3];
 
-        let mut i = 0;
-        for t in name_rel.iter() {
+        for (i, t) in name_rel.iter().enumerate() {
             assert_eq!(DataType::String(names[i].to_string()), t.clone());
-            i += 1;
         }
 
-        let mut i = 0;
-        for t in grade_rel.iter() {
+        for (i, t) in grade_rel.iter().enumerate() {
             assert_eq!(DataType::Float(grades[i]), t.clone());
-            i += 1;
         }
 
-        i = 0;
-        for t in number_rel.iter() {
+        for (i, t) in number_rel.iter().enumerate() {
             assert_eq!(DataType::Int(numbers[i]), t.clone());
-            i += 1;
         }
         Ok(())
     }
@@ -59,7 +52,7 @@ mod tests {
         let data_types = vec![
             DataType::String("Test".to_string()),
             DataType::Int(42),
-            DataType::Float(3.14),
+            DataType::Float(2.75),
         ];
 
         // Serialize the DataType instances
@@ -80,10 +73,7 @@ mod tests {
         let file_path = "test.csv";
         let tbl_name = "students";
 
-        match tbl.load_csv(file_path, tbl_name, ",", vec!["Number", "Name", "Grade"]) {
-            Ok(()) => assert!(true),
-            Err(_) => assert!(false),
-        }  
+        assert!(tbl.load_csv(file_path, tbl_name, ",", vec!["Number", "Name", "Grade"]).is_ok());
 
         let predicate = |data: &DataType| match data {
             DataType::Float(value) => *value < 5.0,
@@ -101,10 +91,7 @@ mod tests {
         let file_path = "test.csv";
         let tbl_name = "students";
 
-        match tbl.load_csv(file_path, tbl_name, ",", vec!["Number", "Name", "Grade"]) {
-            Ok(()) => assert!(true),
-            Err(_) => assert!(false),
-        }  
+        assert!(tbl.load_csv(file_path, tbl_name, ",", vec!["Number", "Name", "Grade"]).is_ok());
 
         // check name and number of tuples
         let tpls = tbl.num_tuples().expect("Error obtaining number of tuples.");
@@ -122,22 +109,16 @@ mod tests {
         let grades = [1.5, 2.5, 3.33];
         let numbers = [0, 1, 3];
 
-        let mut i = 0;
-        for t in name_rel.iter() {
+        for (i, t) in name_rel.iter().enumerate() {
             assert_eq!(DataType::String(names[i].to_string()), t.clone());
-            i += 1;
         }
 
-        i = 0;
-        for t in grade_rel.iter() {
+        for (i, t) in grade_rel.iter().enumerate() {
             assert_eq!(DataType::Float(grades[i]), t.clone());
-            i += 1;
         }
 
-        i = 0;
-        for t in number_rel.iter() {
+        for (i, t) in number_rel.iter().enumerate() {
             assert_eq!(DataType::Int(numbers[i]), t.clone());
-            i += 1;
         }
         Ok(())
     }
@@ -148,7 +129,7 @@ mod tests {
         let data_types = vec![
             DataType::String("Test".to_string()),
             DataType::Int(42),
-            DataType::Float(3.14),
+            DataType::Float(2.75),
         ];
 
         // Serialize the DataType instances
@@ -170,6 +151,10 @@ mod tests {
             columns: HashMap::new(),
             select_columns: vec!["id".to_string(), "name".to_string(), "age".to_string()],
             indices: HashMap::new(),
+            compact_indices: HashMap::new(),
+            composite_indices: HashMap::new(),
+            sorted_columns: HashSet::new(),
+            defaults: HashMap::new(),
         };
 
         relation.fields.insert("id".to_string(), DataType::Int(0));
@@ -254,6 +239,48 @@ mod tests {
         assert_eq!(updated_age_column[2], DataType::Int(35));
     }
 
+    #[test]
+    fn test_update_row_by_key() {
+        let mut relation = setup_relation();
+        relation.create_index("name").unwrap();
+
+        let new_tuple = vec![DataType::Int(7), DataType::String("Robert".to_string()), DataType::Int(31)];
+        assert_eq!(relation.update_row_by_key("id", &DataType::Int(2), new_tuple).unwrap(), 1);
+        assert_eq!(relation.columns["id"], vec![DataType::Int(1), DataType::Int(7)]);
+        assert_eq!(relation.columns["name"][1], DataType::String("Robert".to_string()));
+        assert_eq!(relation.columns["age"][1], DataType::Int(31));
+        assert!(relation.indices["name"].contains_key(&OrdDataType(DataType::String("Robert".to_string()))));
+
+        // no matching key, wrong arity and wrong types are rejected or ignored without changes
+        assert_eq!(relation.update_row_by_key("id", &DataType::Int(2), vec![DataType::Int(2), DataType::Null, DataType::Null]).unwrap(), 0);
+        assert!(relation.update_row_by_key("id", &DataType::Int(1), vec![DataType::Int(1)]).is_err());
+        let wrong_type = vec![DataType::String("one".to_string()), DataType::Null, DataType::Null];
+        assert!(matches!(relation.update_row_by_key("id", &DataType::Int(1), wrong_type), Err(RelationErrors::InvalidInput(_))));
+        assert!(matches!(relation.update_row_by_key("missing", &DataType::Int(1), vec![]), Err(RelationErrors::ColumnNotFound(_))));
+        assert_eq!(relation.columns["id"], vec![DataType::Int(1), DataType::Int(7)]);
+    }
+
+    #[test]
+    fn test_add_partial_tuple() {
+        let mut relation = setup_relation();
+        relation.set_default("age", DataType::Int(18)).unwrap();
+
+        relation.add_partial_tuple(vec![("name", DataType::String("Carol".to_string())), ("id", DataType::Int(3))]).unwrap();
+        assert_eq!(relation.columns["id"][2], DataType::Int(3));
+        assert_eq!(relation.columns["name"][2], DataType::String("Carol".to_string()));
+        assert_eq!(relation.columns["age"][2], DataType::Int(18));
+
+        // columns without a default are filled with null
+        relation.add_partial_tuple(vec![("age", DataType::Int(40))]).unwrap();
+        assert_eq!(relation.columns["id"][3], DataType::Null);
+        assert_eq!(relation.columns["age"][3], DataType::Int(40));
+
+        assert!(matches!(relation.add_partial_tuple(vec![("missing", DataType::Null)]), Err(RelationErrors::ColumnNotFound(_))));
+        assert!(relation.add_partial_tuple(vec![("id", DataType::Int(5)), ("id", DataType::Int(6))]).is_err());
+        assert!(matches!(relation.set_default("missing", DataType::Null), Err(RelationErrors::ColumnNotFound(_))));
+        assert_eq!(relation.num_tuples().unwrap(), 4);
+    }
+
     #[test]
     fn test_delete_tuple() {
         let mut relation = setup_relation();
@@ -266,6 +293,78 @@ mod tests {
         assert_eq!(relation.columns["name"][0], DataType::String("Alice".to_string()));
     }
 
+    #[test]
+    fn test_delete_tuple_many_rows() {
+        let records = (0..20_000).map(|i| vec![DataType::Int(i), DataType::String(format!("name{}", i)), DataType::Int(i * 2)]).collect();
+        let mut relation = ColumnStoreRelation::from_records("people", vec!["id", "name", "age"], records).unwrap();
+
+        // every other row goes, which used to be quadratic in the number of deleted rows
+        let delete_count = relation.delete_tuple("id", |value| matches!(value, DataType::Int(i) if i % 2 == 0)).unwrap();
+        assert_eq!(delete_count, 10_000);
+        assert_eq!(relation.num_tuples().unwrap(), 10_000);
+
+        for row in 0..10_000 {
+            let id = 2 * row as i32 + 1;
+            assert_eq!(relation.columns["id"][row], DataType::Int(id));
+            assert_eq!(relation.columns["name"][row], DataType::String(format!("name{}", id)));
+            assert_eq!(relation.columns["age"][row], DataType::Int(id * 2));
+        }
+    }
+
+    #[test]
+    fn test_validate_column_lengths() {
+        let mut relation = setup_relation();
+        assert_eq!(relation.num_tuples().unwrap(), 2);
+        assert!(relation.validate_column_lengths().is_ok());
+        assert!(ColumnStoreRelation::new().validate_column_lengths().is_ok());
+
+        // a column falling behind is reported, the count follows the longest column
+        relation.columns.get_mut("id").unwrap().push(DataType::Int(3));
+        relation.columns.get_mut("name").unwrap().push(DataType::String("Carol".to_string()));
+        assert_eq!(relation.num_tuples().unwrap(), 3);
+        assert!(matches!(
+            relation.validate_column_lengths(),
+            Err(RelationErrors::ColumnLengthMismatch(column, 2, 3)) if column == "age"
+        ));
+    }
+
+    #[test]
+    fn test_bool_column() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,active\n1,true\n2,FALSE\n3, True").unwrap();
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(file.path().to_str().unwrap(), "users", ",", vec!["id", "active"]).unwrap();
+
+        assert_eq!(relation.columns["active"], vec![DataType::Bool(true), DataType::Bool(false), DataType::Bool(true)]);
+        assert_eq!(relation.fields["active"], DataType::Bool(false));
+        assert!(relation.schema_json().contains(r#"{"name": "active", "type": "Boolean"}"#));
+
+        let active = relation.select("active", |value| *value == DataType::Bool(true)).unwrap();
+        assert_eq!(active.columns["id"], vec![DataType::Int(1), DataType::Int(3)]);
+
+        // saved as true/false and loaded back as booleans
+        let output_file = NamedTempFile::new().unwrap();
+        let output_path = output_file.path().to_str().unwrap();
+        relation.save(output_path).unwrap();
+        assert!(std::fs::read_to_string(output_path).unwrap().contains("2,false"));
+        let mut reloaded = ColumnStoreRelation::new();
+        reloaded.load_csv(output_path, "users", ",", vec!["id", "active"]).unwrap();
+        assert_eq!(reloaded.columns["active"], relation.columns["active"]);
+
+        let bytes = serialize_data_types(&relation.columns["active"]).unwrap();
+        assert_eq!(deserialize_data_types(&bytes).unwrap(), relation.columns["active"]);
+
+        // ints only become booleans when asked for
+        assert_eq!(DataType::from_str("1"), DataType::Int(1));
+        assert_eq!(DataType::Int(1).cast(&DataType::Bool(false), FloatToIntMode::Truncate).unwrap(), DataType::Bool(true));
+        assert!(DataType::Int(2).cast(&DataType::Bool(false), FloatToIntMode::Truncate).is_err());
+        assert_eq!(DataType::Bool(true).cast(&DataType::Int(0), FloatToIntMode::Truncate).unwrap(), DataType::Int(1));
+
+        relation.sort("active", Order::Asc).unwrap();
+        assert_eq!(relation.columns["active"], vec![DataType::Bool(false), DataType::Bool(true), DataType::Bool(true)]);
+        assert_eq!(relation.columns["id"][0], DataType::Int(2));
+    }
+
     #[test]
     fn test_csv_load_save_cycle() {
         // Load data from the temporary file
@@ -298,5 +397,491 @@ mod tests {
         assert_eq!(results[2], vec!["3", "Name3", "3.33"]);
     }
 
-    
-}
\ No newline at end of file
+    #[test]
+    fn test_save_float_format() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,value\n1,3.33\n2,1.0").unwrap();
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(file.path().to_str().unwrap(), "values", ",", vec!["id", "value"]).unwrap();
+
+        let output_file = NamedTempFile::new().unwrap();
+        let output_path = output_file.path().to_str().unwrap();
+        let options = SaveOptions { float_format: FloatFormat::RoundTrip, ..SaveOptions::default() };
+        relation.save_with_options(output_path, &options).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), "id,value\n1,3.33\n2,1.0\n");
+
+        // the saved file loads back with the same values and types
+        let mut reloaded = ColumnStoreRelation::new();
+        reloaded.load_csv(output_path, "values", ",", vec!["id", "value"]).unwrap();
+        assert_eq!(reloaded.columns["value"], vec![DataType::Float(3.33), DataType::Float(1.0)]);
+
+        let options = SaveOptions { float_format: FloatFormat::Fixed(3), ..SaveOptions::default() };
+        relation.save_with_options(output_path, &options).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), "id,value\n1,3.330\n2,1.000\n");
+    }
+
+    #[test]
+    fn test_saved_floats_match_printed() {
+        let relation = ColumnStoreRelation::from_records(
+            "values",
+            vec!["id", "value"],
+            vec![
+                vec![DataType::Int(1), DataType::Float(1.23456)],
+                vec![DataType::Int(2), DataType::Float(2.0)],
+            ],
+        ).unwrap();
+
+        // saving with the print format writes the values exactly as the table shows them
+        let options = SaveOptions { float_format: PRINT_FLOAT_FORMAT, ..SaveOptions::default() };
+        let csv = relation.to_csv_string(&options).unwrap();
+        assert_eq!(csv, "id,value\n1,1.23\n2,2.00\n");
+        let printed = relation.format_plain();
+        assert_eq!(printed, "id  value\n1   1.23\n2   2.00\n");
+
+        // the default keeps the full precision, the same as save
+        let output_file = NamedTempFile::new().unwrap();
+        let output_path = output_file.path().to_str().unwrap();
+        relation.save(output_path).unwrap();
+        let saved = std::fs::read_to_string(output_path).unwrap();
+        assert_eq!(saved, relation.to_csv_string(&SaveOptions::default()).unwrap());
+        assert_eq!(saved, "id,value\n1,1.23456\n2,2\n");
+    }
+
+    #[test]
+    fn test_save_quote_style() {
+        let relation = ColumnStoreRelation::from_records(
+            "people",
+            vec!["id", "name"],
+            vec![vec![DataType::Int(1), DataType::String("Alice".to_string())]],
+        ).unwrap();
+        let output_file = NamedTempFile::new().unwrap();
+        let output_path = output_file.path().to_str().unwrap();
+
+        let options = SaveOptions { quote_style: QuoteStyle::Always, ..SaveOptions::default() };
+        relation.save_with_options(output_path, &options).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), "\"id\",\"name\"\n\"1\",\"Alice\"\n");
+
+        let options = SaveOptions { quote_style: QuoteStyle::NonNumeric, ..SaveOptions::default() };
+        relation.save_with_options(output_path, &options).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), "\"id\",\"name\"\n1,\"Alice\"\n");
+    }
+
+    #[test]
+    fn test_cast_float_to_int_modes() {
+        let target = DataType::Int(0);
+
+        assert_eq!(DataType::Float(2.7).cast(&target, FloatToIntMode::Truncate).unwrap(), DataType::Int(2));
+        assert_eq!(DataType::Float(2.7).cast(&target, FloatToIntMode::Round).unwrap(), DataType::Int(3));
+        assert!(DataType::Float(2.7).cast(&target, FloatToIntMode::ErrorOnFractional).is_err());
+
+        assert_eq!(DataType::Float(2.0).cast(&target, FloatToIntMode::Truncate).unwrap(), DataType::Int(2));
+        assert_eq!(DataType::Float(2.0).cast(&target, FloatToIntMode::Round).unwrap(), DataType::Int(2));
+        assert_eq!(DataType::Float(2.0).cast(&target, FloatToIntMode::ErrorOnFractional).unwrap(), DataType::Int(2));
+    }
+
+    #[test]
+    fn test_parse_as() {
+        assert!(matches!(DataType::parse_as("abc", &DataType::Int(0)), Err(RelationErrors::InvalidInput(_))));
+        assert!(DataType::parse_as("4.5", &DataType::Int(0)).is_err());
+        assert_eq!(DataType::parse_as(" 7 ", &DataType::Int(0)).unwrap(), DataType::Int(7));
+        assert_eq!(DataType::parse_as("42", &DataType::Float(0.0)).unwrap(), DataType::Float(42.0));
+        assert_eq!(DataType::parse_as("0042", &DataType::String(String::new())).unwrap(), DataType::String("0042".to_string()));
+        assert_eq!(DataType::parse_as("TRUE", &DataType::Bool(false)).unwrap(), DataType::Bool(true));
+        assert_eq!(DataType::parse_as("0", &DataType::Bool(false)).unwrap(), DataType::Bool(false));
+        assert!(DataType::parse_as("yes", &DataType::Bool(false)).is_err());
+        assert!(DataType::parse_as("", &DataType::Null).is_err());
+    }
+
+    #[test]
+    fn test_cast_column() {
+        let mut relation = setup_relation();
+        relation.columns.insert("age".to_string(), vec![DataType::Float(30.0), DataType::Float(25.5)]);
+
+        // a fractional value aborts the cast without modifying the column
+        assert!(relation.cast_column("age", &DataType::Int(0), FloatToIntMode::ErrorOnFractional).is_err());
+        assert_eq!(relation.columns["age"], vec![DataType::Float(30.0), DataType::Float(25.5)]);
+
+        relation.create_index("age").unwrap();
+        relation.cast_column("age", &DataType::Int(0), FloatToIntMode::Round).unwrap();
+        assert_eq!(relation.columns["age"], vec![DataType::Int(30), DataType::Int(26)]);
+        assert_eq!(relation.fields["age"], DataType::Int(0));
+
+        // the index is rebuilt from the casted values
+        let selected = relation.index_select("age", |age| *age == DataType::Int(26)).unwrap();
+        assert_eq!(selected.columns["age"], vec![DataType::Int(26)]);
+    }
+
+    #[test]
+    fn test_promote_numeric() {
+        let mut relation = ColumnStoreRelation::from_records("items", vec!["count", "price", "name"], vec![
+            vec![DataType::Int(2), DataType::Float(1.5), DataType::String("apple".to_string())],
+            vec![DataType::Null, DataType::Int(3), DataType::String("pear".to_string())],
+        ]).unwrap();
+
+        relation.create_index("price").unwrap();
+        relation.promote_numeric(DataType::Float(0.0)).unwrap();
+        assert_eq!(relation.columns["count"], vec![DataType::Float(2.0), DataType::Null]);
+        let selected = relation.index_select("price", |price| *price == DataType::Float(3.0)).unwrap();
+        assert_eq!(selected.columns["name"], vec![DataType::String("pear".to_string())]);
+        assert_eq!(relation.columns["price"], vec![DataType::Float(1.5), DataType::Float(3.0)]);
+        assert_eq!(relation.columns["name"], vec![DataType::String("apple".to_string()), DataType::String("pear".to_string())]);
+
+        // 1.5 is not an integer, nothing is cast
+        assert!(relation.promote_numeric(DataType::Int(0)).is_err());
+        assert_eq!(relation.columns["count"], vec![DataType::Float(2.0), DataType::Null]);
+        assert!(relation.promote_numeric(DataType::String(String::new())).is_err());
+    }
+
+    #[test]
+    fn test_conform_to_schema() {
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv("test.csv", "students", ",", vec!["Number", "Name", "Grade"]).unwrap();
+
+        // the relation is missing a column, a strict conform fails without changes
+        let schema = vec![("Name", DataType::String(String::new())), ("Number", DataType::Float(0.0)), ("Grade", DataType::Float(0.0)), ("Year", DataType::Int(0))];
+        assert!(relation.conform_to_schema(schema.clone(), true).is_err());
+        assert_eq!(relation.select_columns, vec!["Number", "Name", "Grade"]);
+
+        relation.conform_to_schema(schema, false).unwrap();
+        assert_eq!(relation.select_columns, vec!["Name", "Number", "Grade", "Year"]);
+        assert_eq!(relation.columns["Number"], vec![DataType::Float(0.0), DataType::Float(1.0), DataType::Float(3.0)]);
+        assert_eq!(relation.columns["Year"], vec![DataType::Null; 3]);
+        assert_eq!(relation.fields["Number"], DataType::Float(0.0));
+
+        // extra columns are dropped unless the conform is strict
+        let schema = vec![("Name", DataType::String(String::new())), ("Grade", DataType::Float(0.0))];
+        assert!(relation.conform_to_schema(schema.clone(), true).is_err());
+        relation.conform_to_schema(schema, false).unwrap();
+        assert_eq!(relation.select_columns, vec!["Name", "Grade"]);
+        assert!(!relation.columns.contains_key("Number"));
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let relation = setup_relation();
+        let mut other = setup_relation();
+        assert!(relation.schema_compatible(&other));
+        assert!(relation.schema_diff(&other).is_empty());
+
+        other.fields.insert("age".to_string(), DataType::Float(0.0));
+        assert!(!relation.schema_compatible(&other));
+        assert_eq!(relation.schema_diff(&other), vec!["column 'age': Integer vs Float".to_string()]);
+    }
+
+    #[test]
+    fn test_add_row_id() {
+        let mut relation = setup_relation();
+        relation.add_row_id("__rowid__").unwrap();
+
+        assert_eq!(relation.columns["__rowid__"], vec![DataType::Int(0), DataType::Int(1)]);
+        assert_eq!(relation.select_columns[0], "__rowid__");
+        assert_eq!(relation.select_columns.len(), 4);
+
+        // the column name has to be new
+        assert!(relation.add_row_id("name").is_err());
+    }
+
+    #[test]
+    fn test_insert_column_at() {
+        let mut relation = setup_relation();
+        relation.insert_column_at(0, "rank", vec![DataType::Int(2), DataType::Int(1)]).unwrap();
+
+        assert_eq!(relation.select_columns[0], "rank");
+        assert_eq!(relation.select_columns.len(), 4);
+        assert_eq!(relation.columns["rank"], vec![DataType::Int(2), DataType::Int(1)]);
+        assert_eq!(relation.fields["rank"], DataType::Int(0));
+
+        // wrong length, position out of range and an existing name are rejected
+        assert!(relation.insert_column_at(1, "score", vec![DataType::Int(1)]).is_err());
+        assert!(relation.insert_column_at(5, "score", vec![DataType::Int(1), DataType::Int(2)]).is_err());
+        assert!(relation.insert_column_at(1, "rank", vec![DataType::Int(1), DataType::Int(2)]).is_err());
+        assert_eq!(relation.select_columns.len(), 4);
+    }
+
+    #[test]
+    fn test_split_column() {
+        let mut relation = ColumnStoreRelation::from_records(
+            "people",
+            vec!["id", "full_name"],
+            vec![
+                vec![DataType::Int(1), DataType::String("Doe, John".to_string())],
+                vec![DataType::Int(2), DataType::String("Prince".to_string())],
+                vec![DataType::Int(3), DataType::Null],
+            ],
+        ).unwrap();
+
+        let mut kept = relation.clone();
+        relation.split_column("full_name", ",", vec!["last", "first"], false).unwrap();
+        assert_eq!(relation.select_columns, vec!["id", "last", "first"]);
+        assert!(!relation.columns.contains_key("full_name"));
+        assert_eq!(relation.columns["last"], vec![
+            DataType::String("Doe".to_string()), DataType::String("Prince".to_string()), DataType::Null
+        ]);
+        assert_eq!(relation.columns["first"], vec![DataType::String("John".to_string()), DataType::Null, DataType::Null]);
+
+        kept.split_column("full_name", ",", vec!["last", "first"], true).unwrap();
+        assert_eq!(kept.select_columns, vec!["id", "full_name", "last", "first"]);
+
+        assert!(kept.split_column("full_name", ",", vec!["last"], true).is_err());
+        assert!(kept.split_column("full_name", "", vec!["a"], true).is_err());
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let records = vec![
+            vec![DataType::Int(1), DataType::String("Alice".to_string())],
+            vec![DataType::Int(2), DataType::String("Bob".to_string())],
+        ];
+
+        let relation = ColumnStoreRelation::from_records("people", vec!["id", "name"], records.clone()).unwrap();
+        assert_eq!(relation.columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(relation.to_records(), records);
+
+        // every record has to match the columns
+        assert!(ColumnStoreRelation::from_records("people", vec!["id", "name"], vec![vec![DataType::Int(1)]]).is_err());
+    }
+
+    #[test]
+    fn test_load_special_floats() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,value\n1,2.5\n2,nan\n3,inf\n4,-1.0").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        // keep loads them as floats, sorting places NaN last in either order
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv_with_options(path, "values", ",", vec!["id", "value"], &LoadOptions::default()).unwrap();
+        assert!(matches!(relation.columns["value"][1], DataType::Float(f) if f.is_nan()));
+        assert_eq!(relation.columns["value"][2], DataType::Float(f64::INFINITY));
+
+        relation.sort("value", Order::Asc).unwrap();
+        assert_eq!(relation.columns["id"], vec![DataType::Int(4), DataType::Int(1), DataType::Int(3), DataType::Int(2)]);
+        relation.sort("value", Order::Desc).unwrap();
+        assert_eq!(relation.columns["id"], vec![DataType::Int(3), DataType::Int(1), DataType::Int(4), DataType::Int(2)]);
+
+        // nullify replaces them with nulls
+        let options = LoadOptions { special_floats: SpecialFloatPolicy::Nullify, ..LoadOptions::default() };
+        relation.load_csv_with_options(path, "values", ",", vec!["id", "value"], &options).unwrap();
+        assert_eq!(relation.columns["value"], vec![DataType::Float(2.5), DataType::Null, DataType::Null, DataType::Float(-1.0)]);
+
+        // reject fails the load
+        let options = LoadOptions { special_floats: SpecialFloatPolicy::Reject, ..LoadOptions::default() };
+        assert!(matches!(
+            relation.load_csv_with_options(path, "values", ",", vec!["id", "value"], &options),
+            Err(RelationErrors::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_delimiter() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id;name\n1;Alice").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(path, "people", ";", vec!["id", "name"]).unwrap();
+        assert_eq!(relation.columns["name"], vec![DataType::String("Alice".to_string())]);
+
+        // saved tab separated and loaded back with the same delimiter
+        let output_file = NamedTempFile::new().unwrap();
+        let output_path = output_file.path().to_str().unwrap();
+        let options = SaveOptions { delimiter: b'\t', ..SaveOptions::default() };
+        relation.save_with_options(output_path, &options).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), "id\tname\n1\tAlice\n");
+        let mut reloaded = ColumnStoreRelation::new();
+        reloaded.load_csv(output_path, "people", "\t", vec!["id", "name"]).unwrap();
+        assert_eq!(reloaded.columns, relation.columns);
+
+        // a multi-byte delimiter is rejected instead of splitting on its first byte
+        let result = relation.load_csv(path, "people", "\u{ff1b}", vec!["id", "name"]);
+        assert!(matches!(result, Err(RelationErrors::InvalidInput(msg)) if msg == "delimiter must be a single ASCII byte"));
+    }
+
+    #[test]
+    fn test_sniff_delimiter() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id;name;note\n1;Alice;a, b\n2;Bob;c").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        assert_eq!(ColumnStoreRelation::sniff_delimiter(path).unwrap(), b';');
+        assert_eq!(ColumnStoreRelation::sniff_delimiter("test.csv").unwrap(), b',');
+
+        // an empty delimiter is detected when loading
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(path, "people", "", vec!["id", "name", "note"]).unwrap();
+        assert_eq!(relation.columns["note"], vec![DataType::String("a, b".to_string()), DataType::String("c".to_string())]);
+    }
+
+    #[test]
+    fn test_load_enforce_homogeneous() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,zip,note\n1,12345,\n2,1234x,a\n3,54321,b").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        // without the flag the mixed column is loaded as it is
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(path, "addresses", ",", vec!["id", "zip", "note"]).unwrap();
+        assert_eq!(relation.columns["zip"][1], DataType::String("1234x".to_string()));
+
+        let options = LoadOptions { enforce_homogeneous: true, ..LoadOptions::default() };
+        let result = relation.load_csv_with_options(path, "addresses", ",", vec!["id", "zip", "note"], &options);
+        assert!(matches!(result, Err(RelationErrors::InvalidInput(message)) if message.contains("zip")));
+
+        // empty cells do not count as another type
+        assert!(relation.load_csv_with_options(path, "addresses", ",", vec!["id", "note"], &options).is_ok());
+    }
+
+    #[test]
+    fn test_load_report() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,name,score,unused\n1,Alice,,\n2,,nan,x\n3,Carol,2.5,").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let options = LoadOptions { special_floats: SpecialFloatPolicy::Nullify, ..LoadOptions::default() };
+        let mut relation = ColumnStoreRelation::new();
+        let report = relation.load_csv_with_options(path, "people", ",", vec!["id", "name", "score"], &options).unwrap();
+
+        assert_eq!(report.rows_loaded, 3);
+        assert_eq!(report.null_counts.len(), 3);
+        assert_eq!(report.null_counts["id"], 0);
+        assert_eq!(report.null_counts["name"], 1);
+        assert_eq!(report.null_counts["score"], 2);
+    }
+
+    #[test]
+    fn test_empty_cells_load_as_null() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,name,score\n1,Alice,\n2,,3.5\n3,Carol,1").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(path, "people", ",", vec!["id", "name", "score"]).unwrap();
+        assert_eq!(relation.columns["name"][1], DataType::Null);
+        assert_eq!(relation.columns["score"], vec![DataType::Null, DataType::Float(3.5), DataType::Int(1)]);
+        assert_eq!(relation.fields["score"], DataType::Float(0.0));
+
+        // nulls are skipped by the aggregations but counted by Count
+        assert_eq!(relation.aggr("score", Aggregation::Sum).unwrap(), DataType::Float(4.5));
+        assert_eq!(relation.aggr("score", Aggregation::Average).unwrap(), DataType::Float(2.25));
+        assert_eq!(relation.aggr("score", Aggregation::Min).unwrap(), DataType::Int(1));
+        assert_eq!(relation.aggr("score", Aggregation::Count).unwrap(), DataType::Int(3));
+
+        assert!(relation.format_plain().contains("2   NULL   3.50"));
+
+        // saved null cells are empty again
+        let output_file = NamedTempFile::new().unwrap();
+        let output_path = output_file.path().to_str().unwrap();
+        relation.save(output_path).unwrap();
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), "id,name,score\n1,Alice,\n2,,3.5\n3,Carol,1\n");
+    }
+
+    #[test]
+    fn test_schema_json() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "name,id,score,note\nAlice,1,2,\nBob,2,3.5,x").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(path, "people", ",", vec!["id", "name", "score", "note"]).unwrap();
+
+        assert_eq!(relation.fields["score"], DataType::Float(0.0));
+        assert_eq!(relation.schema_json(), concat!(
+            r#"{"name": "people", "columns": ["#,
+            r#"{"name": "id", "type": "Integer"}, {"name": "name", "type": "String"}, "#,
+            r#"{"name": "score", "type": "Float"}, {"name": "note", "type": "String"}]}"#
+        ));
+    }
+
+    #[test]
+    fn test_load_skip_rows_and_comments() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "exported by tool x\nversion 2\n# header follows\nid,name\n1,Alice\n# removed row\n2,Bob").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let options = LoadOptions { skip_rows: 2, comment_char: Some(b'#'), ..LoadOptions::default() };
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv_with_options(path, "people", ",", vec!["id", "name"], &options).unwrap();
+
+        assert_eq!(relation.columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(relation.columns["name"], vec![DataType::String("Alice".to_string()), DataType::String("Bob".to_string())]);
+    }
+
+    #[test]
+    fn test_load_without_trim() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id,name\n 1 ,  Alice  \n2,Bob").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut relation = ColumnStoreRelation::new();
+        relation.load_csv(path, "people", ",", vec!["id", "name"]).unwrap();
+        assert_eq!(relation.columns["name"][0], DataType::String("Alice".to_string()));
+
+        // strings keep their padding, numbers are still parsed
+        let options = LoadOptions { trim: false, ..LoadOptions::default() };
+        relation.load_csv_with_options(path, "people", ",", vec!["id", "name"], &options).unwrap();
+        assert_eq!(relation.columns["id"], vec![DataType::Int(1), DataType::Int(2)]);
+        assert_eq!(relation.columns["name"], vec![DataType::String("  Alice  ".to_string()), DataType::String("Bob".to_string())]);
+    }
+
+    #[test]
+    fn test_delete_rows() {
+        let mut relation = ColumnStoreRelation::from_records(
+            "people",
+            vec!["id", "name"],
+            (0..6).map(|i| vec![DataType::Int(i), DataType::String(format!("name{}", i))]).collect(),
+        ).unwrap();
+        relation.create_index("id").unwrap();
+
+        // duplicates and unordered positions are fine
+        assert_eq!(relation.delete_rows(&[4, 1, 4, 0]).unwrap(), 3);
+        assert_eq!(relation.columns["id"], vec![DataType::Int(2), DataType::Int(3), DataType::Int(5)]);
+        assert_eq!(relation.columns["name"], vec![
+            DataType::String("name2".to_string()), DataType::String("name3".to_string()), DataType::String("name5".to_string())
+        ]);
+
+        // the index points at the new row positions
+        let selected = relation.index_select("id", |d| *d == DataType::Int(5)).unwrap();
+        assert_eq!(selected.columns["name"], vec![DataType::String("name5".to_string())]);
+
+        // out of range positions are rejected without deleting anything
+        assert!(relation.delete_rows(&[0, 3]).is_err());
+        assert_eq!(relation.num_tuples().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_reload_csv_rebuilds_indices() {
+        let mut tbl = ColumnStoreRelation::from_records("old", vec!["Number", "Grade"], vec![
+            vec![DataType::Int(3), DataType::Float(1.0)],
+        ]).unwrap();
+        tbl.create_index("Number").unwrap();
+        tbl.create_index_with("Grade", IndexKind::Compact).unwrap();
+        tbl.create_composite_index(vec!["Number", "Grade"]).unwrap();
+
+        // indices of reloaded columns point to the new rows, the ones of columns not loaded are dropped
+        tbl.load_csv("test.csv", "students", ",", vec!["Number", "Name"]).unwrap();
+        let selected = tbl.index_select("Number", |number| *number == DataType::Int(3)).unwrap();
+        assert_eq!(selected.columns["Name"], vec![DataType::String("Name3".to_string())]);
+        assert!(tbl.compact_indices.is_empty() && tbl.composite_indices.is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_load_csv_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        // like `load_csv`, sort flags are dropped and indices are rebuilt from the loaded rows
+        let mut tbl = ColumnStoreRelation::from_records("old", vec!["Number"], vec![vec![DataType::Int(7)]]).unwrap();
+        tbl.create_index("Number").unwrap();
+        tbl.sorted_columns.insert("Number".to_string());
+        let result = runtime.block_on(tbl.load_csv_async("test.csv", "students", ",", vec!["Number", "Name", "Grade"]));
+
+        assert!(result.is_ok());
+        assert_eq!(tbl.num_tuples().unwrap(), 3);
+        assert_eq!(tbl.get_table_name(), "students");
+        assert_eq!(tbl.fields["Number"], DataType::Int(0));
+        assert!(tbl.sorted_columns.is_empty());
+        let selected = tbl.index_select("Number", |number| *number == DataType::Int(3)).unwrap();
+        assert_eq!(selected.columns["Name"], vec![DataType::String("Name3".to_string())]);
+
+        assert!(runtime.block_on(tbl.load_csv_async("missing.csv", "students", ",", vec!["Number"])).is_err());
+    }
+}